@@ -0,0 +1,84 @@
+// `cargo bench`: times drawing a made up chat in memory. the first frame draws
+// every message, after that a frame should only cost what is on screen.
+// BENCH_MESSAGES sets how many messages there are.
+use anyhow::{Context, Result};
+use llm_tui_rs::chat_store::ChatStore;
+use llm_tui_rs::chat_structs::{Assistant, Message, Role};
+use llm_tui_rs::ui::ChatView;
+use ratatui::{buffer::Buffer, layout::Rect, widgets::Widget};
+use std::env;
+use std::fs;
+use std::time::Instant;
+
+const FRAMES: u32 = 100;
+
+fn main() -> Result<()> {
+    let count = match env::var("BENCH_MESSAGES") {
+        Ok(n) => n.parse().context("BENCH_MESSAGES has to be a number")?,
+        Err(_) => 10_000,
+    };
+    let dir = env::temp_dir().join(format!("llm-tui-rs-bench-{}", std::process::id()));
+    let result = bench(count, ChatStore::open(dir.clone())?);
+    fs::remove_dir_all(&dir).ok();
+    result
+}
+
+fn bench(count: usize, store: ChatStore) -> Result<()> {
+    let mut chat = ChatView::open(store)?;
+    chat.messages = Some((0..count).map(sample_message).collect());
+    let mut area = Rect::new(0, 0, 120, 40);
+    println!("{count} messages, {}x{}", area.width, area.height);
+
+    time("first frame", 1, || draw(&chat, area));
+    time("idle at the bottom", FRAMES, || draw(&chat, area));
+    chat.follow = false;
+    chat.scroll = chat.message_row(count / 2);
+    time("idle in the middle", FRAMES, || draw(&chat, area));
+    chat.follow = true;
+    let last = count.saturating_sub(1);
+    time("reply streaming in", FRAMES, || {
+        if let Some(message) = chat.messages.as_mut().and_then(|m| m.get_mut(last)) {
+            message.content.push_str(" more");
+        }
+        chat.message_changed(last);
+        draw(&chat, area);
+    });
+    area.width -= 20;
+    time("after a resize", 1, || draw(&chat, area));
+    Ok(())
+}
+
+fn draw(chat: &ChatView, area: Rect) {
+    let mut buf = Buffer::empty(area);
+    chat.render(area, &mut buf);
+}
+
+fn time(what: &str, frames: u32, mut frame: impl FnMut()) {
+    let started = Instant::now();
+    for _ in 0..frames {
+        frame();
+    }
+    println!("  {what:<20} {:>10.2?} a frame", started.elapsed() / frames);
+}
+
+// prose, a list and now and then some code, like real replies
+fn sample_message(i: usize) -> Message {
+    if i.is_multiple_of(2) {
+        return Message::new(
+            Role::User,
+            format!("question {i}: how do I read a file line by line?"),
+        );
+    }
+    let mut content = format!(
+        "Here is **answer {i}**. You open the file, wrap it in a `BufReader` and go \
+         through `lines()`, which hands out one line at a time without reading the \
+         whole file first.\n\n- it stops at the end of the file\n- every line is a \
+         `Result`\n"
+    );
+    if i.is_multiple_of(3) {
+        content.push_str(
+            "\n```rust\nlet file = File::open(path)?;\nfor line in BufReader::new(file).lines() {\n    println!(\"{}\", line?);\n}\n```\n",
+        );
+    }
+    Message::new(Role::Assistant(Assistant::new("gpt-4o")), content)
+}
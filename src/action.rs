@@ -0,0 +1,266 @@
+// everything a user can do that isn't typing, so the palette can list it and
+// keymap.rs can bind keys to it. ui/actions.rs runs them.
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum Action {
+    // anywhere
+    Palette,
+    OpenChats,
+    OpenSearch,
+    OpenSettings,
+    EditSystemPrompt,
+    MainMenu,
+    Quit,
+    ToggleMouse,
+    // in the chat view
+    ToggleSidebar,
+    ToggleTimestamps,
+    EditPrompt,
+    EditMessage,
+    HistorySearch,
+    ScrollUp,
+    ScrollDown,
+    ScrollTop,
+    ScrollBottom,
+    ScrollLeft,
+    ScrollRight,
+    FindInChat,
+    FindNext,
+    FindPrevious,
+    // on the message selected with SelectMessages, or the last one
+    SelectMessages,
+    CopyMessage,
+    CopyCodeBlock,
+    CopyChat,
+    DeleteMessage,
+    RegenerateMessage,
+    ForkFromMessage,
+    BookmarkMessage,
+    ViewRawMessage,
+    ToggleCollapse,
+    ToggleCodeCollapse,
+    NextBookmark,
+    PreviousBookmark,
+    // on the chat selected in the sidebar
+    NewChat,
+    RenameChat,
+    DeleteChat,
+    ArchiveChat,
+    PinChat,
+    MoveToFolder,
+    TagChat,
+    ExportChat,
+    // on the sidebar
+    FilterByTag,
+    ImportChats,
+    SortByActivity,
+    ShowArchived,
+    ShowTrash,
+    Undo,
+    // moving around the sidebar or the messages, not worth listing in the palette
+    SelectNext,
+    SelectPrevious,
+    SelectFirst,
+    SelectLast,
+    MoveChatDown,
+    MoveChatUp,
+}
+
+impl Action {
+    pub const ALL: [Action; 55] = [
+        Action::Palette,
+        Action::OpenChats,
+        Action::OpenSearch,
+        Action::OpenSettings,
+        Action::EditSystemPrompt,
+        Action::MainMenu,
+        Action::Quit,
+        Action::ToggleMouse,
+        Action::ToggleSidebar,
+        Action::ToggleTimestamps,
+        Action::EditPrompt,
+        Action::EditMessage,
+        Action::HistorySearch,
+        Action::ScrollUp,
+        Action::ScrollDown,
+        Action::ScrollTop,
+        Action::ScrollBottom,
+        Action::ScrollLeft,
+        Action::ScrollRight,
+        Action::FindInChat,
+        Action::FindNext,
+        Action::FindPrevious,
+        Action::SelectMessages,
+        Action::CopyMessage,
+        Action::CopyCodeBlock,
+        Action::CopyChat,
+        Action::DeleteMessage,
+        Action::RegenerateMessage,
+        Action::ForkFromMessage,
+        Action::BookmarkMessage,
+        Action::ViewRawMessage,
+        Action::ToggleCollapse,
+        Action::ToggleCodeCollapse,
+        Action::NextBookmark,
+        Action::PreviousBookmark,
+        Action::NewChat,
+        Action::RenameChat,
+        Action::DeleteChat,
+        Action::ArchiveChat,
+        Action::PinChat,
+        Action::MoveToFolder,
+        Action::TagChat,
+        Action::ExportChat,
+        Action::FilterByTag,
+        Action::ImportChats,
+        Action::SortByActivity,
+        Action::ShowArchived,
+        Action::ShowTrash,
+        Action::Undo,
+        Action::SelectNext,
+        Action::SelectPrevious,
+        Action::SelectFirst,
+        Action::SelectLast,
+        Action::MoveChatDown,
+        Action::MoveChatUp,
+    ];
+
+    /// what keymap.json calls it
+    pub fn name(self) -> &'static str {
+        match self {
+            Action::Palette => "palette",
+            Action::OpenChats => "open_chats",
+            Action::OpenSearch => "open_search",
+            Action::OpenSettings => "open_settings",
+            Action::EditSystemPrompt => "edit_system_prompt",
+            Action::MainMenu => "main_menu",
+            Action::Quit => "quit",
+            Action::ToggleMouse => "toggle_mouse",
+            Action::ToggleSidebar => "toggle_sidebar",
+            Action::ToggleTimestamps => "toggle_timestamps",
+            Action::EditPrompt => "edit_prompt",
+            Action::EditMessage => "edit_message",
+            Action::HistorySearch => "history_search",
+            Action::ScrollUp => "scroll_up",
+            Action::ScrollDown => "scroll_down",
+            Action::ScrollTop => "scroll_top",
+            Action::ScrollBottom => "scroll_bottom",
+            Action::ScrollLeft => "scroll_left",
+            Action::ScrollRight => "scroll_right",
+            Action::FindInChat => "find_in_chat",
+            Action::FindNext => "find_next",
+            Action::FindPrevious => "find_previous",
+            Action::SelectMessages => "select_messages",
+            Action::CopyMessage => "copy_message",
+            Action::CopyCodeBlock => "copy_code_block",
+            Action::CopyChat => "copy_chat",
+            Action::DeleteMessage => "delete_message",
+            Action::RegenerateMessage => "regenerate_message",
+            Action::ForkFromMessage => "fork_from_message",
+            Action::BookmarkMessage => "bookmark_message",
+            Action::ViewRawMessage => "view_raw_message",
+            Action::ToggleCollapse => "toggle_collapse",
+            Action::ToggleCodeCollapse => "toggle_code_collapse",
+            Action::NextBookmark => "next_bookmark",
+            Action::PreviousBookmark => "previous_bookmark",
+            Action::NewChat => "new_chat",
+            Action::RenameChat => "rename_chat",
+            Action::DeleteChat => "delete_chat",
+            Action::ArchiveChat => "archive_chat",
+            Action::PinChat => "pin_chat",
+            Action::MoveToFolder => "move_to_folder",
+            Action::TagChat => "tag_chat",
+            Action::ExportChat => "export_chat",
+            Action::FilterByTag => "filter_by_tag",
+            Action::ImportChats => "import_chats",
+            Action::SortByActivity => "sort_by_activity",
+            Action::ShowArchived => "show_archived",
+            Action::ShowTrash => "show_trash",
+            Action::Undo => "undo",
+            Action::SelectNext => "select_next",
+            Action::SelectPrevious => "select_previous",
+            Action::SelectFirst => "select_first",
+            Action::SelectLast => "select_last",
+            Action::MoveChatDown => "move_chat_down",
+            Action::MoveChatUp => "move_chat_up",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<Self> {
+        Self::ALL.into_iter().find(|a| a.name() == name)
+    }
+
+    /// the palette leaves out opening itself and moving around the sidebar
+    pub fn in_palette(self) -> bool {
+        !matches!(
+            self,
+            Action::Palette
+                | Action::SelectNext
+                | Action::SelectPrevious
+                | Action::SelectFirst
+                | Action::SelectLast
+                | Action::MoveChatDown
+                | Action::MoveChatUp
+        )
+    }
+
+    pub fn label(self) -> &'static str {
+        match self {
+            Action::Palette => "Command palette",
+            Action::OpenChats => "Open chats",
+            Action::OpenSearch => "Search all chats",
+            Action::OpenSettings => "Settings",
+            Action::EditSystemPrompt => "Edit system prompt in $EDITOR",
+            Action::MainMenu => "Main menu",
+            Action::Quit => "Quit",
+            Action::ToggleMouse => "Turn the mouse on or off, off lets the terminal select text",
+            Action::ToggleSidebar => "Toggle sidebar",
+            Action::ToggleTimestamps => "Toggle message times and details",
+            Action::EditPrompt => "Write prompt in $EDITOR",
+            Action::EditMessage => "Edit message in $EDITOR",
+            Action::HistorySearch => "Search prompt history",
+            Action::ScrollUp => "Scroll messages up",
+            Action::ScrollDown => "Scroll messages down",
+            Action::ScrollTop => "Scroll to the first message",
+            Action::ScrollBottom => "Scroll to the newest message and follow new ones",
+            Action::ScrollLeft => "Scroll code blocks left",
+            Action::ScrollRight => "Scroll code blocks right",
+            Action::FindInChat => "Find in this chat",
+            Action::FindNext => "Next match in this chat",
+            Action::FindPrevious => "Previous match in this chat",
+            Action::SelectMessages => "Select messages",
+            Action::CopyMessage => "Copy message",
+            Action::CopyCodeBlock => "Copy a code block of the message",
+            Action::CopyChat => "Copy the chat as markdown",
+            Action::DeleteMessage => "Delete message",
+            Action::RegenerateMessage => "Regenerate reply",
+            Action::ForkFromMessage => "Fork chat from message",
+            Action::BookmarkMessage => "Bookmark / unbookmark message",
+            Action::ViewRawMessage => "View raw markdown",
+            Action::ToggleCollapse => "Collapse / expand message",
+            Action::ToggleCodeCollapse => "Collapse / expand a code block of the message",
+            Action::NextBookmark => "Next bookmark",
+            Action::PreviousBookmark => "Previous bookmark",
+            Action::NewChat => "New chat",
+            Action::RenameChat => "Rename chat",
+            Action::DeleteChat => "Delete chat",
+            Action::ArchiveChat => "Archive / unarchive chat",
+            Action::PinChat => "Pin / unpin chat",
+            Action::MoveToFolder => "Move chat to folder",
+            Action::TagChat => "Tag chat",
+            Action::ExportChat => "Export chat",
+            Action::FilterByTag => "Filter chats by tag",
+            Action::ImportChats => "Import ChatGPT / Open WebUI chats",
+            Action::SortByActivity => "Sort chats by activity",
+            Action::ShowArchived => "Show archived chats",
+            Action::ShowTrash => "Show trash",
+            Action::Undo => "Undo sidebar change",
+            Action::SelectNext => "Next chat or message",
+            Action::SelectPrevious => "Previous chat or message",
+            Action::SelectFirst => "First chat or message",
+            Action::SelectLast => "Last chat or message",
+            Action::MoveChatDown => "Move chat down",
+            Action::MoveChatUp => "Move chat up",
+        }
+    }
+}
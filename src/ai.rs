@@ -7,12 +7,13 @@
 // };
 // https://github.com/graniet/llm/blob/main/examples/multi_backend_example.rs
 use crate::ai_backend::{AIBackend, AISettings};
-use crate::chat_structs::{Message, Role};
+use crate::chat_structs::{Assistant, Generation, Message, Role};
 use anyhow::Result;
 use llm::{
     builder::LLMBuilder, // Builder pattern components
     chat::ChatMessage,
 };
+use std::time::Instant;
 
 pub async fn run_ai(
     chat_history: Option<&[Message]>,
@@ -26,6 +27,9 @@ pub async fn run_ai(
         .temperature(settings.temperature)
         .max_tokens(u32::try_from(settings.max_tokens)?);
 
+    if !settings.system_prompt.trim().is_empty() {
+        builder = builder.system(&settings.system_prompt);
+    }
     if let Some(key) = &settings.api_key {
         builder = builder.api_key(key);
     } else {
@@ -41,11 +45,7 @@ pub async fn run_ai(
 
     let llm = builder.build()?;
 
-    let mut messages = vec![
-        ChatMessage::user()
-            .content("You are a friendly chatbot.")
-            .build(),
-    ];
+    let mut messages = Vec::new();
     // for loop through the chat_history vec if it exists, chat_history[x].role => user() / assistant(), .message -> pass to .content()
 
     if let Some(history) = chat_history {
@@ -71,6 +71,43 @@ pub async fn run_ai(
         .map_err(Into::into)
 }
 
+/// the model's reply to `prompt` after `history` as a message, with how long it
+/// took and what it cost. an error is the reply then
+pub async fn reply(history: &[Message], prompt: &str, settings: &AISettings) -> Message {
+    let started = Instant::now();
+    let ai_reply = run_ai(Some(history), prompt, settings)
+        .await
+        .unwrap_or_else(|e| format!("AI error: {e}"));
+    let latency = u64::try_from(started.elapsed().as_millis()).unwrap_or(u64::MAX);
+    let generation = Generation::new(
+        settings,
+        latency,
+        prompt_tokens(history, prompt, settings),
+        estimate_tokens(&ai_reply),
+    );
+    let assistant = Assistant {
+        model: settings.model.clone(),
+        generation: Some(generation),
+    };
+    Message::new(Role::Assistant(assistant), ai_reply)
+}
+
+/// about how many tokens `text` is, four bytes a token is close enough for
+/// english and code
+pub fn estimate_tokens(text: &str) -> usize {
+    text.len().div_ceil(4)
+}
+
+/// the tokens sending `prompt` after `history` costs, system prompt included
+pub fn prompt_tokens(history: &[Message], prompt: &str, settings: &AISettings) -> usize {
+    estimate_tokens(&settings.system_prompt)
+        + history
+            .iter()
+            .map(|m| estimate_tokens(&m.content))
+            .sum::<usize>()
+        + estimate_tokens(prompt)
+}
+
 pub async fn generate_chat_title(
     chat_history: Option<&[Message]>,
     settings: &AISettings,
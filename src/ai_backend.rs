@@ -60,6 +60,29 @@ pub struct AISettings {
     pub api_key: Option<String>, // override
     pub temperature: f32,
     pub max_tokens: usize,
+    #[serde(default = "default_system_prompt")]
+    pub system_prompt: String,
+    // what the model costs, without it the status bar shows no cost
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub price: Option<Price>,
+}
+
+/// dollars per million tokens: `"price": { "input": 2.5, "output": 10.0 }`
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq)]
+pub struct Price {
+    pub input: f64,
+    pub output: f64,
+}
+
+impl Price {
+    #[allow(clippy::cast_precision_loss)]
+    pub fn cost(self, prompt_tokens: usize, reply_tokens: usize) -> f64 {
+        (prompt_tokens as f64 * self.input + reply_tokens as f64 * self.output) / 1_000_000.0
+    }
+}
+
+pub fn default_system_prompt() -> String {
+    "You are a friendly chatbot.".to_string()
 }
 
 // there has to be a better way to do this...
@@ -93,6 +116,24 @@ impl AISettings {
         Ok(items)
     }
 
+    /// settings.json, or something to start from when there is none yet
+    pub fn load_or_default() -> Self {
+        AISettings::load_all(Path::new("settings.json")).unwrap_or_else(|_| AISettings::fallback())
+    }
+
+    /// what is used until there is a settings.json
+    pub fn fallback() -> Self {
+        AISettings {
+            backend: AIBackend::OpenAI,
+            model: "gpt-3.5-turbo".to_string(),
+            api_key: None,
+            temperature: 0.4,
+            max_tokens: 2048,
+            system_prompt: default_system_prompt(),
+            price: None,
+        }
+    }
+
     pub fn write_all(path: &Path, list: &AISettings) -> anyhow::Result<()> {
         let s = serde_json::to_string_pretty(list)?;
         fs::write(path, s)?;
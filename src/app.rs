@@ -1,17 +1,20 @@
-use crate::ui::{ChatView, Config, MainMenu};
+use crate::ai_backend::AISettings;
+use crate::editor::EditTarget;
+use crate::keymap::{self, KeyPress, Resolved};
+use crate::ui::{ChatView, Config, MainMenu, Palette, SearchView};
 use crossterm::event::KeyEvent;
-use ratatui::{
-    buffer::Buffer,
-    layout::Rect,
-    widgets::{Paragraph, Widget},
-};
+use ratatui::{buffer::Buffer, layout::Rect, widgets::Widget};
 
 use anyhow::Result;
+use std::path::PathBuf;
 
 pub enum CurrentScreen {
     MainMenu(MainMenu),
-    ChatView(ChatView),
-    Settings(Config),
+    ChatView(Box<ChatView>),
+    Search(Box<SearchView>),
+    // drawn over the screen it was opened from, which it keeps
+    Palette(Box<Palette>),
+    Settings(Box<Config>),
     Exit(Exit),
 }
 
@@ -22,33 +25,124 @@ pub struct Exit {
 // -- Input Handling
 
 impl CurrentScreen {
-    pub async fn on_key(&mut self, key: KeyEvent) -> Result<()> {
+    /// `chord` is what was typed so far of a key chord, it is kept between keys
+    pub fn on_key(&mut self, key: KeyEvent, chord: &mut Vec<KeyPress>) -> Result<()> {
+        if let Some(context) = self.key_context() {
+            match keymap::get().resolve(context, chord, key) {
+                Resolved::Action(action) => {
+                    if let Some(chat) = self.chat_view_mut() {
+                        chat.notice = None;
+                    }
+                    return self.run_action(action);
+                }
+                Resolved::Waiting => return Ok(()),
+                Resolved::Unbound => {}
+            }
+        }
         match self {
             CurrentScreen::MainMenu(_) => self.handle_main_menu(key)?,
-            CurrentScreen::ChatView(_) => self.handle_chat_view(key).await?,
+            CurrentScreen::ChatView(_) => self.handle_chat_view(key)?,
+            CurrentScreen::Search(_) => self.handle_search(key)?,
+            CurrentScreen::Palette(_) => self.handle_palette(key),
             CurrentScreen::Settings(_) => self.handle_settings(key),
             CurrentScreen::Exit(_) => {}
         }
         Ok(())
     }
+
+    /// what the screen wants to write in $EDITOR, and the text to start with
+    pub fn take_edit(&mut self) -> Option<(EditTarget, String)> {
+        match self {
+            CurrentScreen::ChatView(chat) => {
+                let target = chat.edit_request.take()?;
+                Some((target, chat.edit_text(target)?))
+            }
+            CurrentScreen::Settings(config) => {
+                let target = config.edit_request.take()?;
+                Some((target, config.ai_settings.system_prompt.clone()))
+            }
+            _ => None,
+        }
+    }
+
+    /// hand back what the editor returned, `None` when it exited with an error
+    pub fn finish_edit(
+        &mut self,
+        target: EditTarget,
+        edited: Result<Option<String>>,
+    ) -> Result<()> {
+        match self {
+            CurrentScreen::ChatView(chat) => match edited {
+                Ok(Some(text)) => chat.apply_edit(target, text)?,
+                Ok(None) => {
+                    chat.notice = Some("The editor exited with an error, nothing changed".into());
+                }
+                Err(err) => chat.notice = Some(format!("{err:#}")),
+            },
+            CurrentScreen::Settings(config) => match edited {
+                Ok(Some(text)) => {
+                    config.ai_settings.system_prompt = text;
+                    match AISettings::write_all(
+                        &PathBuf::from("settings.json"),
+                        &config.ai_settings,
+                    ) {
+                        Ok(()) => config.notice = Some("Saved the system prompt".to_string()),
+                        Err(err) => {
+                            config.notice = Some(format!("Can't save the system prompt: {err:#}"));
+                        }
+                    }
+                }
+                Ok(None) => {
+                    config.notice = Some("The editor exited with an error, nothing changed".into());
+                }
+                Err(err) => config.notice = Some(format!("{err:#}")),
+            },
+            _ => {}
+        }
+        Ok(())
+    }
+
+    /// pasted text goes into the prompt as it is, line breaks included
+    pub fn on_paste(&mut self, text: &str) {
+        match self {
+            CurrentScreen::ChatView(chat) if !chat.show_sidebar => chat.input.insert_str(text),
+            CurrentScreen::Palette(palette) => {
+                palette
+                    .query
+                    .extend(text.chars().filter(|c| !c.is_control()));
+                palette.update_matches();
+            }
+            _ => {}
+        }
+    }
 }
 
 impl Widget for &CurrentScreen {
     fn render(self, area: Rect, buf: &mut Buffer) {
-        let header_area = Rect { height: 1, ..area };
-        Paragraph::new("llm-tui :3").render(header_area, buf);
+        self.render_screen(content_area(area), buf);
+        // after the screen, what it found while drawing goes in the bar
+        self.render_status_bar(Rect { height: 1, ..area }, buf);
+    }
+}
 
-        let content_area = Rect {
-            y: area.y + 1,
-            height: area.height - 1,
-            ..area
-        };
+/// where the screens go, under the header
+pub fn content_area(area: Rect) -> Rect {
+    Rect {
+        y: area.y + 1,
+        height: area.height.saturating_sub(1),
+        ..area
+    }
+}
 
-        // Then delegate to the specific screen
+impl CurrentScreen {
+    /// the screen without the header, the palette draws the one below it with this
+    pub fn render_screen(&self, area: Rect, buf: &mut Buffer) {
         match self {
-            CurrentScreen::MainMenu(screen) => screen.render(content_area, buf),
-            CurrentScreen::ChatView(screen) => screen.render(content_area, buf),
-            CurrentScreen::Settings(screen) => screen.render(content_area, buf),
+            CurrentScreen::MainMenu(screen) => screen.render(area, buf),
+            CurrentScreen::ChatView(screen) => screen.as_ref().render(area, buf),
+            CurrentScreen::Search(screen) => screen.as_ref().render(area, buf),
+            CurrentScreen::Palette(screen) => screen.as_ref().render(area, buf),
+            CurrentScreen::Settings(screen) => screen.render(area, buf),
             CurrentScreen::Exit(_) => (),
         }
     }
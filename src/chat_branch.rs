@@ -1,16 +1,59 @@
 use crate::chat_structs::Message;
 use anyhow::Result;
+use chrono::{DateTime, Utc};
 use serde::{Deserialize, Serialize};
 use std::{fs, io::Write, path::Path};
 
-#[derive(Serialize, Deserialize, Debug, Clone)]
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
 pub struct ChatBranch {
     pub id: usize,
     pub name: String,
+    // empty for index entries, the chat store keeps messages in their own files
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
     pub messages: Vec<Message>,
+    #[serde(default)]
+    pub message_count: usize,
+    // last time a message was added or changed
+    #[serde(default)]
+    pub updated_at: Option<DateTime<Utc>>,
+    // pinned chats are always kept at the front of the list
+    #[serde(default)]
+    pub pinned: bool,
+    #[serde(default)]
+    pub archived: bool,
+    // slash separated, empty means the top level
+    #[serde(default)]
+    pub folder: String,
+    #[serde(default)]
+    pub tags: Vec<String>,
 }
 
 impl ChatBranch {
+    pub fn new(id: usize, name: &str) -> Self {
+        ChatBranch {
+            id,
+            name: name.to_string(),
+            messages: Vec::new(),
+            message_count: 0,
+            updated_at: None,
+            pinned: false,
+            archived: false,
+            folder: String::new(),
+            tags: Vec::new(),
+        }
+    }
+
+    /// ids used to be list indices, now that chats can be deleted and reordered
+    /// they have to be unique across both the chat list and the trash
+    pub fn next_id(branches: &[ChatBranch], trash: &[ChatBranch]) -> usize {
+        branches
+            .iter()
+            .chain(trash)
+            .map(|b| b.id + 1)
+            .max()
+            .unwrap_or(0)
+    }
+
     pub fn load_all(path: &Path) -> Result<Vec<ChatBranch>> {
         if !path.exists() {
             // create empty file
@@ -21,10 +64,4 @@ impl ChatBranch {
         let branches = serde_json::from_str(&data)?;
         Ok(branches)
     }
-
-    pub fn save_all(path: &Path, branches: &Vec<ChatBranch>) -> Result<()> {
-        let s = serde_json::to_string_pretty(branches)?;
-        fs::write(path, s)?;
-        Ok(())
-    }
 }
@@ -0,0 +1,548 @@
+// chats on disk: a small index with one entry per chat and a file per chat with
+// its messages, so listing chats never has to read a single message.
+//
+//   chats/index.json   entries of every chat (no messages)
+//   chats/trash.json   entries of deleted chats, their messages stay until purged
+//   chats/<id>.json    the messages of one chat
+//   chats/.lock        held while writing, several instances can share the store
+//   chats/.version     bumped on every write so other instances notice
+//   chats/.next_id     the lowest id no instance has handed out yet
+//
+// every write reads the file again under the lock and merges in what other
+// instances changed since this one last looked, instead of overwriting it.
+use crate::chat_branch::ChatBranch;
+use crate::chat_structs::Message;
+use anyhow::{Context, Result};
+use chrono::Utc;
+use std::collections::HashMap;
+use std::fs::{self, File};
+use std::io::ErrorKind;
+use std::path::{Path, PathBuf};
+
+#[derive(Debug, Clone)]
+pub struct ChatStore {
+    pub dir: PathBuf,
+    // index and trash as this instance last read or wrote them, anything that
+    // differs from these on disk was changed by someone else
+    base_index: HashMap<usize, ChatBranch>,
+    base_trash: HashMap<usize, ChatBranch>,
+    seen: u64,
+}
+
+/// what happened to messages handed to `save_messages`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Saved {
+    Written,
+    // another instance added messages first, ours were appended after them
+    Merged,
+    // another instance rewrote the chat, nothing was written
+    Refused,
+}
+
+/// write to a temporary file first so a crash never leaves half a file behind
+fn write_atomic(path: &Path, data: &str) -> Result<()> {
+    let tmp = path.with_extension("json.tmp");
+    fs::write(&tmp, data)?;
+    fs::rename(&tmp, path)?;
+    Ok(())
+}
+
+fn read_if_there(path: &Path) -> Result<Option<String>> {
+    match fs::read_to_string(path) {
+        Ok(data) => Ok(Some(data)),
+        Err(err) if err.kind() == ErrorKind::NotFound => Ok(None),
+        Err(err) => Err(err.into()),
+    }
+}
+
+fn snapshot(list: &[ChatBranch]) -> HashMap<usize, ChatBranch> {
+    list.iter().map(|b| (b.id, b.clone())).collect()
+}
+
+/// `theirs` unless we changed the field since `base`
+fn merge_field<T: PartialEq>(ours: &mut T, base: &T, theirs: T) {
+    if *ours == *base {
+        *ours = theirs;
+    }
+}
+
+/// three way merge of index entries: `ours` is what this instance has, `base` what it
+/// last saw on disk and `theirs` what is on disk now. fields only we touched keep
+/// our value, everything else follows the disk, chats new on disk go at the end
+fn merge(ours: &mut Vec<ChatBranch>, base: &HashMap<usize, ChatBranch>, theirs: Vec<ChatBranch>) {
+    let mut by_id = snapshot(&theirs);
+    ours.retain_mut(|branch| {
+        let unchanged = base.get(&branch.id) == Some(branch);
+        match (by_id.remove(&branch.id), base.get(&branch.id)) {
+            (Some(disk), Some(base)) => {
+                // a rename here and a tag edit elsewhere both stay
+                merge_field(&mut branch.name, &base.name, disk.name);
+                merge_field(
+                    &mut branch.message_count,
+                    &base.message_count,
+                    disk.message_count,
+                );
+                merge_field(&mut branch.updated_at, &base.updated_at, disk.updated_at);
+                merge_field(&mut branch.pinned, &base.pinned, disk.pinned);
+                merge_field(&mut branch.archived, &base.archived, disk.archived);
+                merge_field(&mut branch.folder, &base.folder, disk.folder);
+                merge_field(&mut branch.tags, &base.tags, disk.tags);
+                true
+            }
+            (Some(_), None) => true,
+            // deleted elsewhere, unless it is new or was changed here
+            (None, _) => !unchanged,
+        }
+    });
+    for branch in theirs {
+        if by_id.contains_key(&branch.id) && !base.contains_key(&branch.id) {
+            ours.push(branch);
+        }
+    }
+}
+
+impl ChatStore {
+    /// open the store in `dir`, moving chats over from the old single `chats.json`
+    pub fn open(dir: PathBuf) -> Result<Self> {
+        let mut store = ChatStore {
+            dir,
+            base_index: HashMap::new(),
+            base_trash: HashMap::new(),
+            seen: 0,
+        };
+        if !store.index_path().exists() {
+            fs::create_dir_all(&store.dir)
+                .with_context(|| format!("can't create {}", store.dir.display()))?;
+            store.migrate(Path::new("chats.json"), Path::new("trash.json"))?;
+        }
+        Ok(store)
+    }
+
+    fn index_path(&self) -> PathBuf {
+        self.dir.join("index.json")
+    }
+
+    fn trash_path(&self) -> PathBuf {
+        self.dir.join("trash.json")
+    }
+
+    fn chat_path(&self, id: usize) -> PathBuf {
+        self.dir.join(format!("{id}.json"))
+    }
+
+    /// blocks until no other instance is writing, unlocked when the file is dropped
+    fn lock(&self) -> Result<File> {
+        let file = File::options()
+            .create(true)
+            .truncate(false)
+            .write(true)
+            .open(self.dir.join(".lock"))?;
+        file.lock()?;
+        Ok(file)
+    }
+
+    // file times are too coarse to tell two quick writes apart, a counter isn't
+    fn version(&self) -> u64 {
+        fs::read_to_string(self.dir.join(".version"))
+            .ok()
+            .and_then(|v| v.trim().parse().ok())
+            .unwrap_or(0)
+    }
+
+    /// called after every write, the caller holds the lock
+    fn bump_version(&mut self) -> Result<()> {
+        let current = self.version();
+        fs::write(self.dir.join(".version"), (current + 1).to_string())?;
+        // if someone else wrote in between we still have to look at that
+        if current == self.seen {
+            self.seen = current + 1;
+        }
+        Ok(())
+    }
+
+    /// an id no chat here, on disk or in any other instance has, the caller holds
+    /// the lock. handed out ids are remembered in `.next_id`, so a chat another
+    /// instance made but did not save to the index yet keeps its own
+    fn allocate_id(&self, branches: &[ChatBranch], trash: &[ChatBranch]) -> Result<usize> {
+        let path = self.dir.join(".next_id");
+        let counter = fs::read_to_string(&path)
+            .ok()
+            .and_then(|n| n.trim().parse().ok())
+            .unwrap_or(0);
+        let on_disk = ChatBranch::next_id(
+            &ChatBranch::load_all(&self.index_path())?,
+            &ChatBranch::load_all(&self.trash_path())?,
+        );
+        let id = counter
+            .max(on_disk)
+            .max(ChatBranch::next_id(branches, trash));
+        fs::write(&path, (id + 1).to_string())?;
+        Ok(id)
+    }
+
+    /// an id for a new chat that no other instance can pick too
+    pub fn new_id(&self, branches: &[ChatBranch], trash: &[ChatBranch]) -> Result<usize> {
+        let _lock = self.lock()?;
+        self.allocate_id(branches, trash)
+    }
+
+    /// whether another instance wrote to the store since we last looked
+    pub fn changed_on_disk(&self) -> bool {
+        self.version() != self.seen
+    }
+
+    /// split the old files, which held every message of every chat, into the new layout
+    fn migrate(&mut self, chats: &Path, trash: &Path) -> Result<()> {
+        let _lock = self.lock()?;
+        // another instance may have done it while we waited
+        if self.index_path().exists() {
+            return Ok(());
+        }
+        let mut index = Vec::new();
+        let mut trashed = Vec::new();
+        for (path, entries) in [(chats, &mut index), (trash, &mut trashed)] {
+            if !path.exists() {
+                continue;
+            }
+            for branch in ChatBranch::load_all(path)? {
+                entries.push(self.write_new_chat(branch)?);
+            }
+        }
+        write_atomic(&self.trash_path(), &serde_json::to_string_pretty(&trashed)?)?;
+        // the index goes last, it marks the migration as done
+        write_atomic(&self.index_path(), &serde_json::to_string_pretty(&index)?)?;
+        self.bump_version()
+    }
+
+    /// a file of the store's own besides the chats, like the search index. `None`
+    /// when it isn't there yet
+    pub fn read_file(&self, name: &str) -> Result<Option<String>> {
+        let _lock = self.lock()?;
+        read_if_there(&self.dir.join(name))
+    }
+
+    /// rewrite such a file from what is in it now, `update` gets `None` when it
+    /// isn't there yet. no other instance reads or writes it meanwhile
+    pub fn update_file(
+        &self,
+        name: &str,
+        update: impl FnOnce(Option<String>) -> Result<String>,
+    ) -> Result<()> {
+        let _lock = self.lock()?;
+        let path = self.dir.join(name);
+        let current = read_if_there(&path)?;
+        write_atomic(&path, &update(current)?)
+    }
+
+    pub fn load_index(&mut self) -> Result<Vec<ChatBranch>> {
+        let index = ChatBranch::load_all(&self.index_path())?;
+        self.base_index = snapshot(&index);
+        self.seen = self.version();
+        Ok(index)
+    }
+
+    pub fn load_trash(&mut self) -> Result<Vec<ChatBranch>> {
+        let trash = ChatBranch::load_all(&self.trash_path())?;
+        self.base_trash = snapshot(&trash);
+        self.seen = self.version();
+        Ok(trash)
+    }
+
+    /// merge what other instances changed into `branches` and write the result
+    pub fn save_index(&mut self, branches: &mut Vec<ChatBranch>) -> Result<()> {
+        let _lock = self.lock()?;
+        merge(
+            branches,
+            &self.base_index,
+            ChatBranch::load_all(&self.index_path())?,
+        );
+        write_atomic(&self.index_path(), &serde_json::to_string_pretty(branches)?)?;
+        self.base_index = snapshot(branches);
+        self.bump_version()
+    }
+
+    pub fn save_trash(&mut self, trash: &mut Vec<ChatBranch>) -> Result<()> {
+        let _lock = self.lock()?;
+        merge(
+            trash,
+            &self.base_trash,
+            ChatBranch::load_all(&self.trash_path())?,
+        );
+        write_atomic(&self.trash_path(), &serde_json::to_string_pretty(trash)?)?;
+        self.base_trash = snapshot(trash);
+        self.bump_version()
+    }
+
+    /// bring `branches` and `trash` up to date with what other instances wrote
+    pub fn reload(
+        &mut self,
+        branches: &mut Vec<ChatBranch>,
+        trash: &mut Vec<ChatBranch>,
+    ) -> Result<()> {
+        let _lock = self.lock()?;
+        let index = ChatBranch::load_all(&self.index_path())?;
+        let trashed = ChatBranch::load_all(&self.trash_path())?;
+        merge(branches, &self.base_index, index.clone());
+        merge(trash, &self.base_trash, trashed.clone());
+        // whatever we changed and did not save yet still counts as ours
+        self.base_index = snapshot(&index);
+        self.base_trash = snapshot(&trashed);
+        self.seen = self.version();
+        Ok(())
+    }
+
+    /// the messages of chat `id`, a chat that was never written has none
+    pub fn load_messages(&self, id: usize) -> Result<Vec<Message>> {
+        let path = self.chat_path(id);
+        if !path.exists() {
+            return Ok(Vec::new());
+        }
+        let data = fs::read_to_string(&path)?;
+        serde_json::from_str(&data).with_context(|| format!("can't read {}", path.display()))
+    }
+
+    /// write the messages and record the new count and time in `entry` and on disk,
+    /// the caller holds the lock
+    fn write_chat(&mut self, entry: &mut ChatBranch, messages: &[Message]) -> Result<()> {
+        entry.message_count = messages.len();
+        entry.updated_at = Some(Utc::now());
+        write_atomic(
+            &self.chat_path(entry.id),
+            &serde_json::to_string_pretty(messages)?,
+        )?;
+        let mut index = ChatBranch::load_all(&self.index_path())?;
+        if let Some(disk) = index.iter_mut().find(|b| b.id == entry.id) {
+            disk.message_count = entry.message_count;
+            disk.updated_at = entry.updated_at;
+            // only these two are settled now, a rename elsewhere still has to win
+            if let Some(base) = self.base_index.get_mut(&entry.id) {
+                base.message_count = entry.message_count;
+                base.updated_at = entry.updated_at;
+            }
+            write_atomic(&self.index_path(), &serde_json::to_string_pretty(&index)?)?;
+        }
+        self.bump_version()
+    }
+
+    /// write the messages of `entry`'s chat. if another instance added messages
+    /// since we last read it ours are appended after theirs and `messages` is
+    /// updated, if it changed older messages nothing is written
+    pub fn save_messages(
+        &mut self,
+        entry: &mut ChatBranch,
+        messages: &mut Vec<Message>,
+    ) -> Result<Saved> {
+        let _lock = self.lock()?;
+        let index = ChatBranch::load_all(&self.index_path())?;
+        let mut saved = Saved::Written;
+        if let Some(disk) = index.iter().find(|b| b.id == entry.id)
+            && disk.updated_at != entry.updated_at
+        {
+            let theirs = self.load_messages(entry.id)?;
+            let known = entry.message_count.min(messages.len());
+            if theirs.len() < known
+                || !theirs[..known]
+                    .iter()
+                    .zip(&messages[..known])
+                    .all(|(a, b)| a.same(b))
+            {
+                return Ok(Saved::Refused);
+            }
+            let ours = messages.split_off(known);
+            *messages = theirs;
+            messages.extend(ours);
+            saved = Saved::Merged;
+        }
+        self.write_chat(entry, messages)?;
+        Ok(saved)
+    }
+
+    /// write bookmarks and such of `entry`'s chat onto what is on disk. it doesn't
+    /// count as activity, so the time isn't touched, and messages that changed
+    /// elsewhere keep theirs. the version still goes up so other instances look
+    pub fn save_marks(&mut self, entry: &ChatBranch, messages: &[Message]) -> Result<()> {
+        let _lock = self.lock()?;
+        let mut theirs = self.load_messages(entry.id)?;
+        for (disk, ours) in theirs.iter_mut().zip(messages) {
+            if disk.same(ours) {
+                disk.copy_marks(ours);
+            }
+        }
+        write_atomic(
+            &self.chat_path(entry.id),
+            &serde_json::to_string_pretty(&theirs)?,
+        )?;
+        self.bump_version()
+    }
+
+    /// add one message to a chat that is not open, always on top of what is on disk
+    pub fn append_message(&mut self, entry: &mut ChatBranch, message: Message) -> Result<()> {
+        let _lock = self.lock()?;
+        let mut messages = self.load_messages(entry.id)?;
+        messages.push(message);
+        self.write_chat(entry, &messages)
+    }
+
+    /// throw away what we have of `entry`'s chat and take the version on disk
+    pub fn reload_chat(&mut self, entry: &mut ChatBranch) -> Result<Vec<Message>> {
+        let _lock = self.lock()?;
+        if let Some(disk) = ChatBranch::load_all(&self.index_path())?
+            .into_iter()
+            .find(|b| b.id == entry.id)
+        {
+            entry.message_count = disk.message_count;
+            entry.updated_at = disk.updated_at;
+        }
+        self.load_messages(entry.id)
+    }
+
+    pub fn delete_messages(&self, id: usize) -> Result<()> {
+        let path = self.chat_path(id);
+        if path.exists() {
+            fs::remove_file(path)?;
+        }
+        Ok(())
+    }
+
+    /// `entry` together with its messages, for exporting and searching
+    pub fn load_chat(&self, entry: &ChatBranch) -> Result<ChatBranch> {
+        let mut branch = entry.clone();
+        branch.messages = self.load_messages(entry.id)?;
+        Ok(branch)
+    }
+
+    /// store a whole new chat under a fresh id and return its index entry, which
+    /// keeps the time of its last message instead of now
+    pub fn add_chat(
+        &self,
+        mut branch: ChatBranch,
+        branches: &[ChatBranch],
+        trash: &[ChatBranch],
+    ) -> Result<ChatBranch> {
+        let _lock = self.lock()?;
+        branch.id = self.allocate_id(branches, trash)?;
+        self.write_new_chat(branch)
+    }
+
+    /// write the messages of a chat that has its id already
+    fn write_new_chat(&self, mut branch: ChatBranch) -> Result<ChatBranch> {
+        let messages = std::mem::take(&mut branch.messages);
+        write_atomic(
+            &self.chat_path(branch.id),
+            &serde_json::to_string_pretty(&messages)?,
+        )?;
+        branch.message_count = messages.len();
+        branch.updated_at = messages.iter().filter_map(|m| m.created_at).max();
+        Ok(branch)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::chat_structs::Role;
+
+    #[test]
+    fn marks_are_saved_without_touching_the_time() {
+        let dir = std::env::temp_dir().join(format!("llm-tui-rs-marks-{}", std::process::id()));
+        let mut ours = ChatStore::open(dir.clone()).unwrap();
+        let mut theirs = ChatStore::open(dir.clone()).unwrap();
+        let mut branches = vec![ChatBranch::new(0, "chat")];
+        ours.save_index(&mut branches).unwrap();
+        let mut messages = vec![Message::new(Role::User, "hi".into())];
+        ours.save_messages(&mut branches[0], &mut messages).unwrap();
+        theirs.load_index().unwrap();
+        let before = ChatBranch::load_all(&ours.index_path()).unwrap()[0].updated_at;
+
+        messages[0].bookmarked = true;
+        messages[0].collapsed = Some(true);
+        ours.save_marks(&branches[0], &messages).unwrap();
+
+        let index = ChatBranch::load_all(&ours.index_path()).unwrap();
+        assert_eq!(index[0].updated_at, before);
+        assert!(theirs.changed_on_disk());
+        let disk = ours.load_messages(0).unwrap();
+        assert!(disk[0].bookmarked);
+        assert_eq!(disk[0].collapsed, Some(true));
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn store_files_start_missing_and_get_replaced_whole() {
+        let dir = std::env::temp_dir().join(format!("llm-tui-rs-files-{}", std::process::id()));
+        let store = ChatStore::open(dir.clone()).unwrap();
+        assert_eq!(store.read_file("x.json").unwrap(), None);
+        store
+            .update_file("x.json", |current| {
+                assert_eq!(current, None);
+                Ok("1".to_string())
+            })
+            .unwrap();
+        store
+            .update_file("x.json", |current| Ok(format!("{}2", current.unwrap())))
+            .unwrap();
+        assert_eq!(store.read_file("x.json").unwrap().as_deref(), Some("12"));
+        assert!(!dir.join("x.json.tmp").exists());
+        fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn index_changes_from_both_sides_are_kept() {
+        let chat = |id, name| ChatBranch::new(id, name);
+        let base = snapshot(&[chat(0, "a"), chat(1, "b"), chat(2, "c"), chat(5, "f")]);
+
+        let mut ours = vec![chat(0, "renamed"), chat(1, "b"), chat(2, "c"), chat(5, "f")];
+        ours[3].archived = true;
+        ours.push(chat(3, "new here"));
+
+        let mut theirs = vec![chat(0, "a"), chat(2, "c"), chat(4, "new there")];
+        theirs[0].tags = vec!["work".into()];
+        theirs[1].pinned = true;
+
+        merge(&mut ours, &base, theirs);
+        let names: Vec<&str> = ours.iter().map(|b| b.name.as_str()).collect();
+        // b was deleted there, f was archived here after that and stays
+        assert_eq!(names, ["renamed", "c", "f", "new here", "new there"]);
+        assert_eq!(ours[0].tags, ["work"]);
+        assert!(ours[1].pinned);
+        assert!(ours[2].archived);
+    }
+
+    #[test]
+    fn new_messages_from_both_windows_are_kept() {
+        let dir = std::env::temp_dir().join(format!("llm-tui-rs-merge-{}", std::process::id()));
+        let mut ours = ChatStore::open(dir.clone()).unwrap();
+        let mut theirs = ChatStore::open(dir.clone()).unwrap();
+        let mut branches = vec![ChatBranch::new(0, "chat")];
+        ours.save_index(&mut branches).unwrap();
+        let mut mine = vec![Message::new(Role::User, "first".into())];
+        ours.save_messages(&mut branches[0], &mut mine).unwrap();
+
+        let mut other = theirs.load_index().unwrap();
+        let mut their_messages = theirs.load_messages(0).unwrap();
+        their_messages.push(Message::new(Role::User, "theirs".into()));
+        let saved = theirs.save_messages(&mut other[0], &mut their_messages);
+        assert_eq!(saved.unwrap(), Saved::Written);
+
+        mine.push(Message::new(Role::User, "ours".into()));
+        let saved = ours.save_messages(&mut branches[0], &mut mine).unwrap();
+        let contents = |messages: &[Message]| -> Vec<String> {
+            messages.iter().map(|m| m.content.clone()).collect()
+        };
+        assert_eq!(saved, Saved::Merged);
+        assert_eq!(contents(&mine), ["first", "theirs", "ours"]);
+        assert_eq!(contents(&ours.load_messages(0).unwrap()), contents(&mine));
+
+        // an older message edited there can't be merged
+        their_messages = theirs.reload_chat(&mut other[0]).unwrap();
+        their_messages[0].content = "edited".into();
+        theirs
+            .save_messages(&mut other[0], &mut their_messages)
+            .unwrap();
+        mine[1].content = "edited here".into();
+        let saved = ours.save_messages(&mut branches[0], &mut mine).unwrap();
+        assert_eq!(saved, Saved::Refused);
+        assert_eq!(ours.load_messages(0).unwrap()[0].content, "edited");
+        fs::remove_dir_all(dir).unwrap();
+    }
+}
@@ -1,18 +1,107 @@
-// use chrono::DateTime;
-// use chrono::Utc;
+use crate::ai_backend::{AIBackend, AISettings};
+use chrono::DateTime;
+use chrono::Utc;
 use serde::{Deserialize, Serialize};
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct Assistant {
     pub model: String,
+    // missing for old and imported messages
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub generation: Option<Generation>,
 }
-#[derive(Debug, Clone, Serialize, Deserialize)]
+
+impl Assistant {
+    pub fn new(model: &str) -> Self {
+        Assistant {
+            model: model.to_string(),
+            generation: None,
+        }
+    }
+}
+
+/// how an assistant message was generated
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Generation {
+    pub backend: AIBackend,
+    pub temperature: f32,
+    pub max_tokens: usize,
+    pub latency_ms: u64,
+    // guessed from the length of the text, the backends don't tell us
+    #[serde(default)]
+    pub prompt_tokens: usize,
+    #[serde(default)]
+    pub reply_tokens: usize,
+    // dollars, when the settings had a price
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub cost: Option<f64>,
+}
+
+impl Generation {
+    pub fn new(
+        settings: &AISettings,
+        latency_ms: u64,
+        prompt_tokens: usize,
+        reply_tokens: usize,
+    ) -> Self {
+        Generation {
+            backend: settings.backend,
+            temperature: settings.temperature,
+            max_tokens: settings.max_tokens,
+            latency_ms,
+            prompt_tokens,
+            reply_tokens,
+            cost: settings
+                .price
+                .map(|price| price.cost(prompt_tokens, reply_tokens)),
+        }
+    }
+}
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub enum Role {
     User,
     Assistant(Assistant),
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct Message {
     pub role: Role,
     pub content: String,
+    // older chats.json files don't have this
+    #[serde(default)]
+    pub created_at: Option<DateTime<Utc>>,
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub bookmarked: bool,
+    // folded or opened by hand, left out it goes by collapse_after in ui.json
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub collapsed: Option<bool>,
+    // the code blocks folded to one line, counted from 0
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub collapsed_code: Vec<usize>,
+}
+
+impl Message {
+    pub fn new(role: Role, content: String) -> Self {
+        Message {
+            role,
+            content,
+            created_at: Some(Utc::now()),
+            bookmarked: false,
+            collapsed: None,
+            collapsed_code: Vec::new(),
+        }
+    }
+
+    /// the same message, whatever was bookmarked or collapsed since
+    pub fn same(&self, other: &Message) -> bool {
+        self.role == other.role
+            && self.content == other.content
+            && self.created_at == other.created_at
+    }
+
+    /// take the bookmark and what is collapsed over from `other`
+    pub fn copy_marks(&mut self, other: &Message) {
+        self.bookmarked = other.bookmarked;
+        self.collapsed = other.collapsed;
+        self.collapsed_code.clone_from(&other.collapsed_code);
+    }
 }
@@ -0,0 +1,145 @@
+// command line subcommands, running without one starts the TUI
+use crate::chat_branch::ChatBranch;
+use crate::chat_store::ChatStore;
+use crate::export::{self, ExportFormat};
+use crate::import::{self, ImportSource};
+use crate::keymap::{self, Keymap};
+use anyhow::{Context, Result, bail};
+use std::path::{Path, PathBuf};
+
+const USAGE: &str = "usage:
+  llm-tui-rs                    start the TUI
+  llm-tui-rs export <chat> [--format md|html|json] [--output FILE]
+                                write a chat (id or title) to FILE or stdout
+  llm-tui-rs import <file> [--format chatgpt|openwebui]
+                                add the chats of a ChatGPT or Open WebUI export
+  llm-tui-rs keys               list the key bindings from keymap.json and what is
+                                wrong with them
+  llm-tui-rs help               show this";
+
+/// `None` when there is no subcommand and the TUI should start
+pub fn run(args: &[String]) -> Option<Result<()>> {
+    let (command, rest) = args.split_first()?;
+    Some(match command.as_str() {
+        "export" => export(rest),
+        "import" => import(rest),
+        "keys" => {
+            keys();
+            Ok(())
+        }
+        "help" | "--help" | "-h" => {
+            println!("{USAGE}");
+            Ok(())
+        }
+        other => Err(anyhow::anyhow!("unknown command '{other}'\n{USAGE}")),
+    })
+}
+
+/// find a chat by id or by (case insensitive) title
+pub fn find_chat(branches: &[ChatBranch], query: &str) -> Result<ChatBranch> {
+    if let Ok(id) = query.parse::<usize>()
+        && let Some(branch) = branches.iter().find(|b| b.id == id)
+    {
+        return Ok(branch.clone());
+    }
+    let matches: Vec<&ChatBranch> = branches
+        .iter()
+        .filter(|b| b.name.eq_ignore_ascii_case(query))
+        .collect();
+    match matches.as_slice() {
+        [branch] => Ok((*branch).clone()),
+        [] => bail!("no chat called '{query}'"),
+        _ => bail!(
+            "'{query}' matches several chats, use the id instead: {}",
+            matches
+                .iter()
+                .map(|b| b.id.to_string())
+                .collect::<Vec<_>>()
+                .join(", ")
+        ),
+    }
+}
+
+fn export(args: &[String]) -> Result<()> {
+    let mut chat = None;
+    let mut format = None;
+    let mut output = None;
+    let mut args = args.iter();
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "-f" | "--format" => {
+                format = Some(ExportFormat::from_name(
+                    args.next().context("--format needs a value")?,
+                )?);
+            }
+            "-o" | "--output" => {
+                output = Some(PathBuf::from(
+                    args.next().context("--output needs a value")?,
+                ));
+            }
+            _ if chat.is_none() => chat = Some(arg.as_str()),
+            _ => bail!("unexpected argument '{arg}'\n{USAGE}"),
+        }
+    }
+    let chat = chat.context(USAGE)?;
+
+    let mut store = ChatStore::open(PathBuf::from("chats"))?;
+    let entry = find_chat(&store.load_index()?, chat)?;
+    let branch = store.load_chat(&entry)?;
+    let format = match (format, &output) {
+        (Some(format), _) => format,
+        (None, Some(path)) => ExportFormat::from_path(path)?,
+        (None, None) => ExportFormat::Markdown,
+    };
+    let rendered = export::render(&branch, format)?;
+    match output {
+        Some(path) => std::fs::write(path, rendered)?,
+        None => println!("{}", rendered.trim_end()),
+    }
+    Ok(())
+}
+
+fn import(args: &[String]) -> Result<()> {
+    let mut file = None;
+    let mut source = None;
+    let mut args = args.iter();
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "-f" | "--format" => {
+                source = Some(ImportSource::from_name(
+                    args.next().context("--format needs a value")?,
+                )?);
+            }
+            _ if file.is_none() => file = Some(PathBuf::from(arg)),
+            _ => bail!("unexpected argument '{arg}'\n{USAGE}"),
+        }
+    }
+    let file = file.context(USAGE)?;
+
+    let mut store = ChatStore::open(PathBuf::from("chats"))?;
+    let mut branches = store.load_index()?;
+    let trash = store.load_trash()?;
+    let count = import::import_into(&file, source, &store, &mut branches, &trash)?;
+    store.save_index(&mut branches)?;
+    println!("imported {count} chats into the 'imported' folder");
+    Ok(())
+}
+
+fn keys() {
+    let keymap = Keymap::load(Path::new("keymap.json"));
+    for binding in &keymap.bindings {
+        println!(
+            "{:<8} {:<16} {:<20} {}",
+            binding.context.name(),
+            keymap::chord_text(&binding.keys),
+            binding.action.name(),
+            binding.action.label()
+        );
+    }
+    if !keymap.problems.is_empty() {
+        println!("\nproblems:");
+        for problem in &keymap.problems {
+            println!("  {problem}");
+        }
+    }
+}
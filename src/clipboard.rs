@@ -0,0 +1,87 @@
+// copying to the clipboard. OSC 52 asks the terminal to do it, which also works over
+// ssh and inside tmux, and a clipboard tool is run as well for terminals without it
+use anyhow::{Context, Result, bail};
+use base64::Engine;
+use base64::engine::general_purpose::STANDARD;
+use std::env;
+use std::io::{self, Write};
+use std::process::{Command, Stdio};
+
+// tried in this order, the first one that runs wins
+const TOOLS: [(&str, &[&str]); 5] = [
+    ("wl-copy", &[]),
+    ("xclip", &["-selection", "clipboard"]),
+    ("xsel", &["--clipboard", "--input"]),
+    ("pbcopy", &[]),
+    ("clip.exe", &[]),
+];
+
+// screen cuts escape sequences longer than this
+const SCREEN_CHUNK: usize = 768;
+
+/// how far the text got, there is no telling whether the terminal took OSC 52
+pub enum Copied {
+    // this clipboard tool took it
+    Tool(&'static str),
+    // only the escape sequence went out
+    Terminal,
+}
+
+pub fn copy(text: &str) -> Result<Copied> {
+    write_osc52(text)?;
+    // over ssh the clipboard of this machine is not the one in front of the user
+    if env::var_os("SSH_CONNECTION").is_none()
+        && let Ok(tool) = copy_with_tool(text)
+    {
+        return Ok(Copied::Tool(tool));
+    }
+    Ok(Copied::Terminal)
+}
+
+fn write_osc52(text: &str) -> Result<()> {
+    let sequence = format!("\x1b]52;c;{}\x07", STANDARD.encode(text));
+    let mut out = io::stdout().lock();
+    if env::var_os("TMUX").is_some() {
+        // tmux takes it itself with set-clipboard on, and passes it on to the
+        // terminal wrapped like this with allow-passthrough on
+        out.write_all(sequence.as_bytes())?;
+        let inner = sequence.replace('\x1b', "\x1b\x1b");
+        write!(out, "\x1bPtmux;{inner}\x1b\\")?;
+    } else if env::var_os("STY").is_some() {
+        for chunk in sequence.as_bytes().chunks(SCREEN_CHUNK) {
+            out.write_all(b"\x1bP")?;
+            out.write_all(chunk)?;
+            out.write_all(b"\x1b\\")?;
+        }
+    } else {
+        out.write_all(sequence.as_bytes())?;
+    }
+    out.flush()?;
+    Ok(())
+}
+
+fn copy_with_tool(text: &str) -> Result<&'static str> {
+    for (tool, args) in TOOLS {
+        let Ok(mut child) = Command::new(tool)
+            .args(args)
+            .stdin(Stdio::piped())
+            .stdout(Stdio::null())
+            .stderr(Stdio::null())
+            .spawn()
+        else {
+            continue;
+        };
+        // closing stdin tells it the text is complete
+        child
+            .stdin
+            .take()
+            .context("no stdin")?
+            .write_all(text.as_bytes())?;
+        let status = child.wait()?;
+        if !status.success() {
+            bail!("{tool} failed ({status})");
+        }
+        return Ok(tool);
+    }
+    bail!("no clipboard tool found")
+}
@@ -0,0 +1,193 @@
+// slash commands typed into the chat input. this only knows their names, arguments
+// and help and how to split a line into them, ui/commands.rs runs them.
+use anyhow::{Result, bail};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Command {
+    Model,
+    Profile,
+    System,
+    Attach,
+    Copy,
+    Edit,
+    Clear,
+    Export,
+    Title,
+    Retry,
+    Fork,
+    Arena,
+    Help,
+}
+
+/// what a line in the input turned out to be
+pub enum Parsed<'a> {
+    Prompt(&'a str),
+    Command(Command, &'a str),
+}
+
+impl Command {
+    pub const ALL: [Command; 13] = [
+        Command::Model,
+        Command::Profile,
+        Command::System,
+        Command::Attach,
+        Command::Copy,
+        Command::Edit,
+        Command::Clear,
+        Command::Export,
+        Command::Title,
+        Command::Retry,
+        Command::Fork,
+        Command::Arena,
+        Command::Help,
+    ];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Command::Model => "model",
+            Command::Profile => "profile",
+            Command::System => "system",
+            Command::Attach => "attach",
+            Command::Copy => "copy",
+            Command::Edit => "edit",
+            Command::Clear => "clear",
+            Command::Export => "export",
+            Command::Title => "title",
+            Command::Retry => "retry",
+            Command::Fork => "fork",
+            Command::Arena => "arena",
+            Command::Help => "help",
+        }
+    }
+
+    /// `<arg>` has to be given, `[arg]` can be left out
+    pub fn args(self) -> &'static str {
+        match self {
+            Command::Profile => "[name | save <name>]",
+            Command::System => "[prompt]",
+            Command::Attach | Command::Export => "<file>",
+            Command::Model | Command::Title | Command::Fork => "[name]",
+            Command::Help => "[command]",
+            Command::Edit => "[n]",
+            Command::Copy => "[n | chat]",
+            Command::Arena => "[profile,profile,... prompt]",
+            Command::Clear | Command::Retry => "",
+        }
+    }
+
+    pub fn help(self) -> &'static str {
+        match self {
+            Command::Model => "show or switch the model",
+            Command::Profile => "list, switch to or save settings profiles",
+            Command::System => "set the system prompt, or edit it in $EDITOR",
+            Command::Attach => "put a text file into the prompt",
+            Command::Copy => "copy the last message, its code block n, or the whole chat",
+            Command::Edit => "edit message n, counted from the top, or the last prompt in $EDITOR",
+            Command::Clear => "delete every message of this chat",
+            Command::Export => "write this chat to a .md, .html or .json file",
+            Command::Title => "rename this chat, or let the model name it",
+            Command::Retry => "ask for the last reply again",
+            Command::Fork => "copy this chat and continue in the copy",
+            Command::Arena => {
+                "ask several profiles at once and pick the best reply, or show the leaderboard"
+            }
+            Command::Help => "list the commands or explain one",
+        }
+    }
+
+    pub fn usage(self) -> String {
+        format!("/{} {}", self.name(), self.args())
+            .trim_end()
+            .to_string()
+    }
+
+    pub fn from_name(name: &str) -> Option<Self> {
+        Self::ALL.into_iter().find(|c| c.name() == name)
+    }
+}
+
+/// split a line into a command and its arguments. lines that don't start with
+/// a slash are prompts, `//` sends a prompt that starts with one
+pub fn parse(input: &str) -> Result<Parsed<'_>> {
+    let Some(line) = input.strip_prefix('/') else {
+        return Ok(Parsed::Prompt(input));
+    };
+    if line.starts_with('/') {
+        return Ok(Parsed::Prompt(line));
+    }
+    let (name, args) = line
+        .split_once(char::is_whitespace)
+        .map_or((line, ""), |(name, args)| (name, args.trim()));
+    let Some(command) = Command::from_name(name) else {
+        bail!("unknown command /{name}, /help lists them (// sends a literal /)");
+    };
+    if command.args().starts_with('<') && args.is_empty() {
+        bail!("usage: {}", command.usage());
+    }
+    Ok(Parsed::Command(command, args))
+}
+
+/// commands to offer for what is typed so far: every command starting with it
+/// while the name is typed, the one command being used once its arguments are
+pub fn completions(input: &str) -> Vec<Command> {
+    let Some(line) = input.strip_prefix('/') else {
+        return Vec::new();
+    };
+    if line.starts_with('/') || line.contains('\n') {
+        return Vec::new();
+    }
+    match line.split_once(char::is_whitespace) {
+        Some((name, _)) => Command::from_name(name).into_iter().collect(),
+        None => Command::ALL
+            .into_iter()
+            .filter(|c| c.name().starts_with(line))
+            .collect(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn command(input: &str) -> (Command, &str) {
+        match parse(input).unwrap() {
+            Parsed::Command(command, args) => (command, args),
+            Parsed::Prompt(prompt) => panic!("'{input}' was read as the prompt '{prompt}'"),
+        }
+    }
+
+    fn prompt(input: &str) -> &str {
+        match parse(input).unwrap() {
+            Parsed::Prompt(prompt) => prompt,
+            Parsed::Command(command, _) => panic!("'{input}' was read as /{}", command.name()),
+        }
+    }
+
+    #[test]
+    fn lines_split_into_commands_and_prompts() {
+        assert_eq!(command("/model gpt-4o"), (Command::Model, "gpt-4o"));
+        assert_eq!(
+            command("/arena fast,smart  why?\n"),
+            (Command::Arena, "fast,smart  why?")
+        );
+        assert_eq!(command("/clear"), (Command::Clear, ""));
+        assert_eq!(prompt("hello /model"), "hello /model");
+        assert_eq!(prompt("//etc/hosts is"), "/etc/hosts is");
+        assert!(parse("/nope").is_err());
+        let err = parse("/attach").err().unwrap().to_string();
+        assert_eq!(err, "usage: /attach <file>");
+    }
+
+    #[test]
+    fn completions_follow_the_name_typed_so_far() {
+        assert_eq!(completions("/c"), [Command::Copy, Command::Clear]);
+        assert_eq!(completions("/export notes.md"), [Command::Export]);
+        assert_eq!(completions("/").len(), Command::ALL.len());
+        assert!(completions("//").is_empty());
+        assert!(completions("/clear\nmore").is_empty());
+        assert!(completions("clear").is_empty());
+        for command in Command::ALL {
+            assert_eq!(Command::from_name(command.name()), Some(command));
+        }
+    }
+}
@@ -0,0 +1,86 @@
+// writing text in $EDITOR. the main loop hands the terminal over while the
+// editor runs, this only deals with the temporary file and the process.
+use anyhow::{Context, Result};
+use std::fs::{self, File};
+use std::io::Write;
+#[cfg(unix)]
+use std::os::unix::fs::OpenOptionsExt;
+use std::path::Path;
+use std::process::Command;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// what the text coming back from the editor replaces
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum EditTarget {
+    Prompt,
+    SystemPrompt,
+    // index into the open chat's messages
+    Message(usize),
+}
+
+/// `$VISUAL` or `$EDITOR`, which may come with arguments like `code --wait`
+fn editor_command() -> Vec<String> {
+    let editor = std::env::var("VISUAL")
+        .or_else(|_| std::env::var("EDITOR"))
+        .ok()
+        .filter(|e| !e.trim().is_empty())
+        .unwrap_or_else(|| "vi".to_string());
+    editor.split_whitespace().map(str::to_string).collect()
+}
+
+/// a new file only we can read, the temp dir is shared with everyone on the machine.
+/// one left there with the same name is not written to
+fn write_private(path: &Path, text: &str) -> std::io::Result<()> {
+    let mut options = File::options();
+    options.write(true).create_new(true);
+    #[cfg(unix)]
+    options.mode(0o600);
+    options.open(path)?.write_all(text.as_bytes())
+}
+
+/// open `text` in the editor and return what it was saved as, `None` if the
+/// editor exited with an error (`:cq` in vim) and the edit should be dropped
+pub fn edit(text: &str) -> Result<Option<String>> {
+    let nanos = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |d| d.as_nanos());
+    // .md so the editor highlights it as markdown
+    let path = std::env::temp_dir().join(format!("llm-tui-{}-{nanos}.md", std::process::id()));
+    write_private(&path, text).with_context(|| format!("can't write {}", path.display()))?;
+
+    let command = editor_command();
+    let status = Command::new(&command[0])
+        .args(&command[1..])
+        .arg(&path)
+        .status()
+        .with_context(|| format!("can't start '{}'", command.join(" ")));
+    let edited = fs::read_to_string(&path);
+    let _ = fs::remove_file(&path);
+
+    if !status?.success() {
+        return Ok(None);
+    }
+    // editors end the file with a line break, a prompt doesn't need it
+    Ok(Some(edited?.trim_end_matches(['\n', '\r']).to_string()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn temp_files_are_private_and_never_reused() {
+        let path =
+            std::env::temp_dir().join(format!("llm-tui-rs-editor-{}.md", std::process::id()));
+        write_private(&path, "secret").unwrap();
+        assert_eq!(fs::read_to_string(&path).unwrap(), "secret");
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            let mode = fs::metadata(&path).unwrap().permissions().mode();
+            assert_eq!(mode & 0o777, 0o600);
+        }
+        assert!(write_private(&path, "other").is_err());
+        fs::remove_file(path).unwrap();
+    }
+}
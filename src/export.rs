@@ -0,0 +1,343 @@
+// writing a chat out as markdown, a standalone html page or plain json
+use crate::chat_branch::ChatBranch;
+use crate::chat_structs::{Generation, Message, Role};
+use anyhow::{Result, bail};
+use chrono::{DateTime, Utc};
+use pulldown_cmark::{CowStr, Event, Options, Parser, Tag, html};
+use serde::Serialize;
+use std::fmt::Write as _;
+use std::fs::File;
+use std::io::{ErrorKind, Write};
+use std::path::Path;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ExportFormat {
+    Markdown,
+    Html,
+    Json,
+}
+
+impl ExportFormat {
+    pub fn from_name(name: &str) -> Result<Self> {
+        match name.to_lowercase().as_str() {
+            "md" | "markdown" => Ok(ExportFormat::Markdown),
+            "html" | "htm" => Ok(ExportFormat::Html),
+            "json" => Ok(ExportFormat::Json),
+            other => bail!("unknown export format '{other}' (md, html or json)"),
+        }
+    }
+
+    /// guess the format from a file extension
+    pub fn from_path(path: &Path) -> Result<Self> {
+        match path.extension().and_then(|e| e.to_str()) {
+            Some(ext) => Self::from_name(ext),
+            None => bail!("can't tell the export format of '{}'", path.display()),
+        }
+    }
+
+    pub fn extension(self) -> &'static str {
+        match self {
+            ExportFormat::Markdown => "md",
+            ExportFormat::Html => "html",
+            ExportFormat::Json => "json",
+        }
+    }
+}
+
+/// a file name for `branch` that is safe on every platform
+pub fn default_file_name(branch: &ChatBranch, format: ExportFormat) -> String {
+    let stem: String = branch
+        .name
+        .chars()
+        .map(|c| if c.is_alphanumeric() { c } else { '-' })
+        .collect();
+    let stem = stem.trim_matches('-');
+    let stem = if stem.is_empty() { "chat" } else { stem };
+    format!("{stem}.{}", format.extension())
+}
+
+fn role_header(message: &Message) -> String {
+    match &message.role {
+        Role::User => "You".to_string(),
+        Role::Assistant(assistant) => assistant.model.clone(),
+    }
+}
+
+fn timestamp(created_at: Option<DateTime<Utc>>) -> Option<String> {
+    created_at.map(|t| t.format("%Y-%m-%d %H:%M:%S UTC").to_string())
+}
+
+pub fn to_markdown(branch: &ChatBranch) -> String {
+    let mut out = format!("# {}\n", branch.name);
+    for message in &branch.messages {
+        _ = write!(out, "\n## {}\n\n", role_header(message));
+        if let Some(time) = timestamp(message.created_at) {
+            _ = write!(out, "*{time}*\n\n");
+        }
+        out.push_str(demote_headings(&message.content).trim_end());
+        out.push('\n');
+    }
+    out
+}
+
+/// `content` with its headings two levels down, so they sit under the `##` of
+/// the message and never look like one. code blocks are left alone
+pub fn demote_headings(content: &str) -> String {
+    let mut out = String::new();
+    let mut done = 0;
+    for (event, range) in Parser::new_ext(content, Options::empty()).into_offset_iter() {
+        let Event::Start(Tag::Heading { level, .. }) = event else {
+            continue;
+        };
+        let hashes = "#".repeat((level as usize + 2).min(6));
+        let heading = &content[range.clone()];
+        out.push_str(&content[done..range.start]);
+        if let Some(text) = heading.trim_start().strip_prefix('#') {
+            // "# title", whatever number of #
+            out.push_str(&hashes);
+            out.push(' ');
+            out.push_str(text.trim_start_matches('#').trim_start());
+        } else {
+            // a title underlined with === or ---, the underline goes
+            let mut lines: Vec<&str> = heading.trim_end().lines().collect();
+            lines.pop();
+            out.push_str(&hashes);
+            out.push(' ');
+            out.push_str(&lines.iter().map(|l| l.trim()).collect::<Vec<_>>().join(" "));
+            if heading.ends_with('\n') {
+                out.push('\n');
+            }
+        }
+        done = range.end;
+    }
+    out.push_str(&content[done..]);
+    out
+}
+
+fn escape_html(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+const HTML_STYLE: &str = "
+body { max-width: 52rem; margin: 2rem auto; padding: 0 1rem; font: 16px/1.5 system-ui, sans-serif; color: #1f2328; background: #fff; }
+.message { border: 1px solid #d0d7de; border-radius: 8px; padding: 0.25rem 1rem; margin: 1rem 0; }
+.user { background: #f0fff4; }
+.assistant { background: #f6f8fa; }
+.role { font-weight: bold; margin: 0.5rem 0 0; }
+.user .role { color: #1a7f37; }
+.assistant .role { color: #0969da; }
+.time { color: #656d76; font-size: 0.8rem; }
+pre { background: #161b22; color: #e6edf3; padding: 0.75rem 1rem; border-radius: 6px; overflow-x: auto; }
+code { font: 0.9em ui-monospace, SFMono-Regular, Menlo, monospace; }
+:not(pre) > code { background: #eff1f3; padding: 0.1em 0.3em; border-radius: 4px; }
+";
+
+pub fn to_html(branch: &ChatBranch) -> String {
+    let title = escape_html(&branch.name);
+    let mut out = format!(
+        "<!DOCTYPE html>\n<html>\n<head>\n<meta charset=\"utf-8\">\n<title>{title}</title>\n<style>{HTML_STYLE}</style>\n</head>\n<body>\n<h1>{title}</h1>\n"
+    );
+    let mut options = Options::empty();
+    options.insert(Options::ENABLE_STRIKETHROUGH);
+    options.insert(Options::ENABLE_TABLES);
+    for message in &branch.messages {
+        let class = match message.role {
+            Role::User => "user",
+            Role::Assistant(_) => "assistant",
+        };
+        _ = write!(
+            out,
+            "<div class=\"message {class}\">\n<p class=\"role\">{}</p>\n",
+            escape_html(&role_header(message))
+        );
+        if let Some(time) = timestamp(message.created_at) {
+            _ = writeln!(out, "<p class=\"time\">{time}</p>");
+        }
+        // raw html in a message is shown as text, a <script> from a reply must not
+        // run, and neither must a javascript: link
+        let events = Parser::new_ext(&message.content, options).map(|event| match event {
+            Event::Html(html) | Event::InlineHtml(html) => Event::Text(html),
+            Event::Start(Tag::Link {
+                link_type,
+                dest_url,
+                title,
+                id,
+            }) => Event::Start(Tag::Link {
+                link_type,
+                dest_url: safe_url(dest_url),
+                title,
+                id,
+            }),
+            Event::Start(Tag::Image {
+                link_type,
+                dest_url,
+                title,
+                id,
+            }) => Event::Start(Tag::Image {
+                link_type,
+                dest_url: safe_url(dest_url),
+                title,
+                id,
+            }),
+            other => other,
+        });
+        html::push_html(&mut out, events);
+        out.push_str("</div>\n");
+    }
+    out.push_str("</body>\n</html>\n");
+    out
+}
+
+/// `url` when it is http(s), mailto or relative, "#" for anything else
+pub fn safe_url(url: CowStr<'_>) -> CowStr<'_> {
+    // browsers skip tabs and newlines, "java\tscript:" is still javascript:
+    let cleaned: String = url
+        .chars()
+        .filter(|c| !c.is_ascii_whitespace() && !c.is_control())
+        .collect::<String>()
+        .to_ascii_lowercase();
+    let scheme = cleaned
+        .split_once(':')
+        .map(|(scheme, _)| scheme)
+        .filter(|scheme| !scheme.contains(['/', '?', '#']));
+    match scheme {
+        None | Some("http" | "https" | "mailto") => url,
+        Some(_) => CowStr::Borrowed("#"),
+    }
+}
+
+// the on-disk format nests the role enum, exports use a flat shape instead
+#[derive(Serialize)]
+struct ExportedMessage<'a> {
+    role: &'static str,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    model: Option<&'a str>,
+    content: &'a str,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    created_at: Option<DateTime<Utc>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    generation: Option<&'a Generation>,
+}
+
+#[derive(Serialize)]
+struct ExportedChat<'a> {
+    id: usize,
+    title: &'a str,
+    folder: &'a str,
+    tags: &'a [String],
+    messages: Vec<ExportedMessage<'a>>,
+}
+
+pub fn to_json(branch: &ChatBranch) -> Result<String> {
+    let chat = ExportedChat {
+        id: branch.id,
+        title: &branch.name,
+        folder: &branch.folder,
+        tags: &branch.tags,
+        messages: branch
+            .messages
+            .iter()
+            .map(|m| ExportedMessage {
+                role: match m.role {
+                    Role::User => "user",
+                    Role::Assistant(_) => "assistant",
+                },
+                model: match &m.role {
+                    Role::User => None,
+                    Role::Assistant(assistant) => Some(assistant.model.as_str()),
+                },
+                content: &m.content,
+                created_at: m.created_at,
+                generation: match &m.role {
+                    Role::User => None,
+                    Role::Assistant(assistant) => assistant.generation.as_ref(),
+                },
+            })
+            .collect(),
+    };
+    Ok(serde_json::to_string_pretty(&chat)?)
+}
+
+pub fn render(branch: &ChatBranch, format: ExportFormat) -> Result<String> {
+    Ok(match format {
+        ExportFormat::Markdown => to_markdown(branch),
+        ExportFormat::Html => to_html(branch),
+        ExportFormat::Json => to_json(branch)?,
+    })
+}
+
+/// write `branch` to a new file at `path`, the format is picked from the extension.
+/// a file that is already there is left alone
+pub fn export_to_file(branch: &ChatBranch, path: &Path) -> Result<()> {
+    let format = ExportFormat::from_path(path)?;
+    let rendered = render(branch, format)?;
+    let mut file = match File::options().write(true).create_new(true).open(path) {
+        Err(err) if err.kind() == ErrorKind::AlreadyExists => {
+            bail!("{} already exists, pick another name", path.display())
+        }
+        file => file?,
+    };
+    file.write_all(rendered.as_bytes())?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn headings_in_messages_go_two_levels_down() {
+        assert_eq!(
+            demote_headings("# Title\ntext\n## Sub\n"),
+            "### Title\ntext\n#### Sub\n"
+        );
+        assert_eq!(demote_headings("##### deep"), "###### deep");
+        assert_eq!(demote_headings("Title\n===\nafter"), "### Title\nafter");
+        assert_eq!(demote_headings("Sub\n---\n"), "#### Sub\n");
+    }
+
+    #[test]
+    fn headings_in_code_stay() {
+        let code = "```sh\n# a comment\n```\n";
+        assert_eq!(demote_headings(code), code);
+    }
+
+    #[test]
+    fn only_harmless_urls_are_kept() {
+        for url in [
+            "https://a.b/c",
+            "http://x",
+            "mailto:me@x.y",
+            "docs/x.html",
+            "#top",
+            "/a:b",
+        ] {
+            assert_eq!(safe_url(url.into()).as_ref(), url);
+        }
+        for url in [
+            "javascript:alert(1)",
+            "JavaScript:x",
+            "java\tscript:x",
+            "data:text/html,x",
+        ] {
+            assert_eq!(safe_url(url.into()).as_ref(), "#");
+        }
+    }
+
+    #[test]
+    fn html_export_drops_script_links_and_raw_html() {
+        let mut branch = ChatBranch::new(0, "t");
+        branch.messages = vec![Message::new(
+            Role::User,
+            "[x](javascript:alert(1)) ![i](javascript:y) <script>z</script> [ok](https://a.b)"
+                .into(),
+        )];
+        let html = to_html(&branch);
+        assert!(!html.contains("javascript:"));
+        assert!(!html.contains("<script>"));
+        assert!(html.contains("href=\"https://a.b\""));
+    }
+}
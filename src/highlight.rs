@@ -0,0 +1,172 @@
+// coloring fenced code with syntect's bundled grammars. the colors come from
+// the theme's code_theme, code wider than the chat is cut off and scrolls sideways
+use crate::markdown::CodeBlock;
+use crate::theme;
+use crate::ui_config;
+use anyhow::{Context, Result};
+use ratatui::{
+    style::{Color, Modifier, Style},
+    text::{Line, Span},
+};
+use std::sync::LazyLock;
+use syntect::easy::HighlightLines;
+use syntect::highlighting::{FontStyle, Theme, ThemeSet};
+use syntect::parsing::SyntaxSet;
+use syntect::util::LinesWithEndings;
+use unicode_width::UnicodeWidthChar;
+
+pub const DEFAULT_THEME: &str = "base16-ocean.dark";
+
+static SYNTAXES: LazyLock<SyntaxSet> = LazyLock::new(SyntaxSet::load_defaults_newlines);
+static THEME: LazyLock<Theme> = LazyLock::new(|| {
+    find_theme(&theme::get().syntax)
+        .or_else(|_| find_theme(DEFAULT_THEME))
+        .unwrap_or_default()
+});
+
+/// a bundled theme by name, or a .tmTheme file
+pub fn find_theme(name: &str) -> Result<Theme> {
+    let mut themes = ThemeSet::load_defaults();
+    if let Some(theme) = themes.themes.remove(name) {
+        return Ok(theme);
+    }
+    ThemeSet::get_theme(name).with_context(|| {
+        let names: Vec<&str> = themes.themes.keys().map(String::as_str).collect();
+        format!(
+            "no theme '{name}', there are {} or a .tmTheme file",
+            names.join(", ")
+        )
+    })
+}
+
+/// the lines of `code` colored as `lang`, plain when there is no grammar for it
+pub fn highlight(code: &str, lang: &str) -> Vec<Line<'static>> {
+    let syntax = SYNTAXES
+        .find_syntax_by_token(lang)
+        .unwrap_or_else(|| SYNTAXES.find_syntax_plain_text());
+    let mut highlighter = HighlightLines::new(syntax, &THEME);
+    // tabs would be as wide as the terminal likes, not as wide as we measure them
+    let code = code.replace('\t', "    ");
+    LinesWithEndings::from(&code)
+        .map(|line| match highlighter.highlight_line(line, &SYNTAXES) {
+            Ok(ranges) => Line::from(
+                ranges
+                    .into_iter()
+                    .map(|(style, text)| {
+                        Span::styled(
+                            text.trim_end_matches(['\n', '\r']).to_string(),
+                            to_style(style),
+                        )
+                    })
+                    .collect::<Vec<_>>(),
+            ),
+            Err(_) => Line::from(line.trim_end_matches(['\n', '\r']).to_string()),
+        })
+        .collect()
+}
+
+// the background is left to the terminal, the colors are fitted to it
+fn to_style(style: syntect::highlighting::Style) -> Style {
+    let mut out = Style::default().fg(Color::Rgb(
+        style.foreground.r,
+        style.foreground.g,
+        style.foreground.b,
+    ));
+    if style.font_style.contains(FontStyle::BOLD) {
+        out = out.add_modifier(Modifier::BOLD);
+    }
+    if style.font_style.contains(FontStyle::ITALIC) {
+        out = out.add_modifier(Modifier::ITALIC);
+    }
+    if style.font_style.contains(FontStyle::UNDERLINE) {
+        out = out.add_modifier(Modifier::UNDERLINED);
+    }
+    theme::get().depth.fit_style(out)
+}
+
+/// a code block as it is shown in a message: the fences, `number` when the
+/// message has several blocks, and every line `scroll` columns in and at most
+/// `width` wide so nothing wraps
+pub fn code_block_lines(
+    block: &CodeBlock,
+    number: Option<usize>,
+    width: usize,
+    scroll: usize,
+) -> Vec<Line<'static>> {
+    let theme = theme::get();
+    let mut lines = vec![Line::styled(fence(block, number), theme.code_fence)];
+
+    let code = highlight(block.code.trim_end_matches('\n'), &block.lang);
+    let digits = code.len().to_string().len();
+    let line_numbers = ui_config::get().line_numbers;
+    for (i, line) in code.into_iter().enumerate() {
+        let mut spans = Vec::new();
+        let mut room = width;
+        if line_numbers {
+            let gutter = format!("{:>digits$} │ ", i + 1);
+            room = room.saturating_sub(gutter.chars().count());
+            spans.push(Span::styled(gutter, theme.line_number));
+        }
+        spans.extend(clip(line.spans, scroll, room));
+        lines.push(Line::from(spans));
+    }
+    lines.push(Line::styled("```", theme.code_fence));
+    lines
+}
+
+/// a collapsed code block, its opening fence and how many lines it hides
+pub fn collapsed_code_line(block: &CodeBlock, number: Option<usize>) -> Line<'static> {
+    let lines = block.code.trim_end_matches('\n').lines().count();
+    Line::styled(
+        format!("{}  ▸ {lines} lines", fence(block, number)),
+        theme::get().code_fence,
+    )
+}
+
+fn fence(block: &CodeBlock, number: Option<usize>) -> String {
+    match number {
+        Some(n) => format!("```{}  [{n}]", block.lang),
+        None => format!("```{}", block.lang),
+    }
+}
+
+/// `spans` without their first `skip` columns and cut after `width` more
+fn clip(spans: Vec<Span<'static>>, skip: usize, width: usize) -> Vec<Span<'static>> {
+    let end = skip.saturating_add(width);
+    let mut column = 0;
+    let mut out = Vec::new();
+    for span in spans {
+        let mut text = String::new();
+        for c in span.content.chars() {
+            let w = c.width().unwrap_or(0);
+            if column >= skip && column + w <= end {
+                text.push(c);
+            }
+            column += w;
+        }
+        if !text.is_empty() {
+            out.push(Span::styled(text, span.style));
+        }
+        if column >= end {
+            break;
+        }
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn clipping_counts_columns_not_chars() {
+        let spans = vec![Span::raw("ab"), Span::raw("漢字c"), Span::raw("de")];
+        let text = |spans: Vec<Span<'static>>| -> Vec<String> {
+            spans.into_iter().map(|s| s.content.into_owned()).collect()
+        };
+        assert_eq!(text(clip(spans.clone(), 1, 4)), ["b", "漢"]);
+        // a wide character cut in half is left out
+        assert_eq!(text(clip(spans.clone(), 3, 4)), ["字c"]);
+        assert_eq!(text(clip(spans, 0, 100)), ["ab", "漢字c", "de"]);
+    }
+}
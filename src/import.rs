@@ -0,0 +1,467 @@
+// reading conversation exports of other chat apps.
+// both ChatGPT and Open WebUI store a conversation as a tree of messages (every
+// regenerated or edited message starts a new branch), chats here are linear so
+// every leaf of the tree becomes its own chat, the one that was open last keeps
+// the plain title.
+use crate::chat_branch::ChatBranch;
+use crate::chat_store::ChatStore;
+use crate::chat_structs::{Assistant, Message, Role};
+use anyhow::{Context, Result, bail};
+use chrono::{DateTime, Utc};
+use serde_json::Value;
+use std::collections::HashMap;
+use std::{fs, path::Path};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ImportSource {
+    ChatGpt,
+    OpenWebUi,
+}
+
+impl ImportSource {
+    pub fn from_name(name: &str) -> Result<Self> {
+        match name.to_lowercase().replace(['-', '_', ' '], "").as_str() {
+            "chatgpt" | "openai" => Ok(ImportSource::ChatGpt),
+            "openwebui" | "webui" => Ok(ImportSource::OpenWebUi),
+            other => bail!("unknown import format '{other}' (chatgpt or openwebui)"),
+        }
+    }
+
+    fn tag(self) -> &'static str {
+        match self {
+            ImportSource::ChatGpt => "chatgpt",
+            ImportSource::OpenWebUi => "open-webui",
+        }
+    }
+
+    /// look at the first conversation to tell the formats apart
+    fn detect(conversations: &[Value]) -> Option<Self> {
+        let first = conversations.first()?;
+        if first.get("mapping").is_some() {
+            Some(ImportSource::ChatGpt)
+        } else if first.get("chat").is_some() || first.get("history").is_some() {
+            Some(ImportSource::OpenWebUi)
+        } else {
+            None
+        }
+    }
+}
+
+struct Node {
+    parent: Option<String>,
+    children: Vec<String>,
+    message: Option<Message>,
+}
+
+/// seconds (or milliseconds, some exports use those) since the epoch
+fn timestamp(value: Option<&Value>) -> Option<DateTime<Utc>> {
+    let secs = value?.as_f64()?;
+    let secs = if secs > 1e12 { secs / 1000.0 } else { secs };
+    #[allow(clippy::cast_possible_truncation)]
+    DateTime::from_timestamp_millis((secs * 1000.0) as i64)
+}
+
+fn make_message(
+    role: &str,
+    content: String,
+    model: &str,
+    created: Option<DateTime<Utc>>,
+) -> Option<Message> {
+    let role = match role {
+        "user" => Role::User,
+        "assistant" => Role::Assistant(Assistant::new(model)),
+        // system prompts and tool calls have no place in a chat here
+        _ => return None,
+    };
+    if content.trim().is_empty() {
+        return None;
+    }
+    Some(Message {
+        role,
+        content,
+        created_at: created,
+        bookmarked: false,
+        collapsed: None,
+        collapsed_code: Vec::new(),
+    })
+}
+
+fn chatgpt_nodes(conversation: &Value) -> HashMap<String, Node> {
+    let mut nodes = HashMap::new();
+    let Some(mapping) = conversation.get("mapping").and_then(Value::as_object) else {
+        return nodes;
+    };
+    for (id, node) in mapping {
+        let message = node.get("message").filter(|m| !m.is_null()).and_then(|m| {
+            let role = m.pointer("/author/role")?.as_str()?;
+            // parts can also hold images and other attachments, keep the text
+            let content = m
+                .pointer("/content/parts")
+                .and_then(Value::as_array)
+                .map(|parts| {
+                    parts
+                        .iter()
+                        .filter_map(Value::as_str)
+                        .collect::<Vec<_>>()
+                        .join("\n")
+                })
+                .or_else(|| m.pointer("/content/text")?.as_str().map(str::to_string))?;
+            let model = m
+                .pointer("/metadata/model_slug")
+                .and_then(Value::as_str)
+                .unwrap_or("chatgpt");
+            make_message(role, content, model, timestamp(m.get("create_time")))
+        });
+        nodes.insert(
+            id.clone(),
+            Node {
+                parent: node
+                    .get("parent")
+                    .and_then(Value::as_str)
+                    .map(str::to_string),
+                children: string_list(node.get("children")),
+                message,
+            },
+        );
+    }
+    nodes
+}
+
+fn open_webui_nodes(chat: &Value) -> HashMap<String, Node> {
+    let default_model = chat
+        .pointer("/models/0")
+        .and_then(Value::as_str)
+        .unwrap_or("open-webui");
+    let mut nodes: HashMap<String, Node> = HashMap::new();
+
+    let Some(history) = chat.pointer("/history/messages").and_then(Value::as_object) else {
+        // old exports only have the linear list
+        let messages = chat.get("messages").and_then(Value::as_array);
+        let mut parent: Option<String> = None;
+        for (i, m) in messages.into_iter().flatten().enumerate() {
+            let id = i.to_string();
+            if let Some(p) = &parent
+                && let Some(node) = nodes.get_mut(p)
+            {
+                node.children.push(id.clone());
+            }
+            nodes.insert(
+                id.clone(),
+                Node {
+                    parent: parent.replace(id),
+                    children: Vec::new(),
+                    message: open_webui_message(m, default_model),
+                },
+            );
+        }
+        return nodes;
+    };
+
+    for (id, m) in history {
+        nodes.insert(
+            id.clone(),
+            Node {
+                parent: m
+                    .get("parentId")
+                    .and_then(Value::as_str)
+                    .map(str::to_string),
+                children: string_list(m.get("childrenIds")),
+                message: open_webui_message(m, default_model),
+            },
+        );
+    }
+    nodes
+}
+
+fn open_webui_message(m: &Value, default_model: &str) -> Option<Message> {
+    let role = m.get("role")?.as_str()?;
+    let content = m.get("content")?.as_str()?.to_string();
+    let model = m
+        .get("modelName")
+        .or_else(|| m.get("model"))
+        .and_then(Value::as_str)
+        .unwrap_or(default_model);
+    make_message(role, content, model, timestamp(m.get("timestamp")))
+}
+
+fn string_list(value: Option<&Value>) -> Vec<String> {
+    value
+        .and_then(Value::as_array)
+        .map(|items| {
+            items
+                .iter()
+                .filter_map(Value::as_str)
+                .map(str::to_string)
+                .collect()
+        })
+        .unwrap_or_default()
+}
+
+/// messages on the way from the root of the tree down to `leaf`
+fn path_to(nodes: &HashMap<String, Node>, leaf: &str) -> Vec<Message> {
+    let mut messages = Vec::new();
+    let mut current = Some(leaf);
+    // a broken export could contain a cycle
+    let mut steps = 0;
+    while let Some(id) = current
+        && let Some(node) = nodes.get(id)
+        && steps <= nodes.len()
+    {
+        messages.extend(node.message.clone());
+        current = node.parent.as_deref();
+        steps += 1;
+    }
+    messages.reverse();
+    messages
+}
+
+/// leaves in the order the conversation tree lists them
+fn leaves(nodes: &HashMap<String, Node>) -> Vec<&str> {
+    let mut roots: Vec<&str> = nodes
+        .iter()
+        .filter(|(_, n)| n.parent.as_ref().is_none_or(|p| !nodes.contains_key(p)))
+        .map(|(id, _)| id.as_str())
+        .collect();
+    roots.sort_unstable();
+
+    let mut leaves = Vec::new();
+    let mut stack: Vec<&str> = roots.into_iter().rev().collect();
+    while let Some(id) = stack.pop() {
+        if leaves.len() + stack.len() > nodes.len() {
+            break;
+        }
+        let children: Vec<&str> = nodes[id]
+            .children
+            .iter()
+            .filter(|c| nodes.contains_key(c.as_str()))
+            .map(String::as_str)
+            .collect();
+        if children.is_empty() {
+            leaves.push(id);
+        }
+        stack.extend(children.into_iter().rev());
+    }
+    leaves
+}
+
+/// one chat per branch of the tree, the one ending at `current` comes first
+fn linearize(
+    title: &str,
+    nodes: &HashMap<String, Node>,
+    current: Option<&str>,
+) -> Vec<(String, Vec<Message>)> {
+    let leaves = leaves(nodes);
+    let main = current
+        .filter(|c| nodes.contains_key(*c))
+        .or_else(|| leaves.last().copied());
+    let Some(main) = main else {
+        return Vec::new();
+    };
+
+    let mut chats = vec![(title.to_string(), path_to(nodes, main))];
+    for leaf in leaves.into_iter().filter(|l| *l != main) {
+        let messages = path_to(nodes, leaf);
+        if !chats
+            .iter()
+            .any(|(_, m)| m.len() == messages.len() && same_messages(m, &messages))
+        {
+            chats.push((format!("{title} (branch {})", chats.len() + 1), messages));
+        }
+    }
+    chats.retain(|(_, messages)| !messages.is_empty());
+    chats
+}
+
+fn same_messages(a: &[Message], b: &[Message]) -> bool {
+    a.iter().zip(b).all(|(x, y)| x.content == y.content)
+}
+
+/// parse an export, the chats come back with id 0 and have to be numbered by the caller
+pub fn parse(data: &str, source: Option<ImportSource>) -> Result<(ImportSource, Vec<ChatBranch>)> {
+    let value: Value = serde_json::from_str(data).context("not a json file")?;
+    let conversations = match value {
+        Value::Array(items) => items,
+        single @ Value::Object(_) => vec![single],
+        _ => bail!("expected a list of conversations"),
+    };
+    let source = match source {
+        Some(source) => source,
+        None => ImportSource::detect(&conversations)
+            .context("can't tell whether this is a ChatGPT or an Open WebUI export")?,
+    };
+
+    let mut branches = Vec::new();
+    for conversation in &conversations {
+        let (title, nodes, current) = match source {
+            ImportSource::ChatGpt => (
+                conversation.get("title").and_then(Value::as_str),
+                chatgpt_nodes(conversation),
+                conversation.get("current_node").and_then(Value::as_str),
+            ),
+            ImportSource::OpenWebUi => {
+                // the list export wraps every chat, a single chat export doesn't
+                let chat = conversation.get("chat").unwrap_or(conversation);
+                (
+                    conversation
+                        .get("title")
+                        .or_else(|| chat.get("title"))
+                        .and_then(Value::as_str),
+                    open_webui_nodes(chat),
+                    chat.pointer("/history/currentId").and_then(Value::as_str),
+                )
+            }
+        };
+        let title = title
+            .filter(|t| !t.trim().is_empty())
+            .unwrap_or("Imported Chat");
+        for (name, messages) in linearize(title, &nodes, current) {
+            let mut branch = ChatBranch::new(0, &name);
+            branch.messages = messages;
+            branch.folder = "imported".to_string();
+            branch.tags = vec![source.tag().to_string()];
+            branches.push(branch);
+        }
+    }
+    Ok((source, branches))
+}
+
+/// store every chat in the export at `path` and add it to the index `branches`,
+/// returns how many were added
+pub fn import_into(
+    path: &Path,
+    source: Option<ImportSource>,
+    store: &ChatStore,
+    branches: &mut Vec<ChatBranch>,
+    trash: &[ChatBranch],
+) -> Result<usize> {
+    let data =
+        fs::read_to_string(path).with_context(|| format!("can't read {}", path.display()))?;
+    let (_, imported) = parse(&data, source)?;
+    let count = imported.len();
+    for branch in imported {
+        let entry = store.add_chat(branch, branches, trash)?;
+        branches.push(entry);
+    }
+    Ok(count)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn contents(branch: &ChatBranch) -> Vec<&str> {
+        branch.messages.iter().map(|m| m.content.as_str()).collect()
+    }
+
+    #[test]
+    fn every_chatgpt_branch_becomes_a_chat() {
+        let data = r#"[{
+            "title": "Cats",
+            "current_node": "a1",
+            "mapping": {
+                "root": { "parent": null, "children": ["sys"], "message": null },
+                "sys": { "parent": "root", "children": ["u1"], "message": {
+                    "author": { "role": "system" }, "content": { "parts": ["be nice"] } } },
+                "u1": { "parent": "sys", "children": ["a2", "a1"], "message": {
+                    "author": { "role": "user" }, "create_time": 1700000000.5,
+                    "content": { "parts": ["hi", "there"] } } },
+                "a1": { "parent": "u1", "children": [], "message": {
+                    "author": { "role": "assistant" }, "metadata": { "model_slug": "gpt-4o" },
+                    "content": { "parts": ["hello"] } } },
+                "a2": { "parent": "u1", "children": [], "message": {
+                    "author": { "role": "assistant" }, "content": { "parts": ["hey"] } } }
+            }
+        }]"#;
+        let (source, chats) = parse(data, None).unwrap();
+        assert_eq!(source, ImportSource::ChatGpt);
+        let names: Vec<&str> = chats.iter().map(|c| c.name.as_str()).collect();
+        assert_eq!(names, ["Cats", "Cats (branch 2)"]);
+        assert_eq!(contents(&chats[0]), ["hi\nthere", "hello"]);
+        assert_eq!(contents(&chats[1]), ["hi\nthere", "hey"]);
+        assert_eq!(
+            chats[0].messages[0].created_at.unwrap().timestamp_millis(),
+            1_700_000_000_500
+        );
+        match &chats[0].messages[1].role {
+            Role::Assistant(assistant) => assert_eq!(assistant.model, "gpt-4o"),
+            Role::User => panic!("the reply should be the assistant's"),
+        }
+        assert_eq!(chats[1].folder, "imported");
+        assert_eq!(chats[1].tags, ["chatgpt"]);
+    }
+
+    #[test]
+    fn open_webui_trees_and_old_lists_are_read() {
+        let data = r#"[{
+            "title": "Tree",
+            "chat": {
+                "models": ["llama3"],
+                "history": {
+                    "currentId": "b",
+                    "messages": {
+                        "q": { "parentId": null, "childrenIds": ["a", "b"],
+                               "role": "user", "content": "question", "timestamp": 1700000000000 },
+                        "a": { "parentId": "q", "childrenIds": [], "role": "assistant",
+                               "content": "first try" },
+                        "b": { "parentId": "q", "childrenIds": [], "role": "assistant",
+                               "content": "second try", "modelName": "qwen" }
+                    }
+                }
+            }
+        }, {
+            "chat": { "title": "List", "messages": [
+                { "role": "user", "content": "one" },
+                { "role": "assistant", "content": "  " },
+                { "role": "assistant", "content": "two" }
+            ] }
+        }]"#;
+        let (source, chats) = parse(data, None).unwrap();
+        assert_eq!(source, ImportSource::OpenWebUi);
+        let names: Vec<&str> = chats.iter().map(|c| c.name.as_str()).collect();
+        assert_eq!(names, ["Tree", "Tree (branch 2)", "List"]);
+        assert_eq!(contents(&chats[0]), ["question", "second try"]);
+        assert_eq!(contents(&chats[1]), ["question", "first try"]);
+        assert_eq!(contents(&chats[2]), ["one", "two"]);
+        let models: Vec<String> = chats
+            .iter()
+            .map(|c| match &c.messages[1].role {
+                Role::Assistant(assistant) => assistant.model.clone(),
+                Role::User => String::new(),
+            })
+            .collect();
+        assert_eq!(models, ["qwen", "llama3", "open-webui"]);
+        assert_eq!(
+            chats[0].messages[0].created_at.unwrap().timestamp(),
+            1_700_000_000
+        );
+    }
+
+    #[test]
+    fn a_cycle_in_the_tree_ends() {
+        let data = r#"{
+            "title": "Loop",
+            "current_node": "a",
+            "mapping": {
+                "a": { "parent": "b", "children": ["b"], "message": {
+                    "author": { "role": "user" }, "content": { "parts": ["a"] } } },
+                "b": { "parent": "a", "children": ["a"], "message": {
+                    "author": { "role": "assistant" }, "content": { "parts": ["b"] } } }
+            }
+        }"#;
+        let (_, chats) = parse(data, Some(ImportSource::ChatGpt)).unwrap();
+        assert_eq!(chats.len(), 1);
+        assert!(chats[0].messages.len() <= 3);
+    }
+
+    #[test]
+    fn unknown_exports_are_refused() {
+        assert!(parse(r#"[{ "id": 1 }]"#, None).is_err());
+        assert!(parse("42", None).is_err());
+        assert!(parse("not json", None).is_err());
+        assert_eq!(
+            ImportSource::from_name("Open-WebUI").unwrap(),
+            ImportSource::OpenWebUi
+        );
+        assert!(ImportSource::from_name("claude").is_err());
+    }
+}
@@ -0,0 +1,698 @@
+// which keys run which action, read once from keymap.json. a preset is picked
+// first and the file's own bindings replace the preset's for the actions it names:
+//
+//   { "preset": "vim", "chat": { "scroll_up": ["pgup", "ctrl-y"] } }
+//
+// keys are written like "ctrl-k", "alt-e", "shift-enter", "^K" or "G", a chord is
+// several of them with spaces between ("ctrl-x ctrl-c"), an empty list unbinds.
+// typing, moving the cursor and picking from lists stay where they are.
+use crate::action::Action;
+use anyhow::{Result, bail};
+use crossterm::event::{KeyCode, KeyEvent, KeyModifiers};
+use serde::Deserialize;
+use std::collections::BTreeMap;
+use std::fmt;
+use std::fs;
+use std::path::Path;
+use std::str::FromStr;
+use std::sync::OnceLock;
+
+/// where a binding works. global ones work on every screen, but the chat and
+/// sidebar ones come first there
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum KeyContext {
+    Global,
+    // typing in the chat input
+    Chat,
+    // the sidebar has focus
+    Sidebar,
+    // moving between messages after select_messages
+    Messages,
+}
+
+/// one key with its modifiers, shift is left out for characters since the
+/// character already says it
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct KeyPress {
+    pub code: KeyCode,
+    pub modifiers: KeyModifiers,
+}
+
+#[derive(Debug, Clone)]
+pub struct Binding {
+    pub context: KeyContext,
+    pub keys: Vec<KeyPress>,
+    pub action: Action,
+}
+
+pub struct Keymap {
+    pub bindings: Vec<Binding>,
+    // bad entries and keys bound twice, the main menu lists them
+    pub problems: Vec<String>,
+}
+
+/// what a key did
+pub enum Resolved {
+    Action(Action),
+    // part of a chord, or the end of one that isn't bound and gets dropped
+    Waiting,
+    // not bound, the screen handles it as before
+    Unbound,
+}
+
+#[derive(Deserialize, Default)]
+#[serde(default)]
+struct KeymapFile {
+    preset: Option<String>,
+    global: BTreeMap<String, Vec<String>>,
+    chat: BTreeMap<String, Vec<String>>,
+    sidebar: BTreeMap<String, Vec<String>>,
+    messages: BTreeMap<String, Vec<String>>,
+}
+
+type Table = &'static [(KeyContext, Action, &'static [&'static str])];
+
+// keys the screens handle themselves, binding one of them takes it away
+const HARD_CODED: &[(KeyContext, &str, &str)] = &[
+    (KeyContext::Chat, "enter", "sending the prompt"),
+    (KeyContext::Chat, "shift-enter", "a new line"),
+    (KeyContext::Chat, "alt-enter", "a new line"),
+    (KeyContext::Chat, "ctrl-j", "a new line"),
+    (KeyContext::Chat, "ctrl-p", "the previous prompt"),
+    (KeyContext::Chat, "ctrl-n", "the next prompt"),
+    (KeyContext::Chat, "up", "the previous line or prompt"),
+    (KeyContext::Chat, "down", "the next line or prompt"),
+    (KeyContext::Chat, "left", "moving the cursor"),
+    (KeyContext::Chat, "right", "moving the cursor"),
+    (KeyContext::Chat, "ctrl-left", "going back a word"),
+    (KeyContext::Chat, "ctrl-right", "going forward a word"),
+    (KeyContext::Chat, "alt-b", "going back a word"),
+    (KeyContext::Chat, "alt-f", "going forward a word"),
+    (KeyContext::Chat, "home", "the start of the line"),
+    (KeyContext::Chat, "end", "the end of the line"),
+    (KeyContext::Chat, "backspace", "deleting"),
+    (KeyContext::Chat, "del", "deleting"),
+    (
+        KeyContext::Chat,
+        "ctrl-w",
+        "deleting the word before the cursor",
+    ),
+    (
+        KeyContext::Chat,
+        "ctrl-u",
+        "deleting to the start of the line",
+    ),
+    (KeyContext::Chat, "alt-d", "deleting the next word"),
+    (KeyContext::Sidebar, "enter", "opening the chat or folder"),
+    (KeyContext::Sidebar, "space", "opening and closing folders"),
+    (KeyContext::Sidebar, "h", "opening and closing folders"),
+    (KeyContext::Sidebar, "l", "opening and closing folders"),
+    (KeyContext::Sidebar, "left", "opening and closing folders"),
+    (KeyContext::Sidebar, "right", "opening and closing folders"),
+];
+
+const DEFAULT: Table = &[
+    (KeyContext::Global, Action::Palette, &["ctrl-k"]),
+    (KeyContext::Global, Action::ToggleMouse, &["alt-m"]),
+    (KeyContext::Chat, Action::ToggleSidebar, &["tab"]),
+    (KeyContext::Chat, Action::OpenSearch, &["ctrl-f"]),
+    (KeyContext::Chat, Action::ToggleTimestamps, &["ctrl-t"]),
+    (KeyContext::Chat, Action::MainMenu, &["esc"]),
+    (KeyContext::Chat, Action::EditPrompt, &["ctrl-e"]),
+    (KeyContext::Chat, Action::EditMessage, &["alt-e"]),
+    (KeyContext::Chat, Action::HistorySearch, &["ctrl-r"]),
+    (KeyContext::Chat, Action::ScrollUp, &["pgup"]),
+    (KeyContext::Chat, Action::ScrollDown, &["pgdn"]),
+    (KeyContext::Chat, Action::SelectMessages, &["alt-s"]),
+    (KeyContext::Chat, Action::CopyMessage, &["alt-y"]),
+    (KeyContext::Chat, Action::CopyCodeBlock, &["alt-c"]),
+    (KeyContext::Chat, Action::ToggleCollapse, &["alt-z"]),
+    (KeyContext::Chat, Action::ToggleCodeCollapse, &["alt-Z"]),
+    (KeyContext::Chat, Action::ScrollTop, &["ctrl-home"]),
+    (KeyContext::Chat, Action::ScrollBottom, &["ctrl-end"]),
+    (KeyContext::Chat, Action::ScrollLeft, &["alt-h"]),
+    (KeyContext::Chat, Action::ScrollRight, &["alt-l"]),
+    (KeyContext::Chat, Action::FindInChat, &["alt-/"]),
+    (KeyContext::Chat, Action::FindNext, &["alt-n"]),
+    (KeyContext::Chat, Action::FindPrevious, &["alt-N"]),
+    (KeyContext::Sidebar, Action::ToggleSidebar, &["tab", "esc"]),
+    (KeyContext::Sidebar, Action::SelectNext, &["j", "down"]),
+    (KeyContext::Sidebar, Action::SelectPrevious, &["k", "up"]),
+    (KeyContext::Sidebar, Action::SelectFirst, &["home"]),
+    (KeyContext::Sidebar, Action::SelectLast, &["end"]),
+    (KeyContext::Sidebar, Action::MoveChatDown, &["J"]),
+    (KeyContext::Sidebar, Action::MoveChatUp, &["K"]),
+    (KeyContext::Sidebar, Action::NewChat, &["n"]),
+    (KeyContext::Sidebar, Action::RenameChat, &["r"]),
+    (KeyContext::Sidebar, Action::DeleteChat, &["d"]),
+    (KeyContext::Sidebar, Action::ArchiveChat, &["a"]),
+    (KeyContext::Sidebar, Action::ShowArchived, &["A"]),
+    (KeyContext::Sidebar, Action::SortByActivity, &["s"]),
+    (KeyContext::Sidebar, Action::PinChat, &["p"]),
+    (KeyContext::Sidebar, Action::MoveToFolder, &["m"]),
+    (KeyContext::Sidebar, Action::TagChat, &["t"]),
+    (KeyContext::Sidebar, Action::ExportChat, &["e"]),
+    (KeyContext::Sidebar, Action::ImportChats, &["i"]),
+    (KeyContext::Sidebar, Action::FilterByTag, &["f"]),
+    (KeyContext::Sidebar, Action::Undo, &["u"]),
+    (KeyContext::Sidebar, Action::ShowTrash, &["T"]),
+    (
+        KeyContext::Messages,
+        Action::SelectMessages,
+        &["esc", "q", "alt-s"],
+    ),
+    (KeyContext::Messages, Action::SelectNext, &["j", "down"]),
+    (KeyContext::Messages, Action::SelectPrevious, &["k", "up"]),
+    (KeyContext::Messages, Action::SelectFirst, &["home"]),
+    (KeyContext::Messages, Action::SelectLast, &["end"]),
+    (KeyContext::Messages, Action::CopyMessage, &["y"]),
+    (KeyContext::Messages, Action::CopyCodeBlock, &["c"]),
+    (KeyContext::Messages, Action::CopyChat, &["Y"]),
+    (KeyContext::Messages, Action::DeleteMessage, &["d"]),
+    (KeyContext::Messages, Action::EditMessage, &["e"]),
+    (KeyContext::Messages, Action::RegenerateMessage, &["r"]),
+    (KeyContext::Messages, Action::ForkFromMessage, &["f"]),
+    (KeyContext::Messages, Action::BookmarkMessage, &["b"]),
+    (
+        KeyContext::Messages,
+        Action::ViewRawMessage,
+        &["v", "enter"],
+    ),
+    (KeyContext::Messages, Action::ToggleCollapse, &["z"]),
+    (KeyContext::Messages, Action::ToggleCodeCollapse, &["Z"]),
+    (KeyContext::Messages, Action::NextBookmark, &["]"]),
+    (KeyContext::Messages, Action::PreviousBookmark, &["["]),
+    (KeyContext::Messages, Action::ScrollUp, &["pgup"]),
+    (KeyContext::Messages, Action::ScrollDown, &["pgdn"]),
+    (KeyContext::Messages, Action::ScrollLeft, &["h", "left"]),
+    (KeyContext::Messages, Action::ScrollRight, &["l", "right"]),
+    (KeyContext::Messages, Action::FindInChat, &["/"]),
+    (KeyContext::Messages, Action::FindNext, &["n"]),
+    (KeyContext::Messages, Action::FindPrevious, &["N"]),
+];
+
+// on top of the default one. ctrl-u and ctrl-w keep deleting like in insert mode
+const VIM: Table = &[
+    (KeyContext::Chat, Action::ScrollUp, &["pgup", "ctrl-b"]),
+    (KeyContext::Chat, Action::ScrollDown, &["pgdn", "ctrl-f"]),
+    (KeyContext::Chat, Action::OpenSearch, &["ctrl-s"]),
+    (
+        KeyContext::Sidebar,
+        Action::ToggleSidebar,
+        &["tab", "esc", "q"],
+    ),
+    (KeyContext::Sidebar, Action::SelectFirst, &["g g", "home"]),
+    (KeyContext::Sidebar, Action::SelectLast, &["G", "end"]),
+    (KeyContext::Sidebar, Action::DeleteChat, &["d d"]),
+    (KeyContext::Messages, Action::SelectFirst, &["g g", "home"]),
+    (KeyContext::Messages, Action::SelectLast, &["G", "end"]),
+    (KeyContext::Messages, Action::DeleteMessage, &["d d"]),
+];
+
+const EMACS: Table = &[
+    (KeyContext::Global, Action::Palette, &["alt-x"]),
+    (KeyContext::Global, Action::Quit, &["ctrl-x ctrl-c"]),
+    (
+        KeyContext::Chat,
+        Action::ToggleSidebar,
+        &["tab", "ctrl-x o"],
+    ),
+    (KeyContext::Chat, Action::OpenSearch, &["ctrl-s"]),
+    (KeyContext::Chat, Action::EditPrompt, &["ctrl-x ctrl-e"]),
+    (KeyContext::Chat, Action::ScrollUp, &["pgup", "alt-v"]),
+    (KeyContext::Chat, Action::ScrollDown, &["pgdn", "ctrl-v"]),
+    (
+        KeyContext::Sidebar,
+        Action::ToggleSidebar,
+        &["tab", "esc", "ctrl-g", "ctrl-x o"],
+    ),
+    (KeyContext::Sidebar, Action::SelectNext, &["ctrl-n", "down"]),
+    (
+        KeyContext::Sidebar,
+        Action::SelectPrevious,
+        &["ctrl-p", "up"],
+    ),
+    (KeyContext::Sidebar, Action::SelectFirst, &["alt-<", "home"]),
+    (KeyContext::Sidebar, Action::SelectLast, &["alt->", "end"]),
+    (
+        KeyContext::Messages,
+        Action::SelectMessages,
+        &["esc", "ctrl-g", "alt-s"],
+    ),
+    (
+        KeyContext::Messages,
+        Action::SelectNext,
+        &["ctrl-n", "down"],
+    ),
+    (
+        KeyContext::Messages,
+        Action::SelectPrevious,
+        &["ctrl-p", "up"],
+    ),
+    (
+        KeyContext::Messages,
+        Action::SelectFirst,
+        &["alt-<", "home"],
+    ),
+    (KeyContext::Messages, Action::SelectLast, &["alt->", "end"]),
+    (KeyContext::Messages, Action::CopyMessage, &["alt-w", "y"]),
+];
+
+static KEYMAP: OnceLock<Keymap> = OnceLock::new();
+
+/// the keymap from keymap.json, read the first time it is needed
+pub fn get() -> &'static Keymap {
+    KEYMAP.get_or_init(|| Keymap::load(Path::new("keymap.json")))
+}
+
+impl KeyContext {
+    pub fn name(self) -> &'static str {
+        match self {
+            KeyContext::Global => "global",
+            KeyContext::Chat => "chat",
+            KeyContext::Sidebar => "sidebar",
+            KeyContext::Messages => "messages",
+        }
+    }
+
+    // global keys are also pressed while typing
+    fn overlaps(self, other: KeyContext) -> bool {
+        self == other || self == KeyContext::Global || other == KeyContext::Global
+    }
+}
+
+impl KeyPress {
+    pub fn new(code: KeyCode, modifiers: KeyModifiers) -> Self {
+        let mut modifiers =
+            modifiers & (KeyModifiers::CONTROL | KeyModifiers::ALT | KeyModifiers::SHIFT);
+        let code = match code {
+            KeyCode::Char(c) if modifiers.contains(KeyModifiers::SHIFT) => {
+                KeyCode::Char(c.to_ascii_uppercase())
+            }
+            KeyCode::Tab if modifiers.contains(KeyModifiers::SHIFT) => KeyCode::BackTab,
+            code => code,
+        };
+        if matches!(code, KeyCode::Char(_) | KeyCode::BackTab) {
+            modifiers.remove(KeyModifiers::SHIFT);
+        }
+        KeyPress { code, modifiers }
+    }
+
+    /// a key that types something in a text field
+    fn types(self) -> bool {
+        matches!(self.code, KeyCode::Char(_))
+            && !self
+                .modifiers
+                .intersects(KeyModifiers::CONTROL | KeyModifiers::ALT)
+    }
+}
+
+impl From<KeyEvent> for KeyPress {
+    fn from(key: KeyEvent) -> Self {
+        KeyPress::new(key.code, key.modifiers)
+    }
+}
+
+impl FromStr for KeyPress {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        let mut modifiers = KeyModifiers::NONE;
+        let mut rest = s;
+        // "^k" is ctrl-k, not ctrl-shift-k
+        let mut caret = false;
+        loop {
+            if let Some(after) = rest.strip_prefix('^').filter(|after| !after.is_empty()) {
+                modifiers |= KeyModifiers::CONTROL;
+                caret = true;
+                rest = after;
+                continue;
+            }
+            let Some((name, after)) = rest
+                .split_once(['-', '+'])
+                .filter(|(_, after)| !after.is_empty())
+            else {
+                break;
+            };
+            modifiers |= match name.to_ascii_lowercase().as_str() {
+                "ctrl" | "control" | "c" => KeyModifiers::CONTROL,
+                "alt" | "meta" | "m" => KeyModifiers::ALT,
+                "shift" | "s" => KeyModifiers::SHIFT,
+                _ => break,
+            };
+            rest = after;
+        }
+        let code = match rest.to_ascii_lowercase().as_str() {
+            "enter" | "return" => KeyCode::Enter,
+            "esc" | "escape" => KeyCode::Esc,
+            "tab" => KeyCode::Tab,
+            "backtab" => KeyCode::BackTab,
+            "backspace" => KeyCode::Backspace,
+            "delete" | "del" => KeyCode::Delete,
+            "insert" | "ins" => KeyCode::Insert,
+            "home" => KeyCode::Home,
+            "end" => KeyCode::End,
+            "pgup" | "pageup" => KeyCode::PageUp,
+            "pgdn" | "pagedown" => KeyCode::PageDown,
+            "up" => KeyCode::Up,
+            "down" => KeyCode::Down,
+            "left" => KeyCode::Left,
+            "right" => KeyCode::Right,
+            "space" => KeyCode::Char(' '),
+            name if name.len() > 1
+                && let Some(n) = name.strip_prefix('f').and_then(|n| n.parse().ok()) =>
+            {
+                KeyCode::F(n)
+            }
+            _ => {
+                let mut chars = rest.chars();
+                match (chars.next(), chars.next()) {
+                    (Some(c), None) if caret => KeyCode::Char(c.to_ascii_lowercase()),
+                    (Some(c), None) => KeyCode::Char(c),
+                    _ => bail!("unknown key '{s}'"),
+                }
+            }
+        };
+        Ok(KeyPress::new(code, modifiers))
+    }
+}
+
+impl fmt::Display for KeyPress {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        if self.modifiers.contains(KeyModifiers::ALT) {
+            write!(f, "Alt+")?;
+        }
+        if self.modifiers.contains(KeyModifiers::SHIFT) {
+            write!(f, "S-")?;
+        }
+        match self.code {
+            KeyCode::Char(c) if self.modifiers.contains(KeyModifiers::CONTROL) => {
+                if c.is_ascii_lowercase() {
+                    return write!(f, "^{}", c.to_ascii_uppercase());
+                }
+                write!(f, "ctrl-")?;
+            }
+            _ if self.modifiers.contains(KeyModifiers::CONTROL) => write!(f, "^")?,
+            _ => {}
+        }
+        match self.code {
+            KeyCode::Char(' ') => write!(f, "Space"),
+            KeyCode::Char(c) => write!(f, "{c}"),
+            KeyCode::Enter => write!(f, "Enter"),
+            KeyCode::Esc => write!(f, "Esc"),
+            KeyCode::Tab => write!(f, "Tab"),
+            KeyCode::BackTab => write!(f, "S-Tab"),
+            KeyCode::Backspace => write!(f, "Backspace"),
+            KeyCode::Delete => write!(f, "Del"),
+            KeyCode::Insert => write!(f, "Ins"),
+            KeyCode::PageUp => write!(f, "PgUp"),
+            KeyCode::PageDown => write!(f, "PgDn"),
+            KeyCode::F(n) => write!(f, "F{n}"),
+            code => write!(f, "{code:?}"),
+        }
+    }
+}
+
+/// keys with spaces between them, like "^X ^C"
+pub fn chord_text(keys: &[KeyPress]) -> String {
+    keys.iter()
+        .map(ToString::to_string)
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+fn parse_chord(text: &str) -> Result<Vec<KeyPress>> {
+    let keys = text
+        .split_whitespace()
+        .map(str::parse)
+        .collect::<Result<Vec<KeyPress>>>()?;
+    if keys.is_empty() {
+        bail!("empty key");
+    }
+    Ok(keys)
+}
+
+impl Keymap {
+    /// a missing file is the default keymap, a broken one too but it says so
+    pub fn load(path: &Path) -> Self {
+        let mut problems = Vec::new();
+        let file = match fs::read_to_string(path) {
+            Ok(data) => serde_json::from_str(&data).unwrap_or_else(|err| {
+                problems.push(format!("{}: {err}", path.display()));
+                KeymapFile::default()
+            }),
+            Err(_) => KeymapFile::default(),
+        };
+
+        let mut keys: BTreeMap<(KeyContext, Action), Vec<String>> = BTreeMap::new();
+        let mut apply = |table: Table| {
+            for &(context, action, list) in table {
+                let list = list.iter().map(ToString::to_string).collect();
+                keys.insert((context, action), list);
+            }
+        };
+        apply(DEFAULT);
+        match file.preset.as_deref() {
+            None | Some("default") => {}
+            Some("vim") => apply(VIM),
+            Some("emacs") => apply(EMACS),
+            Some(other) => {
+                problems.push(format!(
+                    "no preset '{other}', there are default, vim and emacs"
+                ));
+            }
+        }
+        for (context, section) in [
+            (KeyContext::Global, file.global),
+            (KeyContext::Chat, file.chat),
+            (KeyContext::Sidebar, file.sidebar),
+            (KeyContext::Messages, file.messages),
+        ] {
+            for (name, list) in section {
+                match Action::from_name(&name) {
+                    Some(action) => _ = keys.insert((context, action), list),
+                    None => problems.push(format!("{}: no action '{name}'", context.name())),
+                }
+            }
+        }
+
+        let mut bindings = Vec::new();
+        for ((context, action), list) in keys {
+            for text in list {
+                match parse_chord(&text) {
+                    Ok(keys) => bindings.push(Binding {
+                        context,
+                        keys,
+                        action,
+                    }),
+                    Err(err) => {
+                        problems.push(format!("{}: {}: {err}", context.name(), action.name()));
+                    }
+                }
+            }
+        }
+        let mut keymap = Keymap { bindings, problems };
+        keymap.problems.extend(keymap.conflicts());
+        keymap
+    }
+
+    /// keys bound twice, chords cut short by a shorter binding, plain letters
+    /// where they would stop being typed and keys the screens need themselves
+    pub fn conflicts(&self) -> Vec<String> {
+        let mut conflicts = Vec::new();
+        for (i, a) in self.bindings.iter().enumerate() {
+            for &(context, key, what) in HARD_CODED {
+                if a.context.overlaps(context) && key.parse::<KeyPress>().ok() == Some(a.keys[0]) {
+                    conflicts.push(format!(
+                        "{}: {} ({}) takes it away from {what}",
+                        context.name(),
+                        a.keys[0],
+                        a.action.name()
+                    ));
+                }
+            }
+            if matches!(a.context, KeyContext::Global | KeyContext::Chat) && a.keys[0].types() {
+                conflicts.push(format!(
+                    "{}: {} ({}) can't be typed any more",
+                    a.context.name(),
+                    a.keys[0],
+                    a.action.name()
+                ));
+            }
+            for b in &self.bindings[i + 1..] {
+                if !a.context.overlaps(b.context) || a.action == b.action {
+                    continue;
+                }
+                // where both are pressed
+                let context = a.context.max(b.context).name();
+                let (short, long) = if a.keys.len() <= b.keys.len() {
+                    (a, b)
+                } else {
+                    (b, a)
+                };
+                if short.keys == long.keys {
+                    conflicts.push(format!(
+                        "{context}: {} is both {} and {}",
+                        chord_text(&a.keys),
+                        a.action.name(),
+                        b.action.name()
+                    ));
+                } else if long.keys.starts_with(&short.keys) {
+                    conflicts.push(format!(
+                        "{context}: {} ({}) hides {} ({})",
+                        chord_text(&short.keys),
+                        short.action.name(),
+                        chord_text(&long.keys),
+                        long.action.name()
+                    ));
+                }
+            }
+        }
+        conflicts
+    }
+
+    /// add `key` to the chord typed so far and see if that makes a binding
+    pub fn resolve(
+        &self,
+        context: KeyContext,
+        chord: &mut Vec<KeyPress>,
+        key: KeyEvent,
+    ) -> Resolved {
+        chord.push(key.into());
+        let active = || {
+            self.bindings
+                .iter()
+                .filter(move |b| b.context == context || b.context == KeyContext::Global)
+        };
+        // the screen's own bindings win over global ones
+        if let Some(binding) = active()
+            .filter(|b| b.keys == *chord)
+            .min_by_key(|b| b.context == KeyContext::Global)
+        {
+            chord.clear();
+            return Resolved::Action(binding.action);
+        }
+        if active().any(|b| b.keys.starts_with(chord)) {
+            return Resolved::Waiting;
+        }
+        let was_chord = chord.len() > 1;
+        chord.clear();
+        if was_chord {
+            Resolved::Waiting
+        } else {
+            Resolved::Unbound
+        }
+    }
+
+    /// whether `key` alone runs `action`, for screens that handle keys themselves
+    pub fn is(&self, action: Action, key: KeyEvent) -> bool {
+        let key = KeyPress::from(key);
+        self.bindings
+            .iter()
+            .any(|b| b.action == action && b.keys == [key])
+    }
+
+    /// the first key bound to `action`, to show next to it
+    pub fn hint(&self, action: Action) -> String {
+        self.bindings
+            .iter()
+            .find(|b| b.action == action)
+            .map(|b| match b.context {
+                KeyContext::Global | KeyContext::Chat => chord_text(&b.keys),
+                context => format!("{} {}", context.name(), chord_text(&b.keys)),
+            })
+            .unwrap_or_default()
+    }
+
+    /// the first key for `action` where `context` is what has focus
+    pub fn key_in(&self, context: KeyContext, action: Action) -> Option<String> {
+        self.bindings
+            .iter()
+            .find(|b| {
+                b.action == action && (b.context == context || b.context == KeyContext::Global)
+            })
+            .map(|b| chord_text(&b.keys))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn key(s: &str) -> KeyPress {
+        s.parse().unwrap()
+    }
+
+    #[test]
+    fn keys_are_read_in_every_spelling() {
+        let ctrl_k = KeyPress::new(KeyCode::Char('k'), KeyModifiers::CONTROL);
+        assert_eq!(key("ctrl-k"), ctrl_k);
+        assert_eq!(key("C+k"), ctrl_k);
+        assert_eq!(key("^K"), ctrl_k);
+        assert_eq!(key("shift-g"), key("G"));
+        assert_eq!(key("shift-tab").code, KeyCode::BackTab);
+        assert_eq!(
+            key("alt-/"),
+            KeyPress::new(KeyCode::Char('/'), KeyModifiers::ALT)
+        );
+        assert_eq!(key("-").code, KeyCode::Char('-'));
+        assert_eq!(key("f12").code, KeyCode::F(12));
+        assert_eq!(key("f").code, KeyCode::Char('f'));
+        assert!("ctrl-nope".parse::<KeyPress>().is_err());
+        assert_eq!(ctrl_k.to_string(), "^K");
+        assert_eq!(chord_text(&parse_chord("ctrl-x  ctrl-c").unwrap()), "^X ^C");
+    }
+
+    #[test]
+    fn the_presets_have_no_conflicts() {
+        for preset in ["default", "vim", "emacs"] {
+            let dir = std::env::temp_dir()
+                .join(format!("llm-tui-rs-keymap-{preset}-{}", std::process::id()));
+            fs::create_dir_all(&dir).unwrap();
+            let path = dir.join("keymap.json");
+            fs::write(&path, format!(r#"{{ "preset": "{preset}" }}"#)).unwrap();
+            let keymap = Keymap::load(&path);
+            assert_eq!(keymap.problems, Vec::<String>::new(), "{preset}");
+            fs::remove_dir_all(dir).unwrap();
+        }
+    }
+
+    #[test]
+    fn conflicts_are_listed() {
+        let dir = std::env::temp_dir().join(format!("llm-tui-rs-keymap-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("keymap.json");
+        fs::write(
+            &path,
+            r#"{
+                "global": { "palette": ["ctrl-x"] },
+                "chat": { "edit_prompt": ["ctrl-x ctrl-e"], "main_menu": ["q"] },
+                "sidebar": { "new_chat": ["r"], "undo": ["enter"], "nothing": ["z"] }
+            }"#,
+        )
+        .unwrap();
+        let keymap = Keymap::load(&path);
+        fs::remove_dir_all(dir).unwrap();
+
+        let has = |text: &str| keymap.problems.iter().any(|p| p == text);
+        assert!(has("sidebar: no action 'nothing'"), "{:?}", keymap.problems);
+        assert!(has("chat: ^X (palette) hides ^X ^E (edit_prompt)"));
+        assert!(has("chat: q (main_menu) can't be typed any more"));
+        assert!(has("sidebar: r is both new_chat and rename_chat"));
+        assert!(has(
+            "sidebar: Enter (undo) takes it away from opening the chat or folder"
+        ));
+        assert_eq!(
+            keymap
+                .key_in(KeyContext::Sidebar, Action::NewChat)
+                .as_deref(),
+            Some("r")
+        );
+        assert_eq!(
+            keymap
+                .key_in(KeyContext::Sidebar, Action::Palette)
+                .as_deref(),
+            Some("^X")
+        );
+    }
+}
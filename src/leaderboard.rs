@@ -0,0 +1,113 @@
+// how profiles did against each other in the arena, kept in leaderboard.json in the
+// chat store. picking a reply is a win for its profile and a loss for each of the
+// others.
+use crate::chat_store::ChatStore;
+use anyhow::{Context, Result};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+
+const FILE: &str = "leaderboard.json";
+
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
+pub struct Leaderboard {
+    #[serde(default)]
+    pub profiles: BTreeMap<String, Record>,
+    // every round played, oldest first
+    #[serde(default)]
+    pub rounds: Vec<Round>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, Default)]
+pub struct Record {
+    pub wins: u32,
+    pub losses: u32,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct Round {
+    pub at: DateTime<Utc>,
+    pub prompt: String,
+    // profile names and the model each one answered with
+    pub contenders: Vec<(String, String)>,
+    pub winner: String,
+}
+
+impl Leaderboard {
+    fn parse(data: Option<String>) -> Result<Self> {
+        match data {
+            Some(data) => serde_json::from_str(&data).with_context(|| format!("can't read {FILE}")),
+            None => Ok(Leaderboard::default()),
+        }
+    }
+
+    pub fn load(store: &ChatStore) -> Result<Self> {
+        Self::parse(store.read_file(FILE)?)
+    }
+
+    /// count `round`, whatever other windows counted in the meantime stays
+    pub fn record(store: &ChatStore, round: Round) -> Result<()> {
+        store.update_file(FILE, |current| {
+            let mut board = Self::parse(current)?;
+            board.count(round);
+            Ok(serde_json::to_string_pretty(&board)?)
+        })
+    }
+
+    fn count(&mut self, round: Round) {
+        for (profile, _) in &round.contenders {
+            let record = self.profiles.entry(profile.clone()).or_default();
+            if *profile == round.winner {
+                record.wins += 1;
+            } else {
+                record.losses += 1;
+            }
+        }
+        self.rounds.push(round);
+    }
+
+    /// "name 3-1" for every profile, the most wins first
+    pub fn standings(&self) -> Vec<String> {
+        let mut records: Vec<(&String, &Record)> = self.profiles.iter().collect();
+        records.sort_by(|a, b| b.1.wins.cmp(&a.1.wins).then(a.1.losses.cmp(&b.1.losses)));
+        records
+            .into_iter()
+            .map(|(name, r)| format!("{name} {}-{}", r.wins, r.losses))
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn round(contenders: &[&str], winner: &str) -> Round {
+        Round {
+            at: Utc::now(),
+            prompt: "hi".into(),
+            contenders: contenders
+                .iter()
+                .map(|c| ((*c).to_string(), format!("{c}-model")))
+                .collect(),
+            winner: winner.into(),
+        }
+    }
+
+    #[test]
+    fn rounds_add_up_across_windows() {
+        let dir =
+            std::env::temp_dir().join(format!("llm-tui-rs-leaderboard-{}", std::process::id()));
+        let ours = ChatStore::open(dir.clone()).unwrap();
+        let theirs = ChatStore::open(dir.clone()).unwrap();
+        assert!(Leaderboard::load(&ours).unwrap().standings().is_empty());
+
+        Leaderboard::record(&ours, round(&["fast", "smart"], "smart")).unwrap();
+        Leaderboard::record(&theirs, round(&["fast", "smart", "cheap"], "fast")).unwrap();
+        Leaderboard::record(&ours, round(&["smart", "cheap"], "smart")).unwrap();
+
+        let board = Leaderboard::load(&theirs).unwrap();
+        std::fs::remove_dir_all(dir).unwrap();
+        assert_eq!(board.rounds.len(), 3);
+        assert_eq!(board.standings(), ["smart 2-1", "fast 1-1", "cheap 0-2"]);
+    }
+}
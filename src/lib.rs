@@ -0,0 +1,32 @@
+#![warn(clippy::all, clippy::pedantic)]
+// everything but the terminal loop in main.rs, so benches/ can get at it too. it
+// isn't published, the lints for library APIs only got in the way
+#![allow(
+    clippy::missing_errors_doc,
+    clippy::missing_panics_doc,
+    clippy::must_use_candidate,
+    clippy::implicit_hasher
+)]
+pub mod action;
+pub mod ai;
+pub mod ai_backend;
+pub mod app;
+pub mod chat_branch;
+pub mod chat_store;
+pub mod chat_structs;
+pub mod cli;
+pub mod clipboard;
+pub mod commands;
+pub mod editor;
+pub mod export;
+pub mod highlight;
+pub mod import;
+pub mod keymap;
+pub mod leaderboard;
+pub mod markdown;
+pub mod profiles;
+pub mod prompt_history;
+pub mod search;
+pub mod theme;
+pub mod ui;
+pub mod ui_config;
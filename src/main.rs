@@ -1,32 +1,81 @@
 #![warn(clippy::all, clippy::pedantic)]
 
 use color_eyre::{Result, eyre::eyre};
-use crossterm::event::{self, Event};
-use ratatui::{DefaultTerminal, Frame};
-mod ai;
-mod ai_backend;
-mod app;
-mod chat_branch;
-mod chat_structs;
-use app::CurrentScreen;
-mod ui;
+use crossterm::event::{
+    DisableBracketedPaste, EnableBracketedPaste, Event, KeyboardEnhancementFlags,
+    PopKeyboardEnhancementFlags, PushKeyboardEnhancementFlags,
+};
+use crossterm::{
+    execute,
+    terminal::{EnterAlternateScreen, enable_raw_mode, supports_keyboard_enhancement},
+};
+use llm_tui_rs::app::CurrentScreen;
+use llm_tui_rs::ui::{self, MainMenu};
+use llm_tui_rs::{cli, editor, keymap, theme, ui_config};
+use ratatui::{DefaultTerminal, Frame, layout::Rect};
 use std::sync::{Arc, Mutex};
 use tokio::{
     task,
     time::{Duration, interval},
 };
-use ui::MainMenu;
 
 #[tokio::main]
 async fn main() -> Result<()> {
     color_eyre::install()?;
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    if let Some(result) = cli::run(&args) {
+        return result.map_err(|err| eyre!(Box::new(err)));
+    }
     let terminal = ratatui::init();
-    let result = run(terminal).await;
+    let enhanced = supports_keyboard_enhancement().unwrap_or(false);
+    enter_terminal(enhanced)?;
+    ui::mouse::set_capture(ui_config::get().mouse)?;
+    let result = run(terminal, enhanced);
+    leave_terminal(enhanced)?;
     ratatui::restore();
     result
 }
 
-async fn run(mut terminal: DefaultTerminal) -> Result<()> {
+// pasting shouldn't send every line as its own prompt, and terminals only tell
+// Shift+Enter apart from Enter with the keyboard enhancement protocol
+fn enter_terminal(enhanced: bool) -> std::io::Result<()> {
+    execute!(std::io::stdout(), EnableBracketedPaste)?;
+    ui::mouse::resume()?;
+    if enhanced {
+        execute!(
+            std::io::stdout(),
+            PushKeyboardEnhancementFlags(KeyboardEnhancementFlags::DISAMBIGUATE_ESCAPE_CODES)
+        )?;
+    }
+    Ok(())
+}
+
+fn leave_terminal(enhanced: bool) -> std::io::Result<()> {
+    ui::mouse::release()?;
+    if enhanced {
+        execute!(std::io::stdout(), PopKeyboardEnhancementFlags)?;
+    }
+    execute!(std::io::stdout(), DisableBracketedPaste)
+}
+
+/// give the terminal to $EDITOR and take it back afterwards, the screen lock is
+/// not held meanwhile so replies keep coming in
+fn run_editor(
+    terminal: &mut DefaultTerminal,
+    enhanced: bool,
+    text: &str,
+) -> Result<anyhow::Result<Option<String>>> {
+    leave_terminal(enhanced)?;
+    ratatui::restore();
+    let edited = editor::edit(text);
+    enable_raw_mode()?;
+    execute!(std::io::stdout(), EnterAlternateScreen)?;
+    enter_terminal(enhanced)?;
+    terminal.clear()?;
+    Ok(edited)
+}
+
+fn run(mut terminal: DefaultTerminal, enhanced: bool) -> Result<()> {
     // let mut current_screen = CurrentScreen::MainMenu(MainMenu { selected: 0 });
     // okay so I have to run drain_ai every second in a different thread and share its data without blowing up, how do I do this?
     // https://itsallaboutthebit.com/arc-mutex/
@@ -42,17 +91,20 @@ async fn run(mut terminal: DefaultTerminal) -> Result<()> {
             loop {
                 ticker.tick().await;
                 let mut guard = shared.lock().unwrap();
-                if let CurrentScreen::ChatView(chat) = &mut *guard {
+                if let Some(chat) = guard.chat_view_mut() {
                     let _ = chat.drain_ai();
+                    let _ = chat.sync_store();
                 }
             }
         });
     }
+    // the keys of a chord typed so far
+    let mut chord = Vec::new();
     loop {
         // terminal.draw(|f| render(f, &current_screen))?;
         {
             let guard = shared.lock().unwrap();
-            terminal.draw(|f| render(f, &*guard))?;
+            terminal.draw(|f| render(f, &guard, &chord))?;
         }
         // if let Event::Key(key_event) = event::read()? {
         // ^^^ this makes it block for the next keypress, so new draws / updated structs will block until a key is pressed, no good
@@ -60,20 +112,55 @@ async fn run(mut terminal: DefaultTerminal) -> Result<()> {
 
         // delegate to the current screen
         if crossterm::event::poll(Duration::from_millis(50))? {
-            if let Event::Key(key_event) = crossterm::event::read()? {
-                let mut guard = shared.lock().unwrap();
-                guard
-                    .on_key(key_event)
-                    .await
-                    .map_err(|err| eyre!(Box::new(err)))?;
-                if let CurrentScreen::Exit(_) = &*guard {
-                    break Ok(());
+            match crossterm::event::read()? {
+                Event::Key(key_event) => {
+                    let edit = {
+                        let mut guard = shared.lock().unwrap();
+                        guard
+                            .on_key(key_event, &mut chord)
+                            .map_err(|err| eyre!(Box::new(err)))?;
+                        if let CurrentScreen::Exit(_) = &*guard {
+                            break Ok(());
+                        }
+                        guard.take_edit()
+                    };
+                    if let Some((target, text)) = edit {
+                        let edited = run_editor(&mut terminal, enhanced, &text)?;
+                        shared
+                            .lock()
+                            .unwrap()
+                            .finish_edit(target, edited)
+                            .map_err(|err| eyre!(Box::new(err)))?;
+                    }
+                }
+                Event::Paste(text) => shared.lock().unwrap().on_paste(&text),
+                Event::Mouse(mouse_event) => {
+                    let size = terminal.size()?;
+                    shared
+                        .lock()
+                        .unwrap()
+                        .on_mouse(mouse_event, Rect::new(0, 0, size.width, size.height))
+                        .map_err(|err| eyre!(Box::new(err)))?;
                 }
+                _ => {}
             }
         }
     }
 }
 
-fn render(frame: &mut Frame, current_screen: &CurrentScreen) {
+fn render(frame: &mut Frame, current_screen: &CurrentScreen, chord: &[keymap::KeyPress]) {
     frame.render_widget(current_screen, frame.area());
+    // like vim's showcmd, in the status bar's right corner
+    if !chord.is_empty() {
+        let header = Rect {
+            height: 1,
+            ..frame.area()
+        };
+        ui::status_bar::render_corner(
+            &format!("{} -", keymap::chord_text(chord)),
+            theme::get().status,
+            header,
+            frame.buffer_mut(),
+        );
+    }
 }
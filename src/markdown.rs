@@ -0,0 +1,113 @@
+// reading things out of a message's markdown
+use pulldown_cmark::{CodeBlockKind, Event, Parser, Tag, TagEnd};
+use std::ops::Range;
+
+pub struct CodeBlock {
+    // the first word after the fence, empty if there is none
+    pub lang: String,
+    pub code: String,
+    // where a fenced block outside of lists and quotes is in the markdown, those
+    // are drawn by us instead of tui-markdown
+    pub range: Option<Range<usize>>,
+}
+
+/// a message cut into the markdown between its code blocks and the blocks
+pub enum Segment<'a> {
+    Markdown(&'a str),
+    // with its index in code_blocks
+    Code(usize, CodeBlock),
+}
+
+/// the code blocks in `markdown` in order, fenced or indented
+pub fn code_blocks(markdown: &str) -> Vec<CodeBlock> {
+    let mut blocks = Vec::new();
+    let mut current: Option<CodeBlock> = None;
+    // how many tags the event is in
+    let mut depth = 0;
+    for (event, range) in Parser::new(markdown).into_offset_iter() {
+        match event {
+            Event::Start(Tag::CodeBlock(kind)) => {
+                let (lang, range) = match kind {
+                    CodeBlockKind::Fenced(info) => (
+                        info.split_whitespace().next().unwrap_or("").to_string(),
+                        Some(range).filter(|_| depth == 0),
+                    ),
+                    CodeBlockKind::Indented => (String::new(), None),
+                };
+                current = Some(CodeBlock {
+                    lang,
+                    code: String::new(),
+                    range,
+                });
+                depth += 1;
+            }
+            Event::Start(_) => depth += 1,
+            Event::Text(text) => {
+                if let Some(block) = current.as_mut() {
+                    block.code.push_str(&text);
+                }
+            }
+            Event::End(TagEnd::CodeBlock) => {
+                blocks.extend(current.take());
+                depth -= 1;
+            }
+            Event::End(_) => depth -= 1,
+            _ => {}
+        }
+    }
+    blocks
+}
+
+/// `markdown` with the fenced code blocks that have a range taken out
+pub fn segments(markdown: &str) -> Vec<Segment<'_>> {
+    let mut segments = Vec::new();
+    let mut start = 0;
+    for (n, block) in code_blocks(markdown).into_iter().enumerate() {
+        let Some(range) = block.range.clone() else {
+            continue;
+        };
+        if start < range.start {
+            segments.push(Segment::Markdown(&markdown[start..range.start]));
+        }
+        start = range.end;
+        segments.push(Segment::Code(n, block));
+    }
+    if start < markdown.len() {
+        segments.push(Segment::Markdown(&markdown[start..]));
+    }
+    segments
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn only_top_level_fences_are_cut_out() {
+        let text = "intro\n\n    indented\n\n```rust ignore\nfn main() {}\n```\n\n- item\n\n  ```\n  in a list\n  ```\n";
+        let blocks = code_blocks(text);
+        let found: Vec<(&str, &str, bool)> = blocks
+            .iter()
+            .map(|b| (b.lang.as_str(), b.code.as_str(), b.range.is_some()))
+            .collect();
+        assert_eq!(
+            found,
+            [
+                ("", "indented\n", false),
+                ("rust", "fn main() {}\n", true),
+                ("", "in a list\n", false),
+            ]
+        );
+
+        let parts: Vec<String> = segments(text)
+            .into_iter()
+            .map(|s| match s {
+                Segment::Markdown(text) => text.to_string(),
+                Segment::Code(n, block) => format!("[{n} {}]", block.lang),
+            })
+            .collect();
+        assert_eq!(parts[..2], ["intro\n\n    indented\n\n", "[1 rust]"]);
+        assert!(parts[2].contains("in a list"));
+        assert_eq!(parts.len(), 3);
+    }
+}
@@ -0,0 +1,49 @@
+// named sets of AI settings in profiles.json, switching to one copies it into
+// settings.json which everything else reads.
+use crate::ai_backend::AISettings;
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::Path;
+
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
+pub struct Profiles {
+    // the profile settings.json was last switched to
+    #[serde(default)]
+    pub active: Option<String>,
+    #[serde(default)]
+    pub profiles: BTreeMap<String, AISettings>,
+}
+
+impl Profiles {
+    pub fn load(path: &Path) -> Result<Self> {
+        if !path.exists() {
+            return Ok(Profiles::default());
+        }
+        let data = fs::read_to_string(path)?;
+        serde_json::from_str(&data).with_context(|| format!("can't read {}", path.display()))
+    }
+
+    pub fn save(&self, path: &Path) -> Result<()> {
+        fs::write(path, serde_json::to_string_pretty(self)?)?;
+        Ok(())
+    }
+
+    /// copy profile `name` into settings.json and remember it as the active one
+    pub fn switch(&mut self, name: &str) -> Result<AISettings> {
+        let settings = self.profiles.get(name).cloned().with_context(|| {
+            format!(
+                "no profile called '{name}' (there are: {})",
+                self.names().collect::<Vec<_>>().join(", ")
+            )
+        })?;
+        AISettings::write_all(Path::new("settings.json"), &settings)?;
+        self.active = Some(name.to_string());
+        Ok(settings)
+    }
+
+    pub fn names(&self) -> impl Iterator<Item = &str> {
+        self.profiles.keys().map(String::as_str)
+    }
+}
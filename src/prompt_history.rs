@@ -0,0 +1,177 @@
+// prompts sent before, newest last, kept in prompt_history.json in the chat store so
+// they are there in the next session too. Up/Down walk through them like a shell,
+// Ctrl-R searches.
+use crate::chat_store::ChatStore;
+use anyhow::{Context, Result};
+
+// enough to find last month's prompts, small enough to rewrite on every send
+const MAX_ENTRIES: usize = 1000;
+const FILE: &str = "prompt_history.json";
+
+#[derive(Debug, Default)]
+pub struct PromptHistory {
+    // off when the file couldn't be read, the prompts are only kept until quitting
+    // so the file isn't written over
+    saved: bool,
+    entries: Vec<String>,
+    // the entry shown in the input while walking through them
+    pos: Option<usize>,
+    // what was typed before the walk started, Down past the newest gives it back
+    draft: String,
+}
+
+/// a running Ctrl-R search
+#[derive(Debug, Default)]
+pub struct HistorySearch {
+    pub query: String,
+    // the entry the query matched last
+    pub found: Option<usize>,
+    // nothing older matches the query, the last match stays shown
+    pub failing: bool,
+    // the input from before the search, Esc puts it back
+    pub draft: String,
+}
+
+fn parse(data: Option<String>) -> Result<Vec<String>> {
+    match data {
+        Some(data) => serde_json::from_str(&data).with_context(|| format!("can't read {FILE}")),
+        None => Ok(Vec::new()),
+    }
+}
+
+/// `prompt` added to `entries`, a prompt sent twice only keeps its newest place
+fn remember(entries: &mut Vec<String>, prompt: &str) {
+    entries.retain(|e| e != prompt);
+    entries.push(prompt.to_string());
+    let excess = entries.len().saturating_sub(MAX_ENTRIES);
+    entries.drain(..excess);
+}
+
+impl PromptHistory {
+    pub fn load(store: &ChatStore) -> Result<Self> {
+        Ok(PromptHistory {
+            saved: true,
+            entries: parse(store.read_file(FILE)?)?,
+            ..Default::default()
+        })
+    }
+
+    /// `load`, or an empty history that isn't saved and what went wrong
+    pub fn load_or_empty(store: &ChatStore) -> (Self, Option<String>) {
+        match Self::load(store) {
+            Ok(history) => (history, None),
+            Err(err) => (
+                PromptHistory::default(),
+                Some(format!(
+                    "{err:#}, prompts sent now won't be remembered after quitting"
+                )),
+            ),
+        }
+    }
+
+    /// remember a sent prompt. other instances append too, so it goes on top of
+    /// whatever is on disk, with the store locked meanwhile
+    pub fn push(&mut self, store: &ChatStore, prompt: &str) -> Result<()> {
+        self.reset();
+        if !self.saved {
+            remember(&mut self.entries, prompt);
+            return Ok(());
+        }
+        store.update_file(FILE, |current| {
+            let mut entries = parse(current)?;
+            remember(&mut entries, prompt);
+            let data = serde_json::to_string_pretty(&entries)?;
+            self.entries = entries;
+            Ok(data)
+        })
+    }
+
+    /// stop walking, the next Up starts from the newest prompt again
+    pub fn reset(&mut self) {
+        self.pos = None;
+    }
+
+    /// the prompt before the one shown, `current` is kept to come back to
+    pub fn older(&mut self, current: &str) -> Option<&str> {
+        let pos = match self.pos {
+            None => {
+                self.draft = current.to_string();
+                self.entries.len().checked_sub(1)?
+            }
+            Some(pos) => pos.checked_sub(1)?,
+        };
+        self.pos = Some(pos);
+        Some(&self.entries[pos])
+    }
+
+    /// the prompt after the one shown, or the draft once past the newest
+    pub fn newer(&mut self) -> Option<&str> {
+        let pos = self.pos?;
+        if pos + 1 < self.entries.len() {
+            self.pos = Some(pos + 1);
+            Some(&self.entries[pos + 1])
+        } else {
+            self.pos = None;
+            Some(&self.draft)
+        }
+    }
+
+    /// newest prompt before `before` that contains `query`, ignoring case
+    pub fn search(&self, query: &str, before: usize) -> Option<usize> {
+        let query = query.to_lowercase();
+        self.entries[..before.min(self.entries.len())]
+            .iter()
+            .rposition(|e| e.to_lowercase().contains(&query))
+    }
+
+    pub fn get(&self, idx: usize) -> Option<&str> {
+        self.entries.get(idx).map(String::as_str)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn pushes_merge_with_other_instances() {
+        let dir = std::env::temp_dir().join(format!("llm-tui-rs-history-{}", std::process::id()));
+        let store = ChatStore::open(dir.clone()).unwrap();
+        let mut ours = PromptHistory::load(&store).unwrap();
+        let mut theirs = PromptHistory::load(&store).unwrap();
+
+        ours.push(&store, "one").unwrap();
+        theirs.push(&store, "two").unwrap();
+        ours.push(&store, "one").unwrap();
+
+        assert_eq!(ours.entries, ["two", "one"]);
+        assert_eq!(PromptHistory::load(&store).unwrap().entries, ["two", "one"]);
+        assert!(!dir.join("json.tmp").exists());
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn only_the_newest_entries_are_kept() {
+        let mut entries: Vec<String> = (0..MAX_ENTRIES).map(|i| i.to_string()).collect();
+        remember(&mut entries, "new");
+        assert_eq!(entries.len(), MAX_ENTRIES);
+        assert_eq!(entries[0], "1");
+        assert_eq!(entries.last().unwrap(), "new");
+    }
+
+    #[test]
+    fn walking_comes_back_to_the_draft() {
+        let mut history = PromptHistory {
+            entries: vec!["first".into(), "second".into()],
+            ..Default::default()
+        };
+        assert_eq!(history.older("typing"), Some("second"));
+        assert_eq!(history.older("second"), Some("first"));
+        assert_eq!(history.older("first"), None);
+        assert_eq!(history.newer(), Some("second"));
+        assert_eq!(history.newer(), Some("typing"));
+        assert_eq!(history.newer(), None);
+        assert_eq!(history.search("FIR", 2), Some(0));
+        assert_eq!(history.search("sec", 1), None);
+    }
+}
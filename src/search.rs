@@ -0,0 +1,404 @@
+// full-text search over every chat.
+// the index maps lowercased tokens to (chat id, message index) pairs and is kept on
+// disk, each refresh only reads chats that changed since the last one and only
+// tokenizes the messages in them that changed.
+use crate::chat_branch::ChatBranch;
+use crate::chat_store::ChatStore;
+use crate::chat_structs::{Message, Role};
+use anyhow::Result;
+use chrono::{DateTime, Local, NaiveDate, Utc};
+use serde::{Deserialize, Serialize};
+use std::collections::hash_map::DefaultHasher;
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+use std::hash::{Hash, Hasher};
+
+// next to the chats it indexes
+const INDEX_FILE: &str = "search_index.json";
+
+#[derive(Serialize, Deserialize, Default)]
+pub struct SearchIndex {
+    postings: BTreeMap<String, Vec<(usize, usize)>>,
+    // chat id -> hash of every message that is already in `postings`
+    indexed: HashMap<usize, Vec<u64>>,
+    // chat id -> `updated_at` of the chat when it was indexed
+    #[serde(default)]
+    stamps: HashMap<usize, DateTime<Utc>>,
+}
+
+fn tokenize(text: &str) -> impl Iterator<Item = String> + '_ {
+    text.split(|c: char| !c.is_alphanumeric())
+        .filter(|t| !t.is_empty())
+        .map(str::to_lowercase)
+}
+
+fn hash_message(message: &Message) -> u64 {
+    let mut hasher = DefaultHasher::new();
+    message.content.hash(&mut hasher);
+    hasher.finish()
+}
+
+impl SearchIndex {
+    /// the index kept in `store`, an empty one when it is missing or broken
+    pub fn load(store: &ChatStore) -> Self {
+        store
+            .read_file(INDEX_FILE)
+            .ok()
+            .flatten()
+            .and_then(|data| serde_json::from_str(&data).ok())
+            .unwrap_or_default()
+    }
+
+    pub fn save(&self, store: &ChatStore) -> Result<()> {
+        let data = serde_json::to_string(self)?;
+        store.update_file(INDEX_FILE, |_| Ok(data))
+    }
+
+    /// drop every posting of `chat` from message `from` onwards
+    fn forget(&mut self, chat: usize, from: usize) {
+        self.postings.retain(|_, hits| {
+            hits.retain(|&(c, m)| c != chat || m < from);
+            !hits.is_empty()
+        });
+    }
+
+    /// bring the index up to date, returns whether anything changed
+    pub fn refresh(&mut self, store: &ChatStore, branches: &[ChatBranch]) -> Result<bool> {
+        let mut changed = false;
+
+        let stale: Vec<usize> = self
+            .indexed
+            .keys()
+            .filter(|id| !branches.iter().any(|b| b.id == **id))
+            .copied()
+            .collect();
+        for id in stale {
+            self.forget(id, 0);
+            self.indexed.remove(&id);
+            self.stamps.remove(&id);
+            changed = true;
+        }
+
+        for branch in branches {
+            if branch.updated_at.is_some()
+                && self.stamps.get(&branch.id) == branch.updated_at.as_ref()
+            {
+                continue;
+            }
+            if let Some(updated_at) = branch.updated_at {
+                self.stamps.insert(branch.id, updated_at);
+                changed = true;
+            }
+            let messages = store.load_messages(branch.id)?;
+            let hashes: Vec<u64> = messages.iter().map(hash_message).collect();
+            let known = self.indexed.entry(branch.id).or_default();
+            // everything after the first edited message has to be redone
+            let same = known
+                .iter()
+                .zip(&hashes)
+                .take_while(|(a, b)| a == b)
+                .count();
+            if same == known.len() && same == hashes.len() {
+                continue;
+            }
+            if same < known.len() {
+                self.forget(branch.id, same);
+            }
+            for (i, message) in messages.iter().enumerate().skip(same) {
+                for token in tokenize(&message.content) {
+                    let hits = self.postings.entry(token).or_default();
+                    if hits.last() != Some(&(branch.id, i)) {
+                        hits.push((branch.id, i));
+                    }
+                }
+            }
+            self.indexed.insert(branch.id, hashes);
+            changed = true;
+        }
+        Ok(changed)
+    }
+
+    /// messages containing a token starting with `term`
+    fn lookup(&self, term: &str) -> BTreeSet<(usize, usize)> {
+        self.postings
+            .range(term.to_string()..)
+            .take_while(|(token, _)| token.starts_with(term))
+            .flat_map(|(_, hits)| hits.iter().copied())
+            .collect()
+    }
+}
+
+#[derive(Default)]
+pub struct Query {
+    pub terms: Vec<String>,
+    pub model: Option<String>,
+    pub user_only: Option<bool>,
+    pub after: Option<NaiveDate>,
+    pub before: Option<NaiveDate>,
+}
+
+impl Query {
+    /// plain words are search terms, `model:`, `role:`, `after:` and `before:` filter
+    pub fn parse(input: &str) -> Self {
+        let mut query = Query::default();
+        for word in input.split_whitespace() {
+            let date = |s: &str| NaiveDate::parse_from_str(s, "%Y-%m-%d").ok();
+            match word.split_once(':') {
+                Some(("model", m)) if !m.is_empty() => query.model = Some(m.to_lowercase()),
+                Some(("role", "user" | "you")) => query.user_only = Some(true),
+                Some(("role", "assistant" | "ai")) => query.user_only = Some(false),
+                Some(("after", d)) if date(d).is_some() => query.after = date(d),
+                Some(("before", d)) if date(d).is_some() => query.before = date(d),
+                _ => query.terms.extend(tokenize(word)),
+            }
+        }
+        query
+    }
+
+    fn has_filters(&self) -> bool {
+        self.model.is_some()
+            || self.user_only.is_some()
+            || self.after.is_some()
+            || self.before.is_some()
+    }
+
+    fn matches(&self, message: &Message) -> bool {
+        match (&message.role, self.user_only) {
+            (Role::User, Some(false)) | (Role::Assistant(_), Some(true)) => return false,
+            _ => {}
+        }
+        if let Some(model) = &self.model {
+            let Role::Assistant(assistant) = &message.role else {
+                return false;
+            };
+            if !assistant.model.to_lowercase().contains(model) {
+                return false;
+            }
+        }
+        if self.after.is_some() || self.before.is_some() {
+            let Some(created) = message.created_at else {
+                return false;
+            };
+            let day = created.with_timezone(&Local).date_naive();
+            if self.after.is_some_and(|after| day < after)
+                || self.before.is_some_and(|before| day > before)
+            {
+                return false;
+            }
+        }
+        true
+    }
+}
+
+pub struct SearchHit {
+    pub chat: usize,
+    // None when only the chat title matched
+    pub message: Option<usize>,
+    pub score: usize,
+}
+
+/// end of `term` if it occurs at byte `start` of `text`, ignoring case
+fn match_at(text: &str, start: usize, term: &str) -> Option<usize> {
+    let mut needle = term.chars().peekable();
+    for (offset, c) in text[start..].char_indices() {
+        for lower in c.to_lowercase() {
+            if needle.next() != Some(lower) {
+                return None;
+            }
+        }
+        if needle.peek().is_none() {
+            return Some(start + offset + c.len_utf8());
+        }
+    }
+    None
+}
+
+/// byte ranges of every case-insensitive occurrence of `terms` in `text`
+pub fn find_matches(text: &str, terms: &[String]) -> Vec<(usize, usize)> {
+    let mut ranges = Vec::new();
+    let mut next_free = 0;
+    for (start, _) in text.char_indices() {
+        if start < next_free {
+            continue;
+        }
+        if let Some(end) = terms.iter().find_map(|t| match_at(text, start, t)) {
+            ranges.push((start, end));
+            next_free = end;
+        }
+    }
+    ranges
+}
+
+/// (chat id, message index) of every message that could match, only the filters
+/// still have to be checked against the messages themselves
+pub fn candidates(
+    index: &SearchIndex,
+    branches: &[ChatBranch],
+    query: &Query,
+) -> Vec<(usize, usize)> {
+    if query.terms.is_empty() && !query.has_filters() {
+        return Vec::new();
+    }
+    if query.terms.is_empty() {
+        return branches
+            .iter()
+            .flat_map(|b| (0..b.message_count).map(move |i| (b.id, i)))
+            .collect();
+    }
+    let mut sets = query.terms.iter().map(|t| index.lookup(t));
+    let first = sets.next().unwrap_or_default();
+    sets.fold(first, |acc, set| &acc & &set)
+        .into_iter()
+        .collect()
+}
+
+/// `chats` needs the messages of every chat that `candidates` returns
+pub fn search(
+    index: &SearchIndex,
+    branches: &[ChatBranch],
+    chats: &HashMap<usize, Vec<Message>>,
+    query: &Query,
+) -> Vec<SearchHit> {
+    if query.terms.is_empty() && !query.has_filters() {
+        return Vec::new();
+    }
+
+    let mut hits = Vec::new();
+    // titles are few enough to just scan
+    if !query.has_filters() {
+        for branch in branches {
+            let title = branch.name.to_lowercase();
+            if query.terms.iter().all(|t| title.contains(t.as_str())) {
+                hits.push(SearchHit {
+                    chat: branch.id,
+                    message: None,
+                    score: usize::MAX,
+                });
+            }
+        }
+    }
+
+    for (chat, i) in candidates(index, branches, query) {
+        let Some(message) = chats.get(&chat).and_then(|m| m.get(i)) else {
+            continue;
+        };
+        if query.matches(message) {
+            hits.push(SearchHit {
+                chat,
+                message: Some(i),
+                score: find_matches(&message.content, &query.terms).len(),
+            });
+        }
+    }
+    // best matches first, then the chats written in last and newer messages in
+    // them. ids say nothing about age once chats are imported
+    let updated: HashMap<usize, _> = branches.iter().map(|b| (b.id, b.updated_at)).collect();
+    hits.sort_by(|a, b| {
+        b.score
+            .cmp(&a.score)
+            .then(updated.get(&b.chat).cmp(&updated.get(&a.chat)))
+            .then(b.chat.cmp(&a.chat))
+            .then(b.message.cmp(&a.message))
+    });
+    hits
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::chat_structs::Assistant;
+    use chrono::TimeZone;
+
+    #[test]
+    fn filters_come_out_of_the_query() {
+        let query = Query::parse("model:GPT role:ai after:2024-01-02 before:soon Foo-bar");
+        assert_eq!(query.model.as_deref(), Some("gpt"));
+        assert_eq!(query.user_only, Some(false));
+        assert_eq!(query.after, NaiveDate::from_ymd_opt(2024, 1, 2));
+        assert_eq!(query.before, None);
+        assert_eq!(query.terms, ["before", "soon", "foo", "bar"]);
+        assert_eq!(Query::parse("role:you").user_only, Some(true));
+        assert!(!Query::parse("cats").has_filters());
+    }
+
+    #[test]
+    fn matches_ignore_case_and_dont_overlap() {
+        let terms = ["äb".to_string(), "bc".to_string()];
+        assert_eq!(
+            find_matches("ÄBC äbc abc", &terms),
+            [(0, 3), (5, 8), (11, 13)]
+        );
+        assert_eq!(find_matches("aaaa", &["aa".to_string()]), [(0, 2), (2, 4)]);
+        assert!(find_matches("", &terms).is_empty());
+    }
+
+    fn chat(name: &str, day: u32, messages: &[(bool, &str)]) -> ChatBranch {
+        let mut branch = ChatBranch::new(0, name);
+        let at = Utc.with_ymd_and_hms(2024, 5, day, 12, 0, 0).unwrap();
+        branch.messages = messages
+            .iter()
+            .map(|&(user, text)| {
+                let role = if user {
+                    Role::User
+                } else {
+                    Role::Assistant(Assistant::new("gpt-4o"))
+                };
+                let mut message = Message::new(role, text.into());
+                message.created_at = Some(at);
+                message
+            })
+            .collect();
+        branch
+    }
+
+    #[test]
+    fn best_matches_then_latest_chats_come_first() {
+        let dir = std::env::temp_dir().join(format!("llm-tui-rs-search-{}", std::process::id()));
+        let store = ChatStore::open(dir.clone()).unwrap();
+        let mut branches = Vec::new();
+        for branch in [
+            chat("old", 1, &[(true, "a cat"), (false, "cats cats")]),
+            chat("new", 3, &[(true, "one cat")]),
+            chat("middle", 2, &[(true, "dog"), (false, "catalog")]),
+            chat("Cat pictures", 1, &[(true, "nothing")]),
+        ] {
+            let entry = store.add_chat(branch, &branches, &[]).unwrap();
+            branches.push(entry);
+        }
+        let id = |name: &str| branches.iter().find(|b| b.name == name).unwrap().id;
+
+        let mut index = SearchIndex::default();
+        assert!(index.refresh(&store, &branches).unwrap());
+        assert!(!index.refresh(&store, &branches).unwrap());
+        let chats: HashMap<usize, Vec<Message>> = branches
+            .iter()
+            .map(|b| (b.id, store.load_messages(b.id).unwrap()))
+            .collect();
+        std::fs::remove_dir_all(dir).unwrap();
+
+        let found = |query: &str| -> Vec<(usize, Option<usize>)> {
+            search(&index, &branches, &chats, &Query::parse(query))
+                .iter()
+                .map(|hit| (hit.chat, hit.message))
+                .collect()
+        };
+        assert_eq!(
+            found("cat"),
+            [
+                (id("Cat pictures"), None),
+                (id("old"), Some(1)),
+                (id("new"), Some(0)),
+                (id("middle"), Some(1)),
+                (id("old"), Some(0)),
+            ]
+        );
+        assert_eq!(
+            found("cat role:user"),
+            [(id("new"), Some(0)), (id("old"), Some(0))]
+        );
+        assert_eq!(
+            found("model:gpt after:2024-05-02"),
+            [(id("middle"), Some(1))]
+        );
+        assert!(found("").is_empty());
+    }
+}
@@ -0,0 +1,408 @@
+// the colors of everything, picked by "theme" in ui.json. a theme is a json file
+// naming a style for each part of the screen, what it leaves out comes from its
+// base theme:
+//
+//   { "base": "dark", "user": { "fg": "#98c379", "bold": true }, "border": { "fg": "gray" } }
+//
+// colors are names ("light_blue"), "#rrggbb" or a 256-color index, and are brought
+// down to what the terminal can show
+use crate::highlight;
+use crate::ui_config;
+use ratatui::{
+    style::{Color, Modifier, Style},
+    widgets::{Block, Borders},
+};
+use serde::Deserialize;
+use serde_json::{Map, Value};
+use std::env;
+use std::fs;
+use std::str::FromStr;
+use std::sync::OnceLock;
+
+pub const DEFAULT_THEME: &str = "dark";
+
+// the themes that come with it, the names ui.json and "base" use
+const BUILT_IN: [(&str, &str); 4] = [
+    ("dark", include_str!("../themes/dark.json")),
+    ("light", include_str!("../themes/light.json")),
+    (
+        "high-contrast",
+        include_str!("../themes/high-contrast.json"),
+    ),
+    ("mono", include_str!("../themes/mono.json")),
+];
+
+/// how many colors the terminal shows, "colors" in ui.json
+#[derive(Deserialize, Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum ColorDepth {
+    #[default]
+    #[serde(rename = "auto")]
+    Auto,
+    #[serde(rename = "truecolor")]
+    TrueColor,
+    #[serde(rename = "256")]
+    Indexed,
+    #[serde(rename = "16")]
+    Basic,
+    #[serde(rename = "none")]
+    None,
+}
+
+impl ColorDepth {
+    /// guessed from the environment, the same variables other programs go by
+    pub fn detect() -> Self {
+        let var = |name| env::var(name).unwrap_or_default();
+        let term = var("TERM");
+        if !var("NO_COLOR").is_empty() || term == "dumb" {
+            ColorDepth::None
+        } else if matches!(var("COLORTERM").as_str(), "truecolor" | "24bit")
+            || env::var_os("WT_SESSION").is_some()
+        {
+            ColorDepth::TrueColor
+        } else if term.contains("256") {
+            ColorDepth::Indexed
+        } else {
+            ColorDepth::Basic
+        }
+    }
+
+    /// `color` as close as this many colors get, `None` when there are none
+    pub fn fit(self, color: Color) -> Option<Color> {
+        match (self, color) {
+            (ColorDepth::None, _) => None,
+            (ColorDepth::Indexed, Color::Rgb(r, g, b)) => Some(Color::Indexed(to_256(r, g, b))),
+            (ColorDepth::Basic, Color::Rgb(r, g, b)) => Some(to_16(r, g, b)),
+            (ColorDepth::Basic, Color::Indexed(i)) => {
+                let (r, g, b) = indexed_rgb(i);
+                Some(to_16(r, g, b))
+            }
+            // names are in every terminal
+            _ => Some(color),
+        }
+    }
+
+    /// `style` with its colors fitted, without colors a background turns into
+    /// reversed text so highlights still show
+    pub fn fit_style(self, mut style: Style) -> Style {
+        if self == ColorDepth::None && style.bg.is_some_and(|bg| bg != Color::Reset) {
+            style = style.add_modifier(Modifier::REVERSED);
+        }
+        style.fg = style.fg.and_then(|c| self.fit(c));
+        style.bg = style.bg.and_then(|c| self.fit(c));
+        style
+    }
+}
+
+// the 16 colors as most terminals draw them by default
+const BASIC: [(Color, (u8, u8, u8)); 16] = [
+    (Color::Black, (0, 0, 0)),
+    (Color::Red, (205, 0, 0)),
+    (Color::Green, (0, 205, 0)),
+    (Color::Yellow, (205, 205, 0)),
+    (Color::Blue, (0, 0, 238)),
+    (Color::Magenta, (205, 0, 205)),
+    (Color::Cyan, (0, 205, 205)),
+    (Color::Gray, (229, 229, 229)),
+    (Color::DarkGray, (127, 127, 127)),
+    (Color::LightRed, (255, 0, 0)),
+    (Color::LightGreen, (0, 255, 0)),
+    (Color::LightYellow, (255, 255, 0)),
+    (Color::LightBlue, (92, 92, 255)),
+    (Color::LightMagenta, (255, 0, 255)),
+    (Color::LightCyan, (0, 255, 255)),
+    (Color::White, (255, 255, 255)),
+];
+
+// the steps of the 6x6x6 cube in the 256 colors
+const CUBE: [u8; 6] = [0, 95, 135, 175, 215, 255];
+
+fn distance((r1, g1, b1): (u8, u8, u8), (r2, g2, b2): (u8, u8, u8)) -> u32 {
+    let d = |a: u8, b: u8| u32::from(a.abs_diff(b)).pow(2);
+    d(r1, r2) + d(g1, g2) + d(b1, b2)
+}
+
+fn to_256(r: u8, g: u8, b: u8) -> u8 {
+    let step = |v: u8| {
+        (0..CUBE.len())
+            .min_by_key(|&i| CUBE[i].abs_diff(v))
+            .unwrap_or(0)
+    };
+    let (ri, gi, bi) = (step(r), step(g), step(b));
+    #[allow(clippy::cast_possible_truncation)]
+    let cube = 16 + (36 * ri + 6 * gi + bi) as u8;
+    // the grays between black and white are finer than the cube's
+    let average = (u16::from(r) + u16::from(g) + u16::from(b)) / 3;
+    #[allow(clippy::cast_possible_truncation)]
+    let gray = 232 + (average.saturating_sub(3) / 10).min(23) as u8;
+    if distance(indexed_rgb(gray), (r, g, b)) < distance(indexed_rgb(cube), (r, g, b)) {
+        gray
+    } else {
+        cube
+    }
+}
+
+fn indexed_rgb(i: u8) -> (u8, u8, u8) {
+    match i {
+        0..16 => BASIC[usize::from(i)].1,
+        16..232 => {
+            let i = usize::from(i - 16);
+            (CUBE[i / 36], CUBE[i / 6 % 6], CUBE[i % 6])
+        }
+        _ => {
+            let v = 8 + (i - 232) * 10;
+            (v, v, v)
+        }
+    }
+}
+
+fn to_16(r: u8, g: u8, b: u8) -> Color {
+    BASIC
+        .iter()
+        .min_by_key(|(_, rgb)| distance(*rgb, (r, g, b)))
+        .map_or(Color::Reset, |(color, _)| *color)
+}
+
+/// how one part of the screen looks in a theme file
+#[allow(clippy::struct_excessive_bools)]
+#[derive(Deserialize, Debug, Default)]
+#[serde(default, deny_unknown_fields)]
+pub struct StyleSpec {
+    pub fg: Option<String>,
+    pub bg: Option<String>,
+    pub bold: bool,
+    pub italic: bool,
+    pub dim: bool,
+    pub underline: bool,
+    pub reversed: bool,
+}
+
+impl StyleSpec {
+    pub fn to_style(&self) -> Result<Style, String> {
+        let color =
+            |name: &str| Color::from_str(name).map_err(|_| format!("'{name}' is not a color"));
+        let mut style = Style::default();
+        if let Some(fg) = &self.fg {
+            style = style.fg(color(fg)?);
+        }
+        if let Some(bg) = &self.bg {
+            style = style.bg(color(bg)?);
+        }
+        for (on, modifier) in [
+            (self.bold, Modifier::BOLD),
+            (self.italic, Modifier::ITALIC),
+            (self.dim, Modifier::DIM),
+            (self.underline, Modifier::UNDERLINED),
+            (self.reversed, Modifier::REVERSED),
+        ] {
+            if on {
+                style = style.add_modifier(modifier);
+            }
+        }
+        Ok(style)
+    }
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct Theme {
+    // "You:" and the model's name over messages
+    pub user: Style,
+    pub assistant: Style,
+    pub border: Style,
+    // the chosen row of lists and the focused message
+    pub selection: Style,
+    // text being typed into a list, like a rename
+    pub input: Style,
+    // hints, timestamps and other things that stay out of the way
+    pub muted: Style,
+    pub tag: Style,
+    pub notice: Style,
+    pub error: Style,
+    pub search_match: Style,
+    pub code_fence: Style,
+    pub line_number: Style,
+    // the bar at the top
+    pub status: Style,
+    // the syntect theme for code, "code_theme" in the file. the one in ui.json
+    // wins over it
+    pub syntax: String,
+    pub depth: ColorDepth,
+    // what was wrong with the theme, the main menu lists it
+    pub problems: Vec<String>,
+}
+
+impl Theme {
+    pub fn slot(&mut self, name: &str) -> Option<&mut Style> {
+        Some(match name {
+            "user" => &mut self.user,
+            "assistant" => &mut self.assistant,
+            "border" => &mut self.border,
+            "selection" => &mut self.selection,
+            "input" => &mut self.input,
+            "muted" => &mut self.muted,
+            "tag" => &mut self.tag,
+            "notice" => &mut self.notice,
+            "error" => &mut self.error,
+            "search_match" => &mut self.search_match,
+            "code_fence" => &mut self.code_fence,
+            "line_number" => &mut self.line_number,
+            "status" => &mut self.status,
+            _ => return None,
+        })
+    }
+
+    /// a built-in theme by name or a theme file, anything wrong with it is
+    /// left as it is in the base and noted in problems
+    pub fn load(name: &str) -> Self {
+        let mut theme = Theme::default();
+        if let Some((_, data)) = BUILT_IN.iter().find(|(n, _)| *n == name) {
+            theme.apply(data, name);
+            return theme;
+        }
+        match fs::read_to_string(name) {
+            Ok(data) => {
+                // a file starts from the default theme unless it names a base
+                theme.apply(BUILT_IN[0].1, DEFAULT_THEME);
+                theme.apply(&data, name);
+            }
+            Err(err) => {
+                let names: Vec<&str> = BUILT_IN.iter().map(|(n, _)| *n).collect();
+                theme.apply(BUILT_IN[0].1, DEFAULT_THEME);
+                theme.problems.push(format!(
+                    "no theme '{name}' ({err}), there are {} or a theme file",
+                    names.join(", ")
+                ));
+            }
+        }
+        theme
+    }
+
+    fn apply(&mut self, data: &str, name: &str) {
+        let fields = match serde_json::from_str::<Map<String, Value>>(data) {
+            Ok(fields) => fields,
+            Err(err) => {
+                self.problems.push(format!("{name}: {err}"));
+                return;
+            }
+        };
+        if let Some(base) = fields.get("base") {
+            match BUILT_IN.iter().find(|(n, _)| Some(*n) == base.as_str()) {
+                Some((base, data)) => self.apply(data, base),
+                None => self
+                    .problems
+                    .push(format!("{name}: base {base} is not a built-in theme")),
+            }
+        }
+        for (key, value) in fields {
+            match key.as_str() {
+                "base" => {}
+                "code_theme" => match value.as_str() {
+                    Some(code_theme) => self.syntax = code_theme.to_string(),
+                    None => self
+                        .problems
+                        .push(format!("{name}: code_theme should be a string")),
+                },
+                _ => {
+                    let style = serde_json::from_value::<StyleSpec>(value)
+                        .map_err(|err| err.to_string())
+                        .and_then(|spec| spec.to_style());
+                    match (self.slot(&key), style) {
+                        (Some(slot), Ok(style)) => *slot = style,
+                        (Some(_), Err(err)) => self.problems.push(format!("{name}: {key}: {err}")),
+                        (None, _) => self.problems.push(format!("{name}: no style called {key}")),
+                    }
+                }
+            }
+        }
+    }
+
+    /// every style brought down to `depth` colors
+    pub fn fit(&mut self, depth: ColorDepth) {
+        self.depth = depth;
+        for name in SLOTS {
+            if let Some(slot) = self.slot(name) {
+                *slot = depth.fit_style(*slot);
+            }
+        }
+    }
+}
+
+const SLOTS: [&str; 13] = [
+    "user",
+    "assistant",
+    "border",
+    "selection",
+    "input",
+    "muted",
+    "tag",
+    "notice",
+    "error",
+    "search_match",
+    "code_fence",
+    "line_number",
+    "status",
+];
+
+static THEME: OnceLock<Theme> = OnceLock::new();
+
+/// the theme named in ui.json, fitted to the terminal
+pub fn get() -> &'static Theme {
+    THEME.get_or_init(|| {
+        let config = ui_config::get();
+        let mut theme = Theme::load(&config.theme);
+        if let Some(code_theme) = &config.code_theme {
+            theme.syntax.clone_from(code_theme);
+        } else if let Err(err) = highlight::find_theme(&theme.syntax) {
+            theme.problems.push(format!("code_theme: {err:#}"));
+        }
+        let depth = match config.colors {
+            ColorDepth::Auto => ColorDepth::detect(),
+            depth => depth,
+        };
+        theme.fit(depth);
+        theme
+    })
+}
+
+/// a box drawn in the theme's border style
+pub fn block<'a>() -> Block<'a> {
+    Block::default()
+        .borders(Borders::ALL)
+        .border_style(get().border)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn colors_come_down_to_what_the_terminal_has() {
+        assert_eq!(to_256(255, 0, 0), 196);
+        assert_eq!(to_256(95, 135, 175), 16 + 36 + 12 + 3);
+        // a gray is closer on the gray ramp than in the cube
+        assert_eq!(to_256(128, 128, 128), 244);
+        assert_eq!(to_16(250, 10, 10), Color::LightRed);
+        assert_eq!(to_16(0, 0, 200), Color::Blue);
+
+        let basic = ColorDepth::Basic;
+        assert_eq!(basic.fit(Color::Indexed(196)), Some(Color::LightRed));
+        assert_eq!(basic.fit(Color::Cyan), Some(Color::Cyan));
+        assert_eq!(
+            ColorDepth::TrueColor.fit(Color::Rgb(1, 2, 3)),
+            Some(Color::Rgb(1, 2, 3))
+        );
+
+        let highlight = Style::default().fg(Color::Black).bg(Color::Yellow);
+        let mono = ColorDepth::None.fit_style(highlight);
+        assert_eq!((mono.fg, mono.bg), (None, None));
+        assert!(mono.add_modifier.contains(Modifier::REVERSED));
+    }
+
+    #[test]
+    fn the_built_in_themes_load_cleanly() {
+        for (name, _) in BUILT_IN {
+            let theme = Theme::load(name);
+            assert_eq!(theme.problems, Vec::<String>::new(), "{name}");
+        }
+        assert!(!Theme::load("no-such-theme").problems.is_empty());
+    }
+}
@@ -0,0 +1,274 @@
+use crate::action::Action;
+use crate::app::{CurrentScreen, Exit};
+use crate::chat_store::ChatStore;
+use crate::chat_structs::Role;
+use crate::editor::EditTarget;
+use crate::export::{ExportFormat, default_file_name};
+use crate::keymap::{self, KeyContext};
+use crate::prompt_history::HistorySearch;
+use crate::ui::mouse;
+use crate::ui::sidebar::{ConfirmAction, SidebarInputMode};
+use crate::ui::{ChatView, Config, MainMenu, SearchView};
+use anyhow::Result;
+use std::path::PathBuf;
+
+impl CurrentScreen {
+    /// the chat view, opened first if this is another screen
+    pub fn chat_view(&mut self) -> Result<&mut ChatView> {
+        if !matches!(self, CurrentScreen::ChatView(_)) {
+            let store = ChatStore::open(PathBuf::from("chats"))?;
+            *self = CurrentScreen::ChatView(Box::new(ChatView::open(store)?));
+        }
+        match self {
+            CurrentScreen::ChatView(chat) => Ok(chat),
+            _ => unreachable!(),
+        }
+    }
+
+    /// the chat view if it is what's open, also underneath the palette
+    pub fn chat_view_mut(&mut self) -> Option<&mut ChatView> {
+        match self {
+            CurrentScreen::ChatView(chat) => Some(chat),
+            CurrentScreen::Palette(palette) => palette.previous.chat_view_mut(),
+            _ => None,
+        }
+    }
+
+    pub fn chat_view_ref(&self) -> Option<&ChatView> {
+        match self {
+            CurrentScreen::ChatView(chat) => Some(chat),
+            CurrentScreen::Palette(palette) => palette.previous.chat_view_ref(),
+            _ => None,
+        }
+    }
+
+    /// where the screen keeps the line shown in the status bar until the next key
+    pub fn notice_mut(&mut self) -> Option<&mut Option<String>> {
+        match self {
+            CurrentScreen::ChatView(chat) => Some(&mut chat.notice),
+            CurrentScreen::Settings(config) => Some(&mut config.notice),
+            CurrentScreen::Palette(palette) => palette.previous.notice_mut(),
+            _ => None,
+        }
+    }
+
+    pub fn notice(&self) -> Option<&str> {
+        match self {
+            CurrentScreen::ChatView(chat) => chat.notice.as_deref(),
+            CurrentScreen::Settings(config) => config.notice.as_deref(),
+            CurrentScreen::Palette(palette) => palette.previous.notice(),
+            _ => None,
+        }
+    }
+
+    /// which keymap bindings apply right now. `None` while the palette is open,
+    /// and only the global ones while a prompt or popup takes the keys
+    pub fn key_context(&self) -> Option<KeyContext> {
+        match self {
+            CurrentScreen::ChatView(chat) => Some(
+                if chat.confirm.is_some()
+                    || chat.raw_view.is_some()
+                    || chat.code_picker.is_some()
+                    || chat.arena.is_some()
+                    || chat.find.as_ref().is_some_and(|find| find.typing)
+                    || chat.history_search.is_some()
+                    || (chat.show_sidebar && (chat.sidebar_input_mode.is_some() || chat.show_trash))
+                    || (!chat.command_completions().is_empty()
+                        && !chat.input.text().contains(char::is_whitespace))
+                {
+                    KeyContext::Global
+                } else if chat.show_sidebar {
+                    KeyContext::Sidebar
+                } else if chat.selecting {
+                    KeyContext::Messages
+                } else {
+                    KeyContext::Chat
+                },
+            ),
+            CurrentScreen::Palette(_) | CurrentScreen::Exit(_) => None,
+            _ => Some(KeyContext::Global),
+        }
+    }
+
+    pub fn run_action(&mut self, action: Action) -> Result<()> {
+        match action {
+            Action::Palette => self.open_palette(),
+            Action::OpenChats => _ = self.chat_view()?,
+            Action::OpenSearch => {
+                let store = match self {
+                    CurrentScreen::ChatView(chat) => chat.store.clone(),
+                    _ => ChatStore::open(PathBuf::from("chats"))?,
+                };
+                *self = CurrentScreen::Search(Box::new(SearchView::open(store)?));
+            }
+            Action::OpenSettings => *self = CurrentScreen::Settings(Box::new(Config::open())),
+            Action::EditSystemPrompt => match self {
+                CurrentScreen::Settings(config) => {
+                    config.edit_request = Some(EditTarget::SystemPrompt);
+                }
+                _ => self.chat_view()?.edit_request = Some(EditTarget::SystemPrompt),
+            },
+            Action::ToggleMouse => {
+                let on = !mouse::capturing();
+                mouse::set_capture(on)?;
+                if let Some(chat) = self.chat_view_mut() {
+                    chat.notice = Some(if on {
+                        "Mouse on".to_string()
+                    } else {
+                        format!(
+                            "Mouse off, the terminal selects text now ({} turns it back on)",
+                            keymap::get().hint(Action::ToggleMouse)
+                        )
+                    });
+                }
+            }
+            Action::MainMenu => *self = CurrentScreen::MainMenu(MainMenu { selected: 0 }),
+            Action::Quit => {
+                *self = CurrentScreen::Exit(Exit {
+                    data: "Bye!".to_string(),
+                });
+            }
+            _ => self.chat_view()?.run_chat_action(action)?,
+        }
+        Ok(())
+    }
+}
+
+impl ChatView {
+    /// the selected message or the one jumped to from search, or the last thing
+    /// we asked
+    pub fn message_to_edit(&self) -> Option<usize> {
+        let messages = self.messages.as_deref().unwrap_or_default();
+        self.focused_message
+            .or_else(|| messages.iter().rposition(|m| m.role == Role::User))
+    }
+
+    /// actions that need the chat view, the ones on a chat act on the one selected
+    /// in the sidebar
+    pub fn run_chat_action(&mut self, action: Action) -> Result<()> {
+        match action {
+            Action::ToggleSidebar => self.show_sidebar = !self.show_sidebar,
+            Action::ToggleTimestamps => self.show_timestamps = !self.show_timestamps,
+            Action::EditPrompt => self.edit_request = Some(EditTarget::Prompt),
+            Action::EditMessage => {
+                self.edit_request = self.message_to_edit().map(EditTarget::Message);
+            }
+            Action::HistorySearch => {
+                self.history.reset();
+                self.history_search = Some(HistorySearch {
+                    draft: self.input.text().to_string(),
+                    ..Default::default()
+                });
+            }
+            Action::SelectMessages => self.toggle_selecting(),
+            Action::SelectNext
+            | Action::SelectPrevious
+            | Action::SelectFirst
+            | Action::SelectLast
+                if self.selecting && !self.show_sidebar =>
+            {
+                self.step_message(action);
+            }
+            Action::CopyMessage
+            | Action::CopyCodeBlock
+            | Action::DeleteMessage
+            | Action::RegenerateMessage
+            | Action::ForkFromMessage
+            | Action::BookmarkMessage
+            | Action::ViewRawMessage
+            | Action::ToggleCollapse
+            | Action::ToggleCodeCollapse
+            | Action::NextBookmark
+            | Action::PreviousBookmark
+            | Action::CopyChat => self.run_message_action(action)?,
+            Action::ScrollUp => self.scroll_by(-self.page()),
+            Action::ScrollDown => self.scroll_by(self.page()),
+            Action::ScrollTop => self.scroll_to_top(),
+            Action::ScrollBottom => self.scroll_to_bottom(),
+            Action::FindInChat => self.start_find(),
+            Action::FindNext => self.step_find(true),
+            Action::FindPrevious => self.step_find(false),
+            Action::ScrollLeft => self.code_scroll = self.code_scroll.saturating_sub(8),
+            Action::ScrollRight => {
+                self.code_scroll = (self.code_scroll + 8).min(self.widest_code_line());
+            }
+            _ => self.run_sidebar_action(action)?,
+        }
+        Ok(())
+    }
+
+    /// the actions on the chat list
+    fn run_sidebar_action(&mut self, action: Action) -> Result<()> {
+        let selected = self
+            .branches
+            .get(self.selected_branch)
+            .filter(|_| self.selected_folder.is_none())
+            .cloned();
+        match action {
+            Action::NewChat => self.sidebar_prompt(SidebarInputMode::NewBranch, "Default Chat"),
+            Action::RenameChat => {
+                if let Some(branch) = selected {
+                    self.sidebar_prompt(SidebarInputMode::Renaming, &branch.name);
+                }
+            }
+            Action::DeleteChat => {
+                if let Some(branch) = selected {
+                    self.confirm = Some(ConfirmAction::Delete(branch.id));
+                }
+            }
+            Action::ArchiveChat if selected.is_some() => self.toggle_archive()?,
+            Action::PinChat if selected.is_some() => self.toggle_pin()?,
+            Action::MoveToFolder => {
+                if let Some(branch) = selected {
+                    self.sidebar_prompt(SidebarInputMode::MoveToFolder, &branch.folder);
+                }
+            }
+            Action::TagChat => {
+                if let Some(branch) = selected {
+                    self.sidebar_prompt(SidebarInputMode::Tagging, &branch.tags.join(", "));
+                }
+            }
+            Action::ExportChat => {
+                if let Some(branch) = selected {
+                    let file = default_file_name(&branch, ExportFormat::Markdown);
+                    self.sidebar_prompt(SidebarInputMode::Export, &file);
+                }
+            }
+            Action::FilterByTag => {
+                let filter = self.tag_filter.clone().unwrap_or_default();
+                self.sidebar_prompt(SidebarInputMode::TagFilter, &filter);
+            }
+            Action::ImportChats => {
+                self.sidebar_prompt(SidebarInputMode::Import, "conversations.json");
+            }
+            Action::SortByActivity => self.sort_by_activity = !self.sort_by_activity,
+            Action::ShowArchived => self.show_archived = !self.show_archived,
+            Action::ShowTrash => {
+                self.show_sidebar = true;
+                self.show_trash = true;
+                self.trash_selected = 0;
+            }
+            Action::Undo => self.undo()?,
+            Action::SelectNext => self.step_selection(true),
+            Action::SelectPrevious => self.step_selection(false),
+            Action::SelectFirst => self.select_end(false),
+            Action::SelectLast => self.select_end(true),
+            // manual order only makes sense when it is the one shown
+            Action::MoveChatDown if selected.is_some() && !self.sort_by_activity => {
+                self.move_branch(true)?;
+            }
+            Action::MoveChatUp if selected.is_some() && !self.sort_by_activity => {
+                self.move_branch(false)?;
+            }
+            _ => {}
+        }
+        Ok(())
+    }
+
+    /// ask for a line of text in the sidebar, starting with `text`
+    fn sidebar_prompt(&mut self, mode: SidebarInputMode, text: &str) {
+        self.show_sidebar = true;
+        self.sidebar_input_mode = Some(mode);
+        self.sidebar_input_buffer = text.to_string();
+    }
+}
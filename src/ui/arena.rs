@@ -0,0 +1,349 @@
+// arena mode: `/arena fast,smart <prompt>` sends one prompt to two or more profiles
+// at once and shows the replies side by side. each pane fills in whole once its
+// reply is done: the llm crate only has ChatProvider::chat, which hands back the
+// finished reply, so there are no tokens to show earlier (the same goes for normal
+// replies). picking one adds it to the chat, switches to its profile and counts the
+// round in the leaderboard.
+use crate::ai::reply;
+use crate::ai_backend::AISettings;
+use crate::app::CurrentScreen;
+use crate::chat_structs::{Message, Role};
+use crate::leaderboard::{Leaderboard, Round};
+use crate::profiles::Profiles;
+use crate::theme;
+use crate::ui::ChatView;
+use crate::ui::chat_view::{message_body, message_details};
+use crate::ui::status_bar;
+use anyhow::{Context, Result, bail};
+use chrono::Utc;
+use crossterm::event::{KeyCode, KeyEvent};
+use ratatui::{
+    buffer::Buffer,
+    layout::{Constraint, Direction, Layout, Rect},
+    text::Line,
+    widgets::{Clear, Paragraph, Widget, Wrap},
+};
+use std::path::Path;
+use tokio::sync::mpsc::{UnboundedReceiver, unbounded_channel};
+use tokio::task::JoinHandle;
+
+pub struct Arena {
+    pub prompt: String,
+    pub contenders: Vec<Contender>,
+    // the highlighted pane and how far every pane is scrolled
+    pub selected: usize,
+    pub scroll: u16,
+    // replies by contender
+    rx: UnboundedReceiver<(usize, Message)>,
+    // the requests still going, dropping the arena stops them
+    tasks: Vec<JoinHandle<()>>,
+}
+
+pub struct Contender {
+    pub profile: String,
+    pub model: String,
+    pub reply: Option<Message>,
+}
+
+impl Arena {
+    pub fn waiting(&self) -> usize {
+        self.contenders.iter().filter(|c| c.reply.is_none()).count()
+    }
+}
+
+impl Drop for Arena {
+    fn drop(&mut self) {
+        for task in &self.tasks {
+            task.abort();
+        }
+    }
+}
+
+/// "/arena fast,smart <prompt>" split into the profile names and the prompt
+pub fn parse(args: &str) -> Result<(Vec<&str>, &str)> {
+    let (names, prompt) = args
+        .split_once(char::is_whitespace)
+        .map_or((args, ""), |(names, prompt)| (names, prompt.trim()));
+    let names: Vec<&str> = names.split(',').filter(|n| !n.is_empty()).collect();
+    if names.len() < 2 || prompt.is_empty() {
+        bail!("usage: /arena <profile>,<profile>[,...] <prompt>");
+    }
+    Ok((names, prompt))
+}
+
+impl ChatView {
+    /// ask every profile in `names` for a reply to `prompt` after the open chat
+    pub fn start_arena(&mut self, names: &[&str], prompt: &str) -> Result<()> {
+        let profiles = Profiles::load(Path::new("profiles.json"))?;
+        let mut settings: Vec<(String, AISettings)> = Vec::new();
+        for name in names {
+            let profile = profiles.profiles.get(*name).with_context(|| {
+                format!(
+                    "no profile called '{name}' (there are: {})",
+                    profiles.names().collect::<Vec<_>>().join(", ")
+                )
+            })?;
+            settings.push(((*name).to_string(), profile.clone()));
+        }
+
+        let history = self.messages.clone().unwrap_or_default();
+        let (tx, rx) = unbounded_channel();
+        let mut contenders = Vec::new();
+        let mut tasks = Vec::new();
+        for (i, (profile, settings)) in settings.into_iter().enumerate() {
+            contenders.push(Contender {
+                profile,
+                model: settings.model.clone(),
+                reply: None,
+            });
+            let (tx, history, prompt) = (tx.clone(), history.clone(), prompt.to_string());
+            tasks.push(tokio::spawn(async move {
+                let _ = tx.send((i, reply(&history, &prompt, &settings).await));
+            }));
+        }
+        self.arena = Some(Arena {
+            prompt: prompt.to_string(),
+            contenders,
+            selected: 0,
+            scroll: 0,
+            rx,
+            tasks,
+        });
+        self.selecting = false;
+        self.focused_message = None;
+        self.find = None;
+        Ok(())
+    }
+
+    /// put the replies that came in into their panes
+    pub fn drain_arena(&mut self) {
+        let Some(arena) = self.arena.as_mut() else {
+            return;
+        };
+        while let Ok((i, reply)) = arena.rx.try_recv() {
+            if let Some(contender) = arena.contenders.get_mut(i) {
+                contender.reply = Some(reply);
+            }
+        }
+    }
+
+    /// make contender `i` the winner: its reply goes into the chat after the
+    /// prompt, its profile answers from now on and the round is counted. the
+    /// arena stays open when the chat couldn't be saved, to pick again
+    pub fn pick_winner(&mut self, i: usize) -> Result<()> {
+        let Some(arena) = &self.arena else {
+            return Ok(());
+        };
+        let Some(winner) = arena.contenders.get(i) else {
+            return Ok(());
+        };
+        let Some(reply) = winner.reply.clone() else {
+            self.notice = Some(format!("{} hasn't answered yet", winner.profile));
+            return Ok(());
+        };
+        let name = winner.profile.clone();
+        let prompt = arena.prompt.clone();
+        let mut messages = self.messages.clone().unwrap_or_default();
+        messages.push(Message::new(Role::User, prompt.clone()));
+        messages.push(reply.clone());
+        if !self.replace_open_chat(messages, "the winning reply")? {
+            return Ok(());
+        }
+        self.follow = true;
+        let Some(arena) = self.arena.take() else {
+            return Ok(());
+        };
+        if let Role::Assistant(assistant) = &reply.role
+            && let Some(cost) = assistant.generation.as_ref().and_then(|g| g.cost)
+        {
+            status_bar::add_cost(cost);
+        }
+
+        let round = Round {
+            at: Utc::now(),
+            prompt,
+            contenders: arena
+                .contenders
+                .iter()
+                .map(|c| (c.profile.clone(), c.model.clone()))
+                .collect(),
+            winner: name.clone(),
+        };
+        if let Err(err) = Leaderboard::record(&self.store, round) {
+            self.notice = Some(format!("{name} wins, but the round isn't counted: {err:#}"));
+            return Ok(());
+        }
+        let path = Path::new("profiles.json");
+        let switched = Profiles::load(path).and_then(|mut profiles| {
+            profiles.switch(&name)?;
+            profiles.save(path)
+        });
+        self.notice = Some(match switched {
+            Ok(()) => format!("{name} wins, switched to it"),
+            Err(err) => format!("{name} wins, but switching to it failed: {err:#}"),
+        });
+        Ok(())
+    }
+
+    /// the standings from the leaderboard as a notice
+    pub fn show_leaderboard(&mut self) -> Result<()> {
+        let standings = Leaderboard::load(&self.store)?.standings();
+        self.notice = Some(if standings.is_empty() {
+            "No arena rounds yet, /arena <profile>,<profile> <prompt> starts one".to_string()
+        } else {
+            format!("Leaderboard: {}", standings.join(", "))
+        });
+        Ok(())
+    }
+
+    /// the replies side by side over the messages, the prompt above them
+    pub fn render_arena(&self, area: Rect, buf: &mut Buffer) {
+        let Some(shown) = &self.arena else {
+            return;
+        };
+        let theme = theme::get();
+        Clear.render(area, buf);
+        let rows = Layout::default()
+            .direction(Direction::Vertical)
+            .constraints([Constraint::Length(1), Constraint::Min(0)])
+            .split(area);
+        let prompt = shown.prompt.lines().next().unwrap_or("");
+        Paragraph::new(Line::from(format!(
+            " Arena: {prompt}  (←/→, Enter or 1-9 picks, j/k scroll, Esc cancels)"
+        )))
+        .style(theme.muted)
+        .render(rows[0], buf);
+
+        let count = u32::try_from(shown.contenders.len()).unwrap_or(1);
+        let panes = Layout::default()
+            .direction(Direction::Horizontal)
+            .constraints(shown.contenders.iter().map(|_| Constraint::Ratio(1, count)))
+            .split(rows[1]);
+        for (i, (contender, pane)) in shown.contenders.iter().zip(panes.iter()).enumerate() {
+            let mut block = theme::block().title(format!(
+                " {} · {} · {} ",
+                i + 1,
+                contender.profile,
+                contender.model
+            ));
+            if i == shown.selected {
+                block = block.border_style(theme.selection);
+            }
+            let lines = match &contender.reply {
+                Some(reply) => {
+                    block = block.title_bottom(Line::styled(message_details(reply), theme.muted));
+                    let width = usize::from(pane.width.saturating_sub(2));
+                    message_body(&reply.content, width, 0, &[])
+                }
+                None => vec![Line::styled(
+                    format!("{} waiting for the reply", status_bar::spinner()),
+                    theme.muted,
+                )],
+            };
+            Paragraph::new(lines)
+                .wrap(Wrap { trim: false })
+                .scroll((shown.scroll, 0))
+                .block(block)
+                .render(*pane, buf);
+        }
+    }
+}
+
+impl CurrentScreen {
+    pub fn handle_arena(chat: &mut ChatView, key: KeyEvent) -> Result<()> {
+        let Some(arena) = chat.arena.as_mut() else {
+            return Ok(());
+        };
+        let last = arena.contenders.len().saturating_sub(1);
+        match key.code {
+            KeyCode::Esc => {
+                chat.arena = None;
+                chat.notice = Some("Arena closed, no winner was counted".to_string());
+            }
+            KeyCode::Enter => {
+                let selected = arena.selected;
+                chat.pick_winner(selected)?;
+            }
+            KeyCode::Char(c @ '1'..='9') => {
+                let n = c as usize - '1' as usize;
+                chat.pick_winner(n)?;
+            }
+            KeyCode::Char('h') | KeyCode::Left | KeyCode::BackTab => {
+                arena.selected = arena.selected.saturating_sub(1);
+            }
+            KeyCode::Char('l') | KeyCode::Right | KeyCode::Tab => {
+                arena.selected = (arena.selected + 1).min(last);
+            }
+            KeyCode::Char('j') | KeyCode::Down => arena.scroll = arena.scroll.saturating_add(1),
+            KeyCode::Char('k') | KeyCode::Up => arena.scroll = arena.scroll.saturating_sub(1),
+            KeyCode::PageDown | KeyCode::Char(' ') => {
+                arena.scroll = arena.scroll.saturating_add(10);
+            }
+            KeyCode::PageUp => arena.scroll = arena.scroll.saturating_sub(10),
+            _ => {}
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::chat_store::ChatStore;
+    use crate::chat_structs::Assistant;
+
+    #[test]
+    fn the_arena_names_profiles_then_the_prompt() {
+        assert_eq!(
+            parse("fast,smart why is the sky blue").unwrap(),
+            (vec!["fast", "smart"], "why is the sky blue")
+        );
+        assert!(parse("fast why").is_err());
+        assert!(parse("fast,smart").is_err());
+    }
+
+    #[test]
+    fn a_refused_pick_adds_nothing() {
+        let dir = std::env::temp_dir().join(format!("llm-tui-rs-arena-{}", std::process::id()));
+        let mut chat = ChatView::open(ChatStore::open(dir.clone()).unwrap()).unwrap();
+        chat.save_branches().unwrap();
+        chat.messages = Some(vec![Message::new(Role::User, "first".into())]);
+        assert!(chat.save_open_chat("the test").unwrap());
+
+        // another window rewrites the chat, so the pick can't be saved
+        let mut other = ChatStore::open(dir.clone()).unwrap();
+        let mut branches = other.load_index().unwrap();
+        let mut theirs = other.load_messages(branches[0].id).unwrap();
+        theirs[0].content = "changed".into();
+        other.save_messages(&mut branches[0], &mut theirs).unwrap();
+
+        let reply = Message::new(Role::Assistant(Assistant::new("m")), "reply".into());
+        chat.arena = Some(Arena {
+            prompt: "prompt".into(),
+            contenders: vec![Contender {
+                profile: "only-in-this-test".into(),
+                model: "m".into(),
+                reply: Some(reply),
+            }],
+            selected: 0,
+            scroll: 0,
+            rx: unbounded_channel().1,
+            tasks: Vec::new(),
+        });
+        chat.pick_winner(0).unwrap();
+        assert!(chat.arena.is_some());
+        let contents = |chat: &ChatView| -> Vec<String> {
+            let messages = chat.messages.as_deref().unwrap_or_default();
+            messages.iter().map(|m| m.content.clone()).collect()
+        };
+        assert_eq!(contents(&chat), ["changed"]);
+
+        // picked again on top of what the other window wrote
+        chat.pick_winner(0).unwrap();
+        assert!(chat.arena.is_none());
+        assert_eq!(contents(&chat), ["changed", "prompt", "reply"]);
+        let board = Leaderboard::load(&chat.store).unwrap();
+        std::fs::remove_dir_all(dir).unwrap();
+        assert_eq!(board.standings(), ["only-in-this-test 1-0"]);
+    }
+}
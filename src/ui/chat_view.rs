@@ -1,103 +1,678 @@
-use crate::CurrentScreen;
-use crate::ai::{generate_chat_title, run_ai};
-use crate::ai_backend::{AIBackend, AISettings};
+use crate::action::Action;
+use crate::ai::{generate_chat_title, reply};
+use crate::ai_backend::AISettings;
+use crate::app::CurrentScreen;
 use crate::chat_branch::ChatBranch;
-use crate::chat_structs::{Assistant, Message, Role};
-use crate::ui::MainMenu;
-use anyhow::{Context, Result, bail};
-use crossterm::event::{KeyCode, KeyEvent};
+use crate::chat_store::{ChatStore, Saved};
+use crate::chat_structs::{Assistant, Generation, Message, Role};
+use crate::commands::{self, Parsed};
+use crate::editor::EditTarget;
+use crate::highlight::{code_block_lines, collapsed_code_line};
+use crate::keymap;
+use crate::markdown::{self, Segment};
+use crate::prompt_history::{HistorySearch, PromptHistory};
+use crate::theme;
+use crate::ui::arena::Arena;
+use crate::ui::collapse::{collapsed_line, is_collapsed};
+use crate::ui::copy::CodePicker;
+use crate::ui::find::Find;
+use crate::ui::render_cache::{Marks, RenderCache};
+use crate::ui::selection::selection_hints;
+use crate::ui::sidebar::{ConfirmAction, SidebarInputMode, UndoEntry};
+use crate::ui::status_bar;
+use crate::ui::text_area::TextArea;
+use crate::ui_config;
+use anyhow::{Result, bail};
+use chrono::{DateTime, Local, Utc};
+use crossterm::event::{KeyCode, KeyEvent, KeyModifiers};
 use ratatui::prelude::*;
 use ratatui::{
     buffer::Buffer,
     layout::Rect,
     text::{Line, Span},
-    widgets::{Block, Borders, Paragraph, Widget},
+    widgets::{
+        Paragraph, Scrollbar, ScrollbarOrientation, ScrollbarState, StatefulWidget, Widget, Wrap,
+    },
 };
+use std::cell::{Cell, RefCell};
+use std::collections::HashSet;
 use std::path::PathBuf;
-use tokio::sync::mpsc::{UnboundedReceiver, UnboundedSender};
+use std::time::{Duration, Instant};
+use tokio::sync::mpsc::{UnboundedReceiver, UnboundedSender, unbounded_channel};
 use tui_markdown::from_str;
+use unicode_width::UnicodeWidthStr;
 
-pub enum SidebarInputMode {
-    NewBranch, // User is naming a new branch
-    Renaming,  // User is renaming an existing branch
-}
+// most lines the input box grows to
+const INPUT_ROWS: usize = 10;
+// how often to look for changes made by other instances
+const SYNC_INTERVAL: Duration = Duration::from_millis(500);
 
+// a bag of ui toggles, an enum per flag wouldn't make it any clearer
+#[allow(clippy::struct_excessive_bools)]
 pub struct ChatView {
+    // only the open chat has its messages in memory
     pub messages: Option<Vec<Message>>,
-    pub input_buffer: String,
+    pub open_chat: usize, // id
+    pub input: TextArea,
+    pub history: PromptHistory,
+    pub history_search: Option<HistorySearch>,
+    pub command_selected: usize, // row of the slash command popup
     // the sidebar fields:
     pub branches: Vec<ChatBranch>,
     pub selected_branch: usize,
     pub show_sidebar: bool,
+    // columns, dragging the sidebar's edge with the mouse changes it
+    pub sidebar_width: u16,
+    pub resizing_sidebar: bool,
+    // the chat list box at the last draw, for finding the row clicked
+    pub sidebar_list: Cell<Rect>,
 
     // where we persist them:
-    pub storage_path: PathBuf,
+    pub store: ChatStore,
     // renaming and creating new chat branches
     pub sidebar_input_mode: Option<SidebarInputMode>,
     pub sidebar_input_buffer: String,
+    // deleting, archiving and reordering chats
+    pub trash: Vec<ChatBranch>,
+    pub show_trash: bool,
+    pub trash_selected: usize,
+    pub show_archived: bool,
+    pub sort_by_activity: bool, // newest chats first instead of the manual order
+    pub undo_stack: Vec<UndoEntry>,
+    pub confirm: Option<ConfirmAction>,
+    pub notice: Option<String>,
+    // folders and tags
+    pub selected_folder: Option<String>, // set while the cursor is on a folder row
+    pub collapsed_folders: HashSet<String>,
+    pub tag_filter: Option<String>,
 
+    // the row at the top when not following, see scroll.rs
     pub scroll: usize,
+    pub follow: bool,
+    // the inner size of the messages box at the last draw, for paging
+    pub viewport: Cell<(u16, u16)>,
+    // every message's lines from the last draw, see render_cache.rs
+    pub render_cache: RefCell<RenderCache>,
+    // set when jumping here from search, the message gets highlighted
+    pub focused_message: Option<usize>,
+    // moving focused_message with the keys, see selection.rs
+    pub selecting: bool,
+    // a message's markdown as it was written, and how far it is scrolled
+    pub raw_view: Option<(usize, u16)>,
+    // which code block of a message to copy or collapse
+    pub code_picker: Option<CodePicker>,
+    // finding text in this chat, see find.rs
+    pub find: Option<Find>,
+    // replies of several profiles to pick from, see arena.rs
+    pub arena: Option<Arena>,
+    // how many columns code blocks are scrolled to the right
+    pub code_scroll: usize,
+    pub show_timestamps: bool,
+    // set when something should be opened in $EDITOR, the main loop does that
+    pub edit_request: Option<EditTarget>,
+    // ids of chats waiting on a reply, once per prompt
+    pub pending: Vec<usize>,
+    pub last_sync: Instant,
     // for async / threading for messages
-    pub ai_tx: UnboundedSender<ChatBranch>,
-    pub ai_rx: UnboundedReceiver<ChatBranch>,
+    pub ai_tx: UnboundedSender<(usize, Message)>,
+    pub ai_rx: UnboundedReceiver<(usize, Message)>,
     pub ai_title_tx: UnboundedSender<ChatBranch>,
     pub ai_title_rx: UnboundedReceiver<ChatBranch>,
 }
 
 impl ChatView {
+    pub fn open(mut store: ChatStore) -> Result<Self> {
+        let mut branches = store.load_index()?;
+        let trash = store.load_trash()?;
+        if branches.is_empty() {
+            let id = store.new_id(&branches, &trash)?;
+            branches.push(ChatBranch::new(id, "Default Chat"));
+        }
+        let (ai_tx, ai_rx) = unbounded_channel::<(usize, Message)>();
+        let (ai_title_tx, ai_title_rx) = unbounded_channel::<ChatBranch>();
+        let (history, notice) = PromptHistory::load_or_empty(&store);
+
+        Ok(ChatView {
+            input: TextArea::default(),
+            history,
+            history_search: None,
+            command_selected: 0,
+            // load messages for selected branch
+            messages: Some(store.load_messages(branches[0].id)?),
+            open_chat: branches[0].id,
+            branches,
+            selected_branch: 0,
+            show_sidebar: false,
+            sidebar_width: ui_config::get().sidebar_width,
+            resizing_sidebar: false,
+            sidebar_list: Cell::new(Rect::default()),
+            store,
+            sidebar_input_mode: None,
+            sidebar_input_buffer: String::new(),
+            trash,
+            show_trash: false,
+            trash_selected: 0,
+            show_archived: false,
+            sort_by_activity: false,
+            undo_stack: Vec::new(),
+            confirm: None,
+            notice,
+            selected_folder: None,
+            collapsed_folders: HashSet::new(),
+            tag_filter: None,
+            scroll: 0,
+            follow: true,
+            viewport: Cell::new((0, 0)),
+            render_cache: RefCell::new(RenderCache::default()),
+            focused_message: None,
+            selecting: false,
+            raw_view: None,
+            code_picker: None,
+            find: None,
+            arena: None,
+            code_scroll: 0,
+            edit_request: None,
+            show_timestamps: false,
+            pending: Vec::new(),
+            last_sync: Instant::now(),
+            ai_tx,
+            ai_rx,
+            ai_title_tx,
+            ai_title_rx,
+        })
+    }
+
+    /// open the chat with `id` and scroll to `message`, on to its first line with
+    /// one of `terms` when that is further down than the view reaches
+    pub fn jump_to(&mut self, id: usize, message: Option<usize>, terms: &[String]) -> Result<()> {
+        let Some(idx) = self.index_of(id) else {
+            return Ok(());
+        };
+        self.select_branch(idx)?;
+        self.focused_message = message;
+        match message {
+            Some(m) => {
+                self.scroll = self.hit_row(m, terms);
+                self.follow = false;
+            }
+            None => self.follow = true,
+        }
+        Ok(())
+    }
+
+    /// how far code blocks scroll right, until the widest line is almost gone
+    pub fn widest_code_line(&self) -> usize {
+        self.messages
+            .as_deref()
+            .unwrap_or_default()
+            .iter()
+            .flat_map(|m| markdown::code_blocks(&m.content))
+            .filter_map(|block| block.code.lines().map(UnicodeWidthStr::width).max())
+            .max()
+            .unwrap_or(0)
+            .saturating_sub(8)
+    }
+
     /// helper to drain any finished AI responses
     pub fn drain_ai(&mut self) -> anyhow::Result<()> {
         // there has to be a better way instead of having 2 rx/tx...
-        while let Ok(updated) = self.ai_rx.try_recv() {
-            let idx = updated.id;
-            if idx < self.branches.len() {
-                // dont replace the whole branch, bc it will revert the name
-                self.branches[idx].messages = updated.messages;
-                self.messages = Some(self.branches[idx].messages.clone());
-                ChatBranch::save_all(&self.storage_path, &self.branches)?;
+        while let Ok((id, reply)) = self.ai_rx.try_recv() {
+            if let Some(pos) = self.pending.iter().position(|p| *p == id) {
+                self.pending.remove(pos);
+            }
+            if let Role::Assistant(Assistant {
+                generation:
+                    Some(Generation {
+                        cost: Some(cost), ..
+                    }),
+                ..
+            }) = reply.role
+            {
+                status_bar::add_cost(cost);
+            }
+            // ids are not indices anymore once chats get deleted or moved
+            let Some(idx) = self.index_of(id) else {
+                continue;
+            };
+            if id == self.open_chat {
+                let messages = self.messages.get_or_insert_with(Vec::new);
+                messages.push(reply);
+                match self
+                    .store
+                    .save_messages(&mut self.branches[idx], messages)?
+                {
+                    Saved::Written => {}
+                    Saved::Merged => self.render_cache.get_mut().clear(),
+                    Saved::Refused => {
+                        self.notice = Some(format!(
+                            "'{}' changed in another window, this reply is not saved",
+                            self.branches[idx].name
+                        ));
+                    }
+                }
+            } else {
+                // the user went to another chat in the meantime
+                self.store.append_message(&mut self.branches[idx], reply)?;
             }
         }
         while let Ok(updated) = self.ai_title_rx.try_recv() {
-            let idx = updated.id;
-            if idx < self.branches.len() {
+            if let Some(idx) = self.index_of(updated.id) {
                 self.branches[idx].name = updated.name;
-                ChatBranch::save_all(&self.storage_path, &self.branches)?;
+                self.save_branches()?;
+            }
+        }
+        self.drain_arena();
+        Ok(())
+    }
+
+    /// pick up chats and messages that other instances wrote
+    pub fn sync_store(&mut self) -> Result<()> {
+        if self.last_sync.elapsed() < SYNC_INTERVAL {
+            return Ok(());
+        }
+        self.last_sync = Instant::now();
+        if !self.store.changed_on_disk() {
+            return Ok(());
+        }
+        let selected = self.branches.get(self.selected_branch).map(|b| b.id);
+        let open_stamp = self.open_stamp();
+        self.store.reload(&mut self.branches, &mut self.trash)?;
+        self.reselect(selected)?;
+        self.reload_open_chat_if(open_stamp)
+    }
+
+    /// add `prompt` to the open chat and ask the model for a reply in the background
+    /// returns false when the chat changed in another window and nothing was sent
+    pub fn send_prompt(&mut self, prompt: String, settings: &AISettings) -> Result<bool> {
+        let Some(idx) = self.index_of(self.open_chat) else {
+            return Ok(false);
+        };
+        self.focused_message = None;
+        self.selecting = false;
+        self.follow = true;
+        let messages = self.messages.get_or_insert_with(Vec::new);
+        messages.push(Message::new(Role::User, prompt.clone()));
+        let saved = self
+            .store
+            .save_messages(&mut self.branches[idx], messages)?;
+        if saved != Saved::Written {
+            self.render_cache.get_mut().clear();
+        }
+        if saved == Saved::Refused {
+            *messages = self.store.reload_chat(&mut self.branches[idx])?;
+            self.notice =
+                Some("This chat changed in another window, check it and send again".to_string());
+            return Ok(false);
+        }
+        // whatever another window added before it is part of the history now
+        let history = messages[..messages.len() - 1].to_vec();
+        let id = self.open_chat;
+        self.pending.push(id);
+
+        // ---- spawn the real AI call in the background ----
+        let tx = self.ai_tx.clone();
+        let settings_clone = settings.clone();
+        tokio::spawn(async move {
+            let reply = reply(&history, &prompt, &settings_clone).await;
+            // fire‐and‐forget send back to UI
+            let _ = tx.send((id, reply));
+        });
+
+        let name = &self.branches[idx].name;
+        if name == "Default Chat" || name.is_empty() {
+            self.generate_title(idx, settings);
+        }
+        Ok(true)
+    }
+
+    /// let the model name the open chat at `idx`, in the background
+    pub fn generate_title(&self, idx: usize, settings: &AISettings) {
+        // idk how to make this behavior tbh
+        let mut branch = self.branches[idx].clone();
+        branch.messages = self.messages.clone().unwrap_or_default();
+        let name_tx = self.ai_title_tx.clone();
+        let settings = settings.clone();
+        tokio::spawn(async move {
+            if let Ok(generated_title) =
+                generate_chat_title(Some(&branch.messages), &settings).await
+            {
+                branch.name = generated_title;
+                let _ = name_tx.send(branch);
+            }
+        });
+    }
+
+    /// Up or Ctrl-P: put the prompt sent before the shown one into the input
+    pub fn recall_older(&mut self) {
+        if let Some(prompt) = self.history.older(self.input.text()) {
+            self.input.set_text(prompt);
+        }
+    }
+
+    /// Down or Ctrl-N: the next newer prompt, or what was typed before going back
+    pub fn recall_newer(&mut self) {
+        if let Some(prompt) = self.history.newer() {
+            self.input.set_text(prompt);
+        }
+    }
+
+    /// the text `target` holds right now, the editor starts with it
+    pub fn edit_text(&self, target: EditTarget) -> Option<String> {
+        match target {
+            EditTarget::Prompt => Some(self.input.text().to_string()),
+            EditTarget::SystemPrompt => AISettings::load_all(&PathBuf::from("settings.json"))
+                .ok()
+                .map(|s| s.system_prompt),
+            EditTarget::Message(i) => Some(self.messages.as_ref()?.get(i)?.content.clone()),
+        }
+    }
+
+    /// put what came back from the editor where it was taken from. the prompt is
+    /// left in the input to look over before sending
+    pub fn apply_edit(&mut self, target: EditTarget, text: String) -> Result<()> {
+        match target {
+            EditTarget::Prompt => self.input.set_text(&text),
+            EditTarget::SystemPrompt => {
+                let path = PathBuf::from("settings.json");
+                let mut settings = AISettings::load_all(&path)?;
+                settings.system_prompt = text;
+                AISettings::write_all(&path, &settings)?;
+            }
+            EditTarget::Message(i) => {
+                let Some(message) = self.messages.as_mut().and_then(|m| m.get_mut(i)) else {
+                    return Ok(());
+                };
+                if message.content == text {
+                    return Ok(());
+                }
+                message.content = text;
+                self.message_changed(i);
+                self.save_open_chat("the edit")?;
             }
         }
         Ok(())
     }
+
+    /// write the open chat's messages. if another window changed the chat in the
+    /// meantime it is read back instead, the notice says `what` is not saved and
+    /// false comes back
+    pub fn save_open_chat(&mut self, what: &str) -> Result<bool> {
+        let messages = self.messages.clone().unwrap_or_default();
+        self.replace_open_chat(messages, what)
+    }
+
+    /// `save_open_chat` for `messages` instead of what is open. they only become
+    /// the open chat's messages once they are written
+    pub fn replace_open_chat(&mut self, mut messages: Vec<Message>, what: &str) -> Result<bool> {
+        let Some(idx) = self.index_of(self.open_chat) else {
+            return Ok(false);
+        };
+        let saved = self
+            .store
+            .save_messages(&mut self.branches[idx], &mut messages)?;
+        if saved != Saved::Written {
+            self.render_cache.get_mut().clear();
+        }
+        if saved == Saved::Refused {
+            self.messages = Some(self.store.reload_chat(&mut self.branches[idx])?);
+            self.notice = Some(format!(
+                "This chat changed in another window, {what} is not saved"
+            ));
+            return Ok(false);
+        }
+        self.messages = Some(messages);
+        Ok(true)
+    }
+
+    /// put what was sent in the prompt history, a history that can't be saved
+    /// shouldn't stop the sending
+    pub fn remember_prompt(&mut self, prompt: &str) {
+        if let Err(err) = self.history.push(&self.store, prompt) {
+            self.notice = Some(format!("Can't save the prompt history: {err:#}"));
+        }
+    }
+
+    /// save bookmarks and what is collapsed in the open chat without making it the
+    /// latest
+    pub fn save_open_marks(&mut self) -> Result<()> {
+        let Some(idx) = self.index_of(self.open_chat) else {
+            return Ok(());
+        };
+        let messages = self.messages.as_deref().unwrap_or_default();
+        self.store.save_marks(&self.branches[idx], messages)
+    }
 }
 
-fn iter_messages<'a>(messages: &'a [Message], lines: &mut Vec<Line<'a>>) {
-    for msg in messages {
-        let prefix = match msg.role {
-            Role::User => Span::styled(
-                "You: ",
-                Style::default()
-                    .fg(Color::Green)
-                    .add_modifier(Modifier::BOLD),
-            ),
-            Role::Assistant(ref assistant) => Span::styled(
-                // "Assistant: ",
-                format!("{}: ", assistant.model.as_str()),
-                Style::default()
-                    .fg(Color::Blue)
-                    .add_modifier(Modifier::BOLD),
-            ),
+/// "just now", "5m", "3h", "2d" or the date for anything older than a week
+pub fn short_age(time: DateTime<Utc>) -> String {
+    let secs = (Utc::now() - time).num_seconds();
+    match secs {
+        ..60 => "now".to_string(),
+        60..3_600 => format!("{}m", secs / 60),
+        3_600..86_400 => format!("{}h", secs / 3_600),
+        86_400..604_800 => format!("{}d", secs / 86_400),
+        _ => time.with_timezone(&Local).format("%Y-%m-%d").to_string(),
+    }
+}
+
+pub fn relative_time(time: DateTime<Utc>) -> String {
+    match short_age(time) {
+        age if age == "now" => "just now".to_string(),
+        age if age.contains('-') => age,
+        age => format!("{age} ago"),
+    }
+}
+
+/// the dim bit after the name: when the message was written and how
+pub fn message_details(msg: &Message) -> String {
+    let mut details = Vec::new();
+    if let Some(created) = msg.created_at {
+        details.push(relative_time(created));
+    }
+    if let Role::Assistant(Assistant {
+        generation: Some(generation),
+        ..
+    }) = &msg.role
+    {
+        details.push(format!(
+            "{:?} t={} max={}",
+            generation.backend, generation.temperature, generation.max_tokens
+        ));
+        #[allow(clippy::cast_precision_loss)]
+        details.push(format!("{:.1}s", generation.latency_ms as f64 / 1000.0));
+        if generation.reply_tokens > 0 {
+            details.push(format!("~{} tokens", generation.reply_tokens));
+        }
+    }
+    details.join(" · ")
+}
+
+/// a message's markdown as lines. fenced code is highlighted here and cut to
+/// `width` columns, `code_scroll` columns in, the rest goes to tui-markdown.
+/// the blocks in `collapsed_code` are one line
+pub fn message_body<'a>(
+    content: &'a str,
+    width: usize,
+    code_scroll: usize,
+    collapsed_code: &[usize],
+) -> Vec<Line<'a>> {
+    let segments = markdown::segments(content);
+    let numbered = segments
+        .iter()
+        .filter(|s| matches!(s, Segment::Code(..)))
+        .count()
+        > 1;
+    let mut lines: Vec<Line> = Vec::new();
+    for segment in segments {
+        // tui-markdown puts a line between blocks, do the same around code
+        if lines.last().is_some_and(|l| l.width() > 0) {
+            lines.push(Line::default());
+        }
+        match segment {
+            Segment::Markdown(text) => lines.extend(from_str(text).lines),
+            Segment::Code(n, block) => {
+                let number = numbered.then_some(n + 1);
+                if collapsed_code.contains(&n) {
+                    lines.push(collapsed_code_line(&block, number));
+                } else {
+                    lines.extend(code_block_lines(&block, number, width, code_scroll));
+                }
+            }
+        }
+    }
+    lines
+}
+
+/// one message the way it is drawn, before wrapping
+pub fn message_lines(
+    msg: &Message,
+    focused: bool,
+    show_timestamps: bool,
+    width: usize,
+    code_scroll: usize,
+) -> Vec<Line<'_>> {
+    let mut lines = Vec::new();
+    let theme = theme::get();
+    let mut prefix = match msg.role {
+        Role::User => Span::styled("You: ", theme.user),
+        Role::Assistant(ref assistant) => Span::styled(
+            // "Assistant: ",
+            format!("{}: ", assistant.model.as_str()),
+            theme.assistant,
+        ),
+    };
+
+    if msg.bookmarked {
+        prefix.content = format!("★ {}", prefix.content).into();
+    }
+    if focused {
+        prefix = prefix.patch_style(theme.selection);
+    }
+    if show_timestamps {
+        lines.push(Line::from(vec![
+            Span::from(" ".repeat(prefix.width())),
+            Span::styled(message_details(msg), theme.muted),
+        ]));
+    }
+
+    let mut body = message_body(&msg.content, width, code_scroll, &msg.collapsed_code);
+    if is_collapsed(msg, body.len()) {
+        body = vec![collapsed_line(
+            &msg.content,
+            body.len(),
+            width.saturating_sub(prefix.width()),
+        )];
+    }
+    // idk how this works but i like deepseek
+    // Text contains Lines which contains Spans, so loop through the lines and add the spans to the string.
+    for (i, line) in body.into_iter().enumerate() {
+        let mut spans = Vec::with_capacity(line.spans.len() + 1);
+        if i == 0 {
+            spans.push(prefix.clone());
+        } else {
+            spans.push(Span::from("".repeat(prefix.width())));
+        }
+        spans.extend(line.spans);
+        lines.push(Line::from(spans));
+    }
+    lines
+}
+
+impl ChatView {
+    /// the messages box, scrolled to `top_row` with a scrollbar when it doesn't fit
+    fn render_messages(&self, area: Rect, buf: &mut Buffer) {
+        let width = area.width.saturating_sub(2);
+        let height = area.height.saturating_sub(2);
+        self.viewport.set((width, height));
+
+        // only the messages in view are handed to the paragraph
+        let layout = self.layout(width);
+        let loading = self.pending.contains(&self.open_chat);
+        let message_rows = layout.total_rows();
+        let max_scroll = (message_rows + usize::from(loading)).saturating_sub(usize::from(height));
+        let top = self.top_row(max_scroll);
+        let found = self.find.as_ref().map(|find| find.matches(&layout));
+        let marks = Marks {
+            matches: found.as_deref().map_or(&[], Vec::as_slice),
+            current: self.find.as_ref().map(|find| find.current),
         };
+        let (mut lines, skip) = layout.window(top, usize::from(height), marks);
+        drop(found);
+        drop(layout);
+        if loading && top + usize::from(height) > message_rows {
+            lines.push(Line::from(Span::styled("Loading...", theme::get().muted)));
+        }
+        let paragraph = Paragraph::new(lines).wrap(Wrap { trim: false });
 
-        let markdown = from_str(&msg.content);
-        // idk how this works but i like deepseek
-        // Text contains Lines which contains Spans, so loop through the lines and add the spans to the string.
-        for (i, line) in markdown.lines.into_iter().enumerate() {
-            let mut spans = Vec::with_capacity(line.spans.len() + 1);
-            if i == 0 {
-                spans.push(prefix.clone());
-            } else {
-                spans.push(Span::from("".repeat(prefix.width())));
+        let mut block = theme::block().title(
+            self.index_of(self.open_chat)
+                .map_or("", |idx| self.branches[idx].name.as_str()),
+        );
+        if self.selecting {
+            block = block.title_bottom(selection_hints());
+        }
+        if top < max_scroll {
+            let below = max_scroll - top;
+            block = block.title_bottom(
+                Line::from(format!(
+                    "↓ {below} more lines ({}) ",
+                    keymap::get().hint(Action::ScrollBottom)
+                ))
+                .right_aligned(),
+            );
+        }
+        paragraph
+            .block(block)
+            .scroll((u16::try_from(skip).unwrap_or(u16::MAX), 0))
+            .render(area, buf);
+
+        if max_scroll > 0 {
+            let mut state = ScrollbarState::new(max_scroll)
+                .position(top)
+                .viewport_content_length(usize::from(height));
+            Scrollbar::new(ScrollbarOrientation::VerticalRight)
+                .style(theme::get().border)
+                .begin_symbol(None)
+                .end_symbol(None)
+                .render(
+                    area.inner(Margin {
+                        vertical: 1,
+                        horizontal: 0,
+                    }),
+                    buf,
+                    &mut state,
+                );
+        }
+    }
+
+    /// the title over the input, with the keys that matter there
+    fn input_title(&self) -> String {
+        match &self.history_search {
+            Some(search) if search.failing => {
+                format!("failing reverse search: {}", search.query)
+            }
+            Some(search) => format!(
+                "reverse search: {} ({}=older, Enter=take, Esc=cancel)",
+                search.query,
+                keymap::get().hint(Action::HistorySearch)
+            ),
+            None => {
+                let keymap = keymap::get();
+                let hints: Vec<String> = [
+                    (Action::MainMenu, "back"),
+                    (Action::ToggleSidebar, "sidebar"),
+                    (Action::OpenSearch, "search"),
+                    (Action::ToggleTimestamps, "times"),
+                    (Action::EditPrompt, "editor"),
+                    (Action::HistorySearch, "history"),
+                ]
+                .into_iter()
+                .map(|(action, what)| (keymap.hint(action), what))
+                .filter(|(key, _)| !key.is_empty())
+                .map(|(key, what)| format!("{key}={what}"))
+                .collect();
+                format!("Input ({}, S-Enter=newline)", hints.join(", "))
             }
-            spans.extend(line.spans);
-            lines.push(Line::from(spans));
         }
     }
 }
@@ -106,58 +681,17 @@ impl Widget for &ChatView {
     fn render(self, area: Rect, buf: &mut Buffer) {
         // Layout:  [messages box]
         //          [input box]
-        // [TODO]: sidebar component containing past chats. storage -> ??? idk
         // if sidebar is ON, split horizontally:
         // Decide whether we need to carve off a left‐hand pane.
         let chat_area = if self.show_sidebar {
             // 1) split horizontally: left is 30 cols, right is rest
             let h = Layout::default()
                 .direction(Direction::Horizontal)
-                .constraints([Constraint::Length(30), Constraint::Min(0)])
+                .constraints([Constraint::Length(self.sidebar_width), Constraint::Min(0)])
                 .split(area);
 
             // 2) Render sidebar in h[0]
-            let items: Vec<Line> = self
-                .branches
-                .iter()
-                .enumerate()
-                .map(|(i, branch)| {
-                    let is_selected = i == self.selected_branch;
-                    let prefix = if is_selected { "▶" } else { " " };
-
-                    let mut text = format!("{prefix} {branch}", branch = branch.name);
-                    let mut style = Style::default();
-
-                    if is_selected
-                        && matches!(self.sidebar_input_mode, Some(SidebarInputMode::Renaming))
-                    {
-                        text = format!("{prefix} {input}", input = self.sidebar_input_buffer);
-                        style = style.bg(Color::DarkGray);
-                    }
-
-                    Line::from(Span::styled(text, style))
-                })
-                .collect();
-
-            let mut all_lines = items;
-
-            if let Some(SidebarInputMode::NewBranch) = self.sidebar_input_mode {
-                let input_line = Line::from(Span::styled(
-                    format!("▶ {}", self.sidebar_input_buffer),
-                    Style::default().bg(Color::DarkGray),
-                ));
-                all_lines.push(input_line);
-            }
-
-            Paragraph::new(all_lines)
-                .block(Block::default().borders(Borders::ALL).title(
-                    match self.sidebar_input_mode {
-                        Some(SidebarInputMode::NewBranch) => "New Chat (Enter: save, Esc: cancel)",
-                        Some(SidebarInputMode::Renaming) => "Renaming (Enter: save, Esc: cancel)",
-                        None => "Chats (n: new, r: rename)",
-                    },
-                ))
-                .render(h[0], buf);
+            self.render_sidebar(h[0], buf);
 
             // Return the *right* pane as the actual chat area
             h[1]
@@ -166,250 +700,175 @@ impl Widget for &ChatView {
             area
         };
 
-        // Now split chat_area vertically into messages + input
+        // Now split chat_area vertically into messages + input, the input box
+        // grows with the prompt up to INPUT_ROWS lines and scrolls after that
+        let (input_lines, cursor_row) = self
+            .input
+            .lines(chat_area.width.saturating_sub(2), !self.show_sidebar);
+        let input_rows = input_lines.len().min(INPUT_ROWS);
         let chunks = Layout::default()
             .direction(Direction::Vertical)
-            .constraints([Constraint::Min(3), Constraint::Length(3)])
+            .constraints([
+                Constraint::Min(3),
+                Constraint::Length(u16::try_from(input_rows).unwrap_or(1) + 2),
+            ])
             .split(chat_area);
-        // Message area: render each message as one line, distinguishing User/AI
-        let mut lines = Vec::new();
-        if let Some(messages) = &self.messages {
-            iter_messages(messages, &mut lines);
-        }
-
-        Paragraph::new(lines)
-            .block(
-                Block::default()
-                    .borders(Borders::ALL)
-                    .title(self.branches[self.selected_branch].name.as_str()),
-            )
-            .wrap(ratatui::widgets::Wrap { trim: false })
-            .scroll((
-                u16::try_from(
-                    self.messages
-                        .as_ref()
-                        .and_then(|x| x.len().checked_sub(chunks[0].height as usize))
-                        .unwrap_or(self.scroll),
-                )
-                .unwrap(),
-                self.scroll.try_into().unwrap(),
-            ))
-            .render(chunks[0], buf);
+        self.render_messages(chunks[0], buf);
+        self.render_arena(chunks[0], buf);
 
         // Input area: always bottom
-        let input_line = format!("> {}", self.input_buffer);
-        Paragraph::new(vec![Line::from(input_line)])
-            .block(
-                Block::default()
-                    .borders(Borders::ALL)
-                    .title("Input (Esc=back, tab=sidebar)"),
-            )
+        let input_scroll = cursor_row.saturating_sub(input_rows - 1);
+        let input_title = self.input_title();
+        Paragraph::new(input_lines)
+            .block(theme::block().title(input_title))
+            .scroll((u16::try_from(input_scroll).unwrap_or(0), 0))
             .render(chunks[1], buf);
+
+        self.render_find_bar(chunks[1], buf);
+        self.render_command_popup(chunks[1], buf);
+        self.render_raw_view(chunks[0], buf);
+        self.render_code_picker(chunks[0], buf);
+        self.render_confirm(area, buf);
     }
 }
 
 impl CurrentScreen {
-    pub fn handle_chat_view_sidebar(chat: &mut ChatView, key: KeyEvent) -> Result<bool> {
-        if let Some(input_mode) = &mut chat.sidebar_input_mode {
-            // Handle input for renaming or new branch
-            match key.code {
-                KeyCode::Enter => {
-                    let new_name = chat.sidebar_input_buffer.trim();
-                    if !new_name.is_empty() {
-                        match input_mode {
-                            SidebarInputMode::NewBranch => {
-                                // Create new branch with custom name
-                                let new_branch = ChatBranch {
-                                    id: chat.branches.len(),
-                                    name: new_name.to_string(),
-                                    messages: Vec::new(),
-                                };
-                                chat.branches.push(new_branch);
-                                chat.selected_branch = chat.branches.len() - 1;
-                                chat.messages = Some(Vec::new());
-                                ChatBranch::save_all(&chat.storage_path, &chat.branches)?;
-                            }
-                            SidebarInputMode::Renaming => {
-                                // Rename selected branch
-                                if let Some(branch) = chat.branches.get_mut(chat.selected_branch) {
-                                    branch.name = new_name.to_string();
-                                    ChatBranch::save_all(&chat.storage_path, &chat.branches)?;
-                                }
-                            }
-                        }
-                    }
-                    // Reset input mode
-                    chat.sidebar_input_mode = None;
-                    chat.sidebar_input_buffer.clear();
-                }
-                KeyCode::Char(c) => chat.sidebar_input_buffer.push(c),
-                KeyCode::Backspace => {
-                    chat.sidebar_input_buffer.pop();
-                }
-                KeyCode::Esc => {
-                    chat.sidebar_input_mode = None;
-                    chat.sidebar_input_buffer.clear();
-                }
-                _ => {}
-            }
-            Ok(false)
-        } else {
-            // sidebar is active: j/k/Enter/Esc/n
-            match key.code {
-                KeyCode::Char('j') => {
-                    chat.selected_branch = (chat.selected_branch + 1) % chat.branches.len();
-                }
-                KeyCode::Char('k') => {
-                    chat.selected_branch =
-                        (chat.branches.len() + chat.selected_branch - 1) % chat.branches.len();
-                }
-                KeyCode::Enter => {
-                    // switch to that chat branch
-                    chat.messages = Some(
-                        // clone, otherwise we get a self-referential struct
-                        chat.branches[chat.selected_branch].messages.clone(),
-                    );
-                    chat.show_sidebar = false;
-                }
-                KeyCode::Char('n') => {
-                    chat.sidebar_input_mode = Some(SidebarInputMode::NewBranch);
-                    // chat.sidebar_input_buffer.clear();
-                    chat.sidebar_input_buffer = "Default Chat".to_string();
-                }
-                KeyCode::Char('r') => {
-                    // Start renaming (if branches exist)
-                    if !chat.branches.is_empty() {
-                        chat.sidebar_input_mode = Some(SidebarInputMode::Renaming);
-                        chat.sidebar_input_buffer =
-                            chat.branches[chat.selected_branch].name.to_string();
-                    }
-                }
-                KeyCode::Tab | KeyCode::Esc => {
-                    chat.show_sidebar = false;
-                }
-                _ => {}
-            }
-            Ok(true)
-        }
-    }
-
-    pub async fn handle_chat_view(&mut self, key: KeyEvent) -> Result<()> {
+    pub fn handle_chat_view(&mut self, key: KeyEvent) -> Result<()> {
         let CurrentScreen::ChatView(chat) = self else {
             bail!("Not in chat view");
         };
         // chat.drain_ai()?;
-        let storage_path = PathBuf::from("settings.json");
-        let settings = AISettings::load_all(&storage_path).unwrap_or(AISettings {
-            backend: AIBackend::OpenAI,
-            model: "gpt-3.5-turbo".to_string(),
-            api_key: None,
-            temperature: 0.4,
-            max_tokens: 2048,
-        }); // sidebar selection
+        let settings = AISettings::load_or_default();
+        // the y/n dialog comes from the sidebar or from /clear
+        if chat.confirm.is_some() {
+            Self::handle_sidebar_confirm(chat, key)?;
+            return Ok(());
+        }
         if chat.show_sidebar && Self::handle_chat_view_sidebar(chat, key)? {
             return Ok(());
         }
 
         // normal chat view
+        chat.notice = None;
+        if chat.raw_view.is_some() {
+            Self::handle_raw_view(chat, key);
+            return Ok(());
+        }
+        if chat.code_picker.is_some() {
+            return Self::handle_code_picker(chat, key);
+        }
+        if chat.arena.is_some() {
+            return Self::handle_arena(chat, key);
+        }
+        if chat.find.as_ref().is_some_and(|find| find.typing) {
+            Self::handle_find(chat, key);
+            return Ok(());
+        }
+        // the keys for the selected message are all in the keymap
+        if chat.selecting {
+            return Ok(());
+        }
+        if chat.history_search.is_some() {
+            Self::handle_history_search(chat, key);
+            return Ok(());
+        }
+        if Self::handle_command_popup(chat, key) {
+            return Ok(());
+        }
+        // starting a prompt closes the find, the keys for it are in the keymap
+        if chat.input.text().is_empty()
+            && !key
+                .modifiers
+                .intersects(KeyModifiers::CONTROL | KeyModifiers::ALT)
+            && matches!(key.code, KeyCode::Char(_))
+        {
+            chat.close_find();
+        }
+        // the keys that run actions are in the keymap, these are for typing
         match key.code {
-            KeyCode::Tab => {
-                // toggle sidebar
-                chat.show_sidebar = true;
-            }
-            KeyCode::Up => {
-                if chat.scroll >= 5 {
-                    chat.scroll -= 5;
-                }
-            }
-            KeyCode::Down => {
-                chat.scroll += 5;
-            }
-            KeyCode::Char(c) => {
-                if !chat.show_sidebar {
-                    chat.input_buffer.push(c);
-                }
-            }
-            KeyCode::Backspace => {
-                chat.input_buffer.pop();
-            }
-            KeyCode::Enter => {
-                if !chat.show_sidebar {
-                    let user_input = chat.input_buffer.trim().to_string();
-                    if !user_input.is_empty() {
-                        chat.messages
-                            .as_mut()
-                            .context("No messages found")?
-                            .push(Message {
-                                role: Role::User,
-                                content: user_input.to_string(),
-                            });
-
-                        let mut branch_clone = chat.branches[chat.selected_branch].clone();
-
-                        // ---- spawn the real AI call in the background ----
-                        let tx = chat.ai_tx.clone();
-                        let settings_clone = settings.clone();
-                        tokio::spawn(async move {
-                            // call the LLM
-                            let history = Some(branch_clone.messages.as_slice());
-                            let ai_reply = run_ai(history, &user_input, &settings_clone)
-                                .await
-                                .unwrap_or_else(|e| format!("AI error: {}", e));
-
-                            // remove loading, push the real reply
-                            branch_clone.messages.pop(); // pop the "…Loading…"
-                            branch_clone.messages.push(Message {
-                                role: Role::Assistant(Assistant {
-                                    model: settings_clone.model.clone(),
-                                }),
-                                content: ai_reply.clone(),
-                            });
-
-                            // fire‐and‐forget send back to UI
-                            let _ = tx.send(branch_clone);
-                        });
-                        chat.messages
-                            .as_mut()
-                            .context("No messages found")?
-                            .push(Message {
-                                role: Role::Assistant(Assistant {
-                                    model: "Loading...".to_string(),
-                                }),
-                                content: "Loading...".to_string(),
-                            });
-                        let mut branch = chat.branches[chat.selected_branch].clone();
-                        branch.messages = chat
-                            .messages
-                            .as_deref()
-                            .context("No messages found")?
-                            .to_vec();
-                        // idk how to make this behavior tbh
-                        let name_tx = chat.ai_title_tx.clone();
-                        if branch.name == "Default Chat" || branch.name.is_empty() {
-                            tokio::spawn(async move {
-                                let generated_title =
-                                    generate_chat_title(Some(&branch.messages), &settings.clone())
-                                        .await
-                                        .unwrap_or("Default Chat".to_string());
-                                branch.name = generated_title;
-                                let _ = name_tx.send(branch);
-                            });
+            KeyCode::Enter if key.modifiers.is_empty() => {
+                let user_input = chat.input.text().trim().to_string();
+                match commands::parse(&user_input) {
+                    _ if user_input.is_empty() => {}
+                    // shown under the messages, the input stays to fix it
+                    Err(err) => chat.notice = Some(err.to_string()),
+                    Ok(Parsed::Command(command, args)) => {
+                        chat.input.clear();
+                        chat.remember_prompt(&user_input);
+                        if let Err(err) = chat.run_command(command, args, &settings) {
+                            chat.notice = Some(format!("/{}: {err:#}", command.name()));
+                        }
+                    }
+                    Ok(Parsed::Prompt(prompt)) => {
+                        if chat.send_prompt(prompt.to_string(), &settings)? {
+                            chat.input.clear();
+                            chat.remember_prompt(&user_input);
                         }
-
-                        // Clear input
-                        chat.input_buffer.clear();
-                        // Optionally scroll up if too many
-                        // if chat.messages.len() > 100 {
-                        //     chat.messages.pop_front();
-                        // }
                     }
                 }
             }
-            KeyCode::Esc => {
-                *self = CurrentScreen::MainMenu(MainMenu { selected: 0 });
+            KeyCode::Char('p') if key.modifiers.contains(KeyModifiers::CONTROL) => {
+                chat.recall_older();
+            }
+            KeyCode::Char('n') if key.modifiers.contains(KeyModifiers::CONTROL) => {
+                chat.recall_newer();
             }
+            // with nothing typed there is no line to go to the start or end of
+            KeyCode::Home if chat.input.text().is_empty() => chat.scroll_to_top(),
+            KeyCode::End if chat.input.text().is_empty() => chat.scroll_to_bottom(),
+            _ if chat.input.input(key) => {}
+            // up and down only go through the history once the cursor can't move further
+            KeyCode::Up => chat.recall_older(),
+            KeyCode::Down => chat.recall_newer(),
             _ => {}
         }
         Ok(())
     }
+
+    /// Ctrl-R: typing narrows the search, Ctrl-R again goes to older matches
+    fn handle_history_search(chat: &mut ChatView, key: KeyEvent) {
+        let Some(search) = chat.history_search.as_mut() else {
+            return;
+        };
+        let ctrl = key.modifiers.contains(KeyModifiers::CONTROL);
+        match key.code {
+            _ if keymap::get().is(Action::HistorySearch, key) => {
+                // the same query, further back
+                let older = search
+                    .found
+                    .and_then(|found| chat.history.search(&search.query, found));
+                search.failing = older.is_none();
+                search.found = older.or(search.found);
+            }
+            _ if key.code == KeyCode::Esc || (ctrl && key.code == KeyCode::Char('g')) => {
+                chat.input.set_text(&search.draft);
+                chat.history_search = None;
+                return;
+            }
+            KeyCode::Char(c) if !ctrl => {
+                search.query.push(c);
+                // the shown match stays if it still matches
+                let before = search.found.map_or(usize::MAX, |found| found + 1);
+                let found = chat.history.search(&search.query, before);
+                search.failing = found.is_none();
+                search.found = found.or(search.found);
+            }
+            KeyCode::Backspace => {
+                search.query.pop();
+                let found = chat.history.search(&search.query, usize::MAX);
+                search.failing = found.is_none();
+                search.found = found.or(search.found);
+            }
+            // anything else takes the match and goes back to editing
+            _ => {
+                chat.history_search = None;
+                return;
+            }
+        }
+        let text = search
+            .found
+            .and_then(|found| chat.history.get(found))
+            .unwrap_or(&search.draft);
+        chat.input.set_text(text);
+    }
 }
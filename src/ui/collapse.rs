@@ -0,0 +1,85 @@
+// collapsing a message or one of its code blocks to a single line. a message
+// longer than collapse_after in ui.json starts collapsed, and whatever was
+// collapsed or expanded by hand is saved with the chat, though it doesn't count
+// as activity.
+use crate::action::Action;
+use crate::chat_structs::Message;
+use crate::theme;
+use crate::ui::ChatView;
+use crate::ui::chat_view::message_body;
+use crate::ui_config;
+use anyhow::Result;
+use ratatui::text::{Line, Span};
+use unicode_width::{UnicodeWidthChar, UnicodeWidthStr};
+
+/// whether `msg` shows as one line, `lines` is how long its body is drawn
+pub fn is_collapsed(msg: &Message, lines: usize) -> bool {
+    let after = ui_config::get().collapse_after;
+    msg.collapsed.unwrap_or(after > 0 && lines > after)
+}
+
+/// a collapsed message: its first line and how many it hides, `width` wide at most
+pub fn collapsed_line(content: &str, lines: usize, width: usize) -> Line<'static> {
+    let count = format!("  ▸ {lines} lines");
+    let first = content
+        .lines()
+        .map(str::trim)
+        .find(|l| !l.is_empty())
+        .unwrap_or("");
+    Line::from(vec![
+        Span::raw(cut(first, width.saturating_sub(count.width()))),
+        Span::styled(count, theme::get().muted),
+    ])
+}
+
+/// `text` cut to `width` columns, with … when something was left out
+fn cut(text: &str, width: usize) -> String {
+    if text.width() <= width {
+        return text.to_string();
+    }
+    let mut out = String::new();
+    let mut used = 1;
+    for c in text.chars() {
+        used += c.width().unwrap_or(0);
+        if used > width {
+            break;
+        }
+        out.push(c);
+    }
+    out.push('…');
+    out
+}
+
+impl ChatView {
+    /// collapse message `i`, or expand it when it is collapsed
+    pub fn toggle_collapse(&mut self, i: usize) -> Result<()> {
+        let (width, _) = self.view_size();
+        let Some(msg) = self.messages.as_mut().and_then(|m| m.get_mut(i)) else {
+            return Ok(());
+        };
+        let lines = message_body(&msg.content, usize::from(width), 0, &msg.collapsed_code).len();
+        msg.collapsed = Some(!is_collapsed(msg, lines));
+        self.message_changed(i);
+        self.save_open_marks()
+    }
+
+    /// collapse or expand code block `n` of message `i`, counting from 1. without
+    /// `n` it is the only block, or the picker asks which
+    pub fn toggle_code_collapse(&mut self, i: usize, n: Option<usize>) -> Result<()> {
+        let Some(n) = self.pick_code_block(Action::ToggleCodeCollapse, i, n) else {
+            return Ok(());
+        };
+        let Some(msg) = self.messages.as_mut().and_then(|m| m.get_mut(i)) else {
+            return Ok(());
+        };
+        let block = n - 1;
+        if let Some(pos) = msg.collapsed_code.iter().position(|&b| b == block) {
+            msg.collapsed_code.remove(pos);
+        } else {
+            msg.collapsed_code.push(block);
+            msg.collapsed_code.sort_unstable();
+        }
+        self.message_changed(i);
+        self.save_open_marks()
+    }
+}
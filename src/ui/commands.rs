@@ -0,0 +1,305 @@
+use crate::action::Action;
+use crate::ai_backend::AISettings;
+use crate::app::CurrentScreen;
+use crate::chat_branch::ChatBranch;
+use crate::commands::{self, Command};
+use crate::editor::EditTarget;
+use crate::export::export_to_file;
+use crate::profiles::Profiles;
+use crate::theme;
+use crate::ui::ChatView;
+use crate::ui::arena;
+use crate::ui::sidebar::ConfirmAction;
+use anyhow::{Context, Result, bail};
+use crossterm::event::{KeyCode, KeyEvent};
+use ratatui::prelude::*;
+use ratatui::{
+    buffer::Buffer,
+    layout::Rect,
+    text::{Line, Span},
+    widgets::{Clear, Paragraph, Widget},
+};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+impl ChatView {
+    /// run a slash command on the open chat, errors end up as a notice
+    pub fn run_command(
+        &mut self,
+        command: Command,
+        args: &str,
+        settings: &AISettings,
+    ) -> Result<()> {
+        let idx = self.index_of(self.open_chat).context("no chat is open")?;
+        match command {
+            Command::Model if args.is_empty() => {
+                self.notice = Some(format!(
+                    "Model: {} ({:?})",
+                    settings.model, settings.backend
+                ));
+            }
+            Command::Model => {
+                let mut settings = settings.clone();
+                settings.model = args.to_string();
+                AISettings::write_all(&PathBuf::from("settings.json"), &settings)?;
+                self.notice = Some(format!("Switched to {args}"));
+            }
+            Command::Profile => self.run_profile(args, settings)?,
+            Command::System if args.is_empty() => {
+                self.edit_request = Some(EditTarget::SystemPrompt);
+            }
+            Command::System => {
+                self.apply_edit(EditTarget::SystemPrompt, args.to_string())?;
+                self.notice = Some("System prompt set".to_string());
+            }
+            Command::Attach => self.attach(Path::new(args))?,
+            Command::Copy => match args {
+                "" => self.run_message_action(Action::CopyMessage)?,
+                "chat" => self.copy_chat()?,
+                n => {
+                    let n = n.parse().ok().context("usage: /copy [n | chat]")?;
+                    let i = self.target_message().context("no messages yet")?;
+                    self.copy_code_block(i, Some(n));
+                }
+            },
+            Command::Edit => {
+                let count = self.messages.as_ref().map_or(0, Vec::len);
+                let i = if args.is_empty() {
+                    self.message_to_edit().context("no messages yet")?
+                } else {
+                    args.parse::<usize>()
+                        .ok()
+                        .and_then(|n| n.checked_sub(1))
+                        .filter(|&i| i < count)
+                        .with_context(|| {
+                            format!("usage: /edit [n], this chat has {count} messages")
+                        })?
+                };
+                self.edit_request = Some(EditTarget::Message(i));
+            }
+            Command::Clear => self.confirm = Some(ConfirmAction::Clear(self.open_chat)),
+            Command::Export => {
+                export_to_file(&self.full_chat(idx)?, Path::new(args))?;
+                self.notice = Some(format!("Exported to {args}"));
+            }
+            Command::Title if args.is_empty() => {
+                self.generate_title(idx, settings);
+                self.notice = Some("Asking the model for a title...".to_string());
+            }
+            Command::Title => {
+                self.branches[idx].name = args.to_string();
+                self.save_branches()?;
+            }
+            Command::Retry => self.retry(settings)?,
+            Command::Fork => self.fork(idx, args, None)?,
+            Command::Arena if args.is_empty() => self.show_leaderboard()?,
+            Command::Arena => {
+                let (names, prompt) = arena::parse(args)?;
+                self.start_arena(&names, prompt)?;
+            }
+            Command::Help => {
+                self.notice = Some(match Command::from_name(args.trim_start_matches('/')) {
+                    Some(command) => format!("{}  {}", command.usage(), command.help()),
+                    None if args.is_empty() => format!(
+                        "Commands: {} (/help <command> for more)",
+                        Command::ALL.map(|c| format!("/{}", c.name())).join(" ")
+                    ),
+                    None => bail!("no command /{args}"),
+                });
+            }
+        }
+        Ok(())
+    }
+
+    /// `/profile` lists them, `/profile name` switches, `/profile save name` saves
+    fn run_profile(&mut self, args: &str, settings: &AISettings) -> Result<()> {
+        let path = Path::new("profiles.json");
+        let mut profiles = Profiles::load(path)?;
+        if args.is_empty() {
+            let names: Vec<String> = profiles
+                .names()
+                .map(|name| {
+                    if profiles.active.as_deref() == Some(name) {
+                        format!("*{name}")
+                    } else {
+                        name.to_string()
+                    }
+                })
+                .collect();
+            self.notice = Some(if names.is_empty() {
+                "No profiles yet, /profile save <name> keeps the current settings".to_string()
+            } else {
+                format!("Profiles: {}", names.join(", "))
+            });
+            return Ok(());
+        }
+        if let Some(name) = args
+            .strip_prefix("save")
+            .filter(|rest| rest.is_empty() || rest.starts_with(' '))
+            .map(str::trim)
+        {
+            if name.is_empty() {
+                bail!("usage: /profile save <name>");
+            }
+            profiles.profiles.insert(name.to_string(), settings.clone());
+            profiles.active = Some(name.to_string());
+            self.notice = Some(format!("Saved profile {name}"));
+        } else {
+            let switched = profiles.switch(args)?;
+            self.notice = Some(format!(
+                "Switched to {args} ({:?}, {})",
+                switched.backend, switched.model
+            ));
+        }
+        profiles.save(path)
+    }
+
+    /// put a text file into the prompt as a code block, the question goes after it
+    fn attach(&mut self, path: &Path) -> Result<()> {
+        let content = fs::read(path).with_context(|| format!("can't read {}", path.display()))?;
+        let content = String::from_utf8(content)
+            .ok()
+            .with_context(|| format!("{} is not a text file", path.display()))?;
+        let lang = path.extension().and_then(|e| e.to_str()).unwrap_or("");
+        self.input.insert_str(&format!(
+            "{}:\n```{lang}\n{}\n```\n",
+            path.display(),
+            content.trim_end()
+        ));
+        Ok(())
+    }
+
+    /// drop the last reply and send the prompt before it again
+    fn retry(&mut self, settings: &AISettings) -> Result<()> {
+        let last = self
+            .messages
+            .as_ref()
+            .and_then(|m| m.len().checked_sub(1))
+            .context("nothing to retry yet")?;
+        self.regenerate(last, settings)
+    }
+
+    /// copy the chat at `idx` right below it and open the copy, only up to message
+    /// `upto` if that is given
+    pub fn fork(&mut self, idx: usize, name: &str, upto: Option<usize>) -> Result<()> {
+        let source = self.full_chat(idx)?;
+        let name = if name.is_empty() {
+            format!("{} (fork)", source.name)
+        } else {
+            name.to_string()
+        };
+        let mut branch = ChatBranch::new(0, &name);
+        branch.folder = source.folder;
+        branch.tags = source.tags;
+        branch.messages = source.messages;
+        if let Some(upto) = upto {
+            branch.messages.truncate(upto + 1);
+        }
+        let entry = self.store.add_chat(branch, &self.branches, &self.trash)?;
+        self.branches.insert(idx + 1, entry);
+        self.select_branch(idx + 1)?;
+        self.save_branches()?;
+        self.notice = Some(format!("Forked into '{name}'"));
+        Ok(())
+    }
+
+    /// what /clear does once confirmed
+    pub fn clear_chat(&mut self, id: usize) -> Result<()> {
+        if id != self.open_chat {
+            return Ok(());
+        }
+        self.messages.get_or_insert_with(Vec::new).clear();
+        self.render_cache.get_mut().clear();
+        if self.save_open_chat("clearing")? {
+            self.follow = true;
+        }
+        Ok(())
+    }
+
+    /// commands matching the input, the popup shows them
+    pub fn command_completions(&self) -> Vec<Command> {
+        if self.show_sidebar || self.history_search.is_some() {
+            return Vec::new();
+        }
+        commands::completions(self.input.text())
+    }
+
+    /// the popup right above the input box
+    pub fn render_command_popup(&self, input_area: Rect, buf: &mut Buffer) {
+        let completions = self.command_completions();
+        if completions.is_empty() {
+            return;
+        }
+        let selected = self.command_selected % completions.len();
+        // only one left once its arguments are typed, that one isn't picked from
+        let picking = !self.input.text().contains(char::is_whitespace);
+        let lines: Vec<Line> = completions
+            .iter()
+            .enumerate()
+            .map(|(i, command)| {
+                let style = if picking && i == selected {
+                    theme::get().selection
+                } else {
+                    Style::default()
+                };
+                Line::from(vec![
+                    Span::styled(format!("{:<30}", command.usage()), style),
+                    Span::styled(command.help(), theme::get().muted),
+                ])
+            })
+            .collect();
+
+        let height = u16::try_from(lines.len() + 2)
+            .unwrap_or(u16::MAX)
+            .min(input_area.y);
+        let area = Rect {
+            x: input_area.x,
+            y: input_area.y - height,
+            width: input_area.width.min(80),
+            height,
+        };
+        Clear.render(area, buf);
+        Paragraph::new(lines)
+            .block(theme::block().title(if picking {
+                "Commands (Tab=complete, Esc=close)"
+            } else {
+                "Usage"
+            }))
+            .render(area, buf);
+    }
+}
+
+impl CurrentScreen {
+    /// keys for the command popup while a command name is typed, false if the popup
+    /// is closed or the key is not one of them
+    pub fn handle_command_popup(chat: &mut ChatView, key: KeyEvent) -> bool {
+        let completions = chat.command_completions();
+        if completions.is_empty() || chat.input.text().contains(char::is_whitespace) {
+            return false;
+        }
+        let len = completions.len();
+        let selected = chat.command_selected % len;
+        let command = completions[selected];
+        let typed_whole_name = chat.input.text() == format!("/{}", command.name());
+        match key.code {
+            KeyCode::Up => chat.command_selected = (selected + len - 1) % len,
+            KeyCode::Down => chat.command_selected = (selected + 1) % len,
+            KeyCode::Tab => complete(chat, command),
+            KeyCode::Enter if key.modifiers.is_empty() && !typed_whole_name => {
+                complete(chat, command);
+            }
+            KeyCode::Esc => chat.input.clear(),
+            _ => return false,
+        }
+        true
+    }
+}
+
+fn complete(chat: &mut ChatView, command: Command) {
+    let mut text = format!("/{}", command.name());
+    if !command.args().is_empty() {
+        text.push(' ');
+    }
+    chat.input.set_text(&text);
+    chat.command_selected = 0;
+}
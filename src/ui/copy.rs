@@ -0,0 +1,156 @@
+// copying a message, one of its code blocks or the whole chat. a message with more
+// than one code block opens a small picker to choose from, or /copy <n> picks it.
+use crate::action::Action;
+use crate::app::CurrentScreen;
+use crate::clipboard::{self, Copied};
+use crate::export;
+use crate::markdown::{self, CodeBlock};
+use crate::theme;
+use crate::ui::ChatView;
+use crate::ui::sidebar::centered_rect;
+use anyhow::{Context, Result};
+use crossterm::event::{KeyCode, KeyEvent};
+use ratatui::{
+    buffer::Buffer,
+    layout::Rect,
+    text::Line,
+    widgets::{Clear, Paragraph, Widget},
+};
+
+/// choosing a code block of `message` for `action`, copying or collapsing it
+pub struct CodePicker {
+    pub message: usize,
+    pub selected: usize,
+    pub action: Action,
+}
+
+impl ChatView {
+    /// copy `text` and say how that went, `what` is named in the status bar
+    pub fn copy_text(&mut self, text: &str, what: &str) {
+        match clipboard::copy(text) {
+            Ok(Copied::Tool(tool)) => self.notice = Some(format!("Copied {what} with {tool}")),
+            Ok(Copied::Terminal) => {
+                self.notice = Some(format!("Sent {what} to the terminal's clipboard"));
+            }
+            Err(err) => self.notice = Some(format!("Copy failed: {err:#}")),
+        }
+    }
+
+    pub fn message_code_blocks(&self, i: usize) -> Vec<CodeBlock> {
+        self.messages
+            .as_ref()
+            .and_then(|m| m.get(i))
+            .map(|m| markdown::code_blocks(&m.content))
+            .unwrap_or_default()
+    }
+
+    /// which code block of message `i` `action` is for, counting from 1. without
+    /// `n` it is the only one, or the picker opens when there are more
+    pub fn pick_code_block(&mut self, action: Action, i: usize, n: Option<usize>) -> Option<usize> {
+        let blocks = self.message_code_blocks(i).len();
+        match (n, blocks) {
+            (_, 0) => {
+                self.notice = Some("No code blocks in this message".to_string());
+                None
+            }
+            (None, 1) => Some(1),
+            (None, _) => {
+                self.code_picker = Some(CodePicker {
+                    message: i,
+                    selected: 0,
+                    action,
+                });
+                None
+            }
+            (Some(n), _) if (1..=blocks).contains(&n) => Some(n),
+            (Some(n), _) => {
+                self.notice = Some(format!(
+                    "There is no code block {n}, this message has {blocks}"
+                ));
+                None
+            }
+        }
+    }
+
+    /// copy code block `n` of message `i`, counting from 1
+    pub fn copy_code_block(&mut self, i: usize, n: Option<usize>) {
+        let Some(n) = self.pick_code_block(Action::CopyCodeBlock, i, n) else {
+            return;
+        };
+        if let Some(block) = self.message_code_blocks(i).get(n - 1) {
+            self.copy_text(
+                block.code.trim_end_matches('\n'),
+                &format!("code block {n}"),
+            );
+        }
+    }
+
+    /// the open chat as markdown, the way it is exported
+    pub fn copy_chat(&mut self) -> Result<()> {
+        let idx = self.index_of(self.open_chat).context("no chat is open")?;
+        let text = export::to_markdown(&self.full_chat(idx)?);
+        self.copy_text(&text, "the chat");
+        Ok(())
+    }
+
+    pub fn render_code_picker(&self, area: Rect, buf: &mut Buffer) {
+        let Some(picker) = &self.code_picker else {
+            return;
+        };
+        let blocks = self.message_code_blocks(picker.message);
+        let lines: Vec<Line> = blocks
+            .iter()
+            .enumerate()
+            .map(|(n, block)| {
+                let first = block.code.lines().next().unwrap_or("");
+                let line = Line::from(format!("{}  {:<10} {first}", n + 1, block.lang));
+                if n == picker.selected {
+                    line.style(theme::get().selection)
+                } else {
+                    line
+                }
+            })
+            .collect();
+        let height = u16::try_from(lines.len())
+            .unwrap_or(u16::MAX)
+            .saturating_add(2);
+        let popup = centered_rect(area, 60, height);
+        let title = match picker.action {
+            Action::ToggleCodeCollapse => "Collapse which code block? (1-9 or Enter, Esc cancels)",
+            _ => "Copy which code block? (1-9 or Enter, Esc cancels)",
+        };
+        Clear.render(popup, buf);
+        Paragraph::new(lines)
+            .block(theme::block().title(title))
+            .render(popup, buf);
+    }
+}
+
+impl CurrentScreen {
+    pub fn handle_code_picker(chat: &mut ChatView, key: KeyEvent) -> Result<()> {
+        let Some(mut picker) = chat.code_picker.take() else {
+            return Ok(());
+        };
+        let len = chat.message_code_blocks(picker.message).len();
+        let n = match key.code {
+            KeyCode::Char(c @ '1'..='9') => c as usize - '0' as usize,
+            KeyCode::Enter => picker.selected + 1,
+            KeyCode::Char('j') | KeyCode::Down => {
+                picker.selected = (picker.selected + 1).min(len.saturating_sub(1));
+                chat.code_picker = Some(picker);
+                return Ok(());
+            }
+            KeyCode::Char('k') | KeyCode::Up => {
+                picker.selected = picker.selected.saturating_sub(1);
+                chat.code_picker = Some(picker);
+                return Ok(());
+            }
+            _ => return Ok(()),
+        };
+        match picker.action {
+            Action::ToggleCodeCollapse => chat.toggle_code_collapse(picker.message, Some(n))?,
+            _ => chat.copy_code_block(picker.message, Some(n)),
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,307 @@
+// finding text in the open chat. the query is matched against the messages as
+// they are drawn, so what gets highlighted is what was found, markdown and code
+// included. the keymap has the keys for it, / n and N while selecting messages and
+// Alt+/ Alt+N Alt+Shift+N while typing.
+use crate::action::Action;
+use crate::app::CurrentScreen;
+use crate::keymap;
+use crate::theme;
+use crate::ui::ChatView;
+use crate::ui::render_cache::RenderCache;
+use crossterm::event::{KeyCode, KeyEvent, KeyModifiers};
+use ratatui::{
+    buffer::Buffer,
+    layout::Rect,
+    widgets::{Clear, Paragraph, Widget},
+};
+use regex::{Regex, RegexBuilder};
+use std::cell::{Ref, RefCell};
+use std::ops::Range;
+
+#[derive(Default)]
+pub struct Find {
+    pub query: String,
+    // the query box takes the keys
+    pub typing: bool,
+    pub regex: bool,
+    pub case_sensitive: bool,
+    // which match n and N are on
+    pub current: usize,
+    // what the query found the last time the chat was drawn
+    found: RefCell<Found>,
+}
+
+#[derive(Default)]
+struct Found {
+    // the drawing and the query it was found for
+    key: Option<(u64, String, bool, bool)>,
+    matches: Vec<FindMatch>,
+    error: Option<String>,
+}
+
+/// bytes `range` of line `line` of message `message`, as drawn
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FindMatch {
+    pub message: usize,
+    pub line: usize,
+    pub range: Range<usize>,
+}
+
+impl Find {
+    fn pattern(&self) -> Result<Regex, regex::Error> {
+        let pattern = if self.regex {
+            self.query.clone()
+        } else {
+            regex::escape(&self.query)
+        };
+        RegexBuilder::new(&pattern)
+            .case_insensitive(!self.case_sensitive)
+            .build()
+    }
+
+    /// the matches in `layout`, looked for again when the chat was drawn again
+    /// or the query changed
+    pub fn matches(&self, layout: &RenderCache) -> Ref<'_, Vec<FindMatch>> {
+        let key = (
+            layout.generation(),
+            self.query.clone(),
+            self.regex,
+            self.case_sensitive,
+        );
+        if self.found.borrow().key.as_ref() != Some(&key) {
+            let mut found = self.found.borrow_mut();
+            found.key = Some(key);
+            (found.matches, found.error) = match self.pattern() {
+                _ if self.query.is_empty() => (Vec::new(), None),
+                Ok(pattern) => (layout.find(&pattern), None),
+                Err(err) => (Vec::new(), Some(err.to_string())),
+            };
+        }
+        Ref::map(self.found.borrow(), |found| &found.matches)
+    }
+
+    /// "3/17", or why there is nothing, as of the last draw
+    pub fn counter(&self) -> String {
+        let found = self.found.borrow();
+        match (&found.error, found.matches.len()) {
+            // the last line of regex's error says what is wrong
+            (Some(error), _) => format!(
+                "bad regex, {}",
+                error
+                    .lines()
+                    .last()
+                    .unwrap_or("")
+                    .trim_start_matches("error: ")
+            ),
+            (None, 0) => "no matches".to_string(),
+            (None, n) => format!("{}/{n}", self.current.min(n - 1) + 1),
+        }
+    }
+}
+
+impl ChatView {
+    /// open the find box, the last query is kept to change
+    pub fn start_find(&mut self) {
+        let find = self.find.get_or_insert_with(Find::default);
+        find.typing = true;
+        self.show_sidebar = false;
+    }
+
+    pub fn close_find(&mut self) {
+        self.find = None;
+    }
+
+    /// the first match from the top of the view down, while typing
+    fn find_from_view(&mut self) {
+        let (width, _) = self.view_size();
+        let top = self.top_row(self.max_scroll());
+        let Some(find) = &self.find else {
+            return;
+        };
+        let layout = self.layout(width);
+        let matches = find.matches(&layout);
+        let first = matches.partition_point(|m| layout.row_of_line(m.message, m.line) < top);
+        let current = if first < matches.len() { first } else { 0 };
+        let found = !matches.is_empty();
+        drop(matches);
+        drop(layout);
+        if found {
+            self.go_to_match(current);
+        }
+    }
+
+    pub fn find_count(&self) -> usize {
+        let (width, _) = self.view_size();
+        let layout = self.layout(width);
+        self.find
+            .as_ref()
+            .map_or(0, |find| find.matches(&layout).len())
+    }
+
+    /// step to the next match, or the one before with `forward` off, round the ends
+    pub fn step_find(&mut self, forward: bool) {
+        let count = self.find_count();
+        let Some(find) = &self.find else {
+            self.notice = Some(format!(
+                "Nothing to find yet ({} starts)",
+                keymap::get().hint(Action::FindInChat)
+            ));
+            return;
+        };
+        if count == 0 {
+            self.notice = Some(format!("'{}' is not in this chat", find.query));
+            return;
+        }
+        let current = find.current.min(count - 1);
+        let next = if forward {
+            (current + 1) % count
+        } else {
+            (current + count - 1) % count
+        };
+        self.go_to_match(next);
+    }
+
+    /// make match `i` the current one and scroll to it, a third down the view when
+    /// it is out of sight. while selecting its message is selected
+    fn go_to_match(&mut self, i: usize) {
+        let (width, height) = self.view_size();
+        let Some(find) = self.find.as_mut() else {
+            return;
+        };
+        find.current = i;
+        let layout = self.layout(width);
+        let found = self
+            .find
+            .as_ref()
+            .and_then(|find| find.matches(&layout).get(i).cloned());
+        let Some(found) = found else {
+            return;
+        };
+        let row = layout.row_of_line(found.message, found.line);
+        drop(layout);
+        let top = self.top_row(self.max_scroll());
+        if row < top || row >= top + height {
+            self.scroll = row.saturating_sub(height / 3);
+            self.follow = false;
+        }
+        if self.selecting {
+            self.focused_message = Some(found.message);
+        }
+    }
+
+    /// the find box over the input while typing
+    pub fn render_find_bar(&self, area: Rect, buf: &mut Buffer) {
+        let Some(find) = self.find.as_ref().filter(|f| f.typing) else {
+            return;
+        };
+        let on = |on: bool| if on { "on" } else { "off" };
+        let title = format!(
+            "Find {} (Enter=go, Esc=close, Alt+R=regex {}, Alt+C=case {})",
+            find.counter(),
+            on(find.regex),
+            on(find.case_sensitive)
+        );
+        Clear.render(area, buf);
+        Paragraph::new(format!("/{}", find.query))
+            .block(theme::block().title(title))
+            .render(area, buf);
+    }
+}
+
+impl CurrentScreen {
+    pub fn handle_find(chat: &mut ChatView, key: KeyEvent) {
+        let Some(find) = chat.find.as_mut() else {
+            return;
+        };
+        let alt = key.modifiers.contains(KeyModifiers::ALT);
+        match key.code {
+            KeyCode::Esc => chat.close_find(),
+            KeyCode::Enter => {
+                find.typing = false;
+                let current = find.current;
+                let query = find.query.clone();
+                if chat.find_count() == 0 {
+                    if !query.is_empty() {
+                        chat.notice = Some(format!("'{query}' is not in this chat"));
+                    }
+                    chat.close_find();
+                    return;
+                }
+                chat.go_to_match(current);
+            }
+            KeyCode::Char('r') if alt => {
+                find.regex = !find.regex;
+                chat.find_from_view();
+            }
+            KeyCode::Char('c') if alt => {
+                find.case_sensitive = !find.case_sensitive;
+                chat.find_from_view();
+            }
+            KeyCode::Char(c) if !key.modifiers.contains(KeyModifiers::CONTROL) && !alt => {
+                find.query.push(c);
+                chat.find_from_view();
+            }
+            KeyCode::Backspace => {
+                find.query.pop();
+                chat.find_from_view();
+            }
+            _ => {}
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::chat_structs::{Assistant, Message, Role};
+    use crate::ui::render_cache::DrawOptions;
+
+    fn drawn(messages: &[Message]) -> RenderCache {
+        let mut layout = RenderCache::default();
+        layout.update(messages, None, DrawOptions::new(80, true, 0));
+        layout
+    }
+
+    #[test]
+    fn only_the_message_text_is_searched() {
+        let messages = [
+            Message::new(Role::User, "what is a You: cat".into()),
+            Message::new(
+                Role::Assistant(Assistant::new("cat-model")),
+                "a **Cat** is\n\n```\ncat x\n```".into(),
+            ),
+        ];
+        let layout = drawn(&messages);
+        let find = Find {
+            query: "cat".into(),
+            ..Default::default()
+        };
+        let matches = find.matches(&layout);
+        // not the model's name, the details line or the fence
+        let at: Vec<(usize, usize)> = matches.iter().map(|m| (m.message, m.line)).collect();
+        assert_eq!(at, [(0, 1), (1, 1), (1, 4)], "{matches:?}");
+        assert_eq!(find.counter(), "1/3");
+    }
+
+    #[test]
+    fn regex_and_case_are_options() {
+        let messages = [Message::new(Role::User, "Abc abc a.c".into())];
+        let layout = drawn(&messages);
+        let mut find = Find {
+            query: "a.c".into(),
+            ..Default::default()
+        };
+        assert_eq!(find.matches(&layout).len(), 1);
+        find.regex = true;
+        assert_eq!(find.matches(&layout).len(), 3);
+        find.case_sensitive = true;
+        assert_eq!(find.matches(&layout).len(), 2);
+        find.query = "(".into();
+        assert!(find.matches(&layout).is_empty());
+        assert!(
+            find.counter().starts_with("bad regex"),
+            "{}",
+            find.counter()
+        );
+    }
+}
@@ -1,8 +1,9 @@
 use crate::{
-    ai_backend::{AIBackend, AISettings},
     app::{CurrentScreen, Exit},
-    chat_branch::ChatBranch,
-    ui::{ChatView, Config},
+    chat_store::ChatStore,
+    keymap, theme,
+    ui::{ChatView, Config, SearchView},
+    ui_config,
 };
 use anyhow::{Result, bail};
 use crossterm::event::{KeyCode, KeyEvent};
@@ -13,7 +14,8 @@ use ratatui::{
     widgets::{Paragraph, Widget},
 };
 use std::path::PathBuf;
-use tokio::sync::mpsc::unbounded_channel;
+
+pub const MENU_ITEMS: [&str; 4] = ["Chat View", "Search", "Settings", "Exit"];
 
 pub struct MainMenu {
     pub selected: usize,
@@ -22,11 +24,11 @@ pub struct MainMenu {
 impl Widget for &MainMenu {
     fn render(self, area: Rect, buf: &mut Buffer) {
         // let mut buffer = String::new();
-        // 1) Define your menu labels in the same order as `selected` (0,1,2)
-        let menu_labels = ["Chat View", "Settings", "Exit"];
+        // 1) Define your menu labels in the same order as `selected` (0,1,2,3)
+        let menu_labels = MENU_ITEMS;
 
         // 2) Turn each label into a line, prefixing the selected one with ">>"
-        let lines: Vec<Line> = menu_labels
+        let mut lines: Vec<Line> = menu_labels
             .iter()
             .enumerate()
             .map(|(idx, label)| {
@@ -34,6 +36,33 @@ impl Widget for &MainMenu {
                 Line::from(Span::raw(format!("{prefix} {label}")))
             })
             .collect();
+        // a broken keymap.json still starts, with the defaults for what's wrong
+        let problems = &keymap::get().problems;
+        if !problems.is_empty() {
+            lines.push(Line::default());
+            lines.push(Line::from(format!(
+                "keymap.json has {} problem(s), `llm-tui-rs keys` shows the keys in use:",
+                problems.len()
+            )));
+            lines.extend(problems.iter().map(|p| Line::from(format!("  {p}"))));
+        }
+        let problems = &ui_config::get().problems;
+        if !problems.is_empty() {
+            lines.push(Line::default());
+            lines.push(Line::from(
+                "ui.json has problems, the defaults are used for them:",
+            ));
+            lines.extend(problems.iter().map(|p| Line::from(format!("  {p}"))));
+        }
+        let problems = &theme::get().problems;
+        if !problems.is_empty() {
+            lines.push(Line::default());
+            lines.push(Line::styled(
+                "the theme has problems, those styles are left as the base theme has them:",
+                theme::get().error,
+            ));
+            lines.extend(problems.iter().map(|p| Line::from(format!("  {p}"))));
+        }
 
         // 3) Create a Paragraph from those lines, add a border/title, and render it.
         // render_to(
@@ -54,73 +83,34 @@ impl CurrentScreen {
         };
         match key.code {
             KeyCode::Char('j') | KeyCode::Down => {
-                menu.selected = (menu.selected + 1) % 3;
+                menu.selected = (menu.selected + 1) % 4;
             }
             KeyCode::Char('k') | KeyCode::Up => {
-                menu.selected = (3 + menu.selected - 1) % 3;
+                menu.selected = (4 + menu.selected - 1) % 4;
             }
             KeyCode::Enter => {
-                let storage_path = PathBuf::from("chats.json");
-                let mut branches = ChatBranch::load_all(&storage_path)?;
-                if branches.is_empty() {
-                    branches.push(ChatBranch {
-                        id: 0,
-                        name: "Default Chat".to_string(),
-                        messages: Vec::new(),
-                    });
-                }
-                *self = match menu.selected {
-                    0 => {
-                        let (ai_tx, ai_rx) = unbounded_channel::<ChatBranch>();
-                        let (ai_title_tx, ai_title_rx) = unbounded_channel::<ChatBranch>();
-
-                        let mut chat_view = ChatView {
-                            input_buffer: String::new(),
-                            branches,
-                            selected_branch: 0,
-                            show_sidebar: false,
-                            messages: Some(Vec::new()),
-                            storage_path,
-                            sidebar_input_mode: None,
-                            sidebar_input_buffer: String::new(),
-                            scroll: 0,
-                            ai_tx,
-                            ai_rx,
-                            ai_title_tx,
-                            ai_title_rx,
-                        };
-                        // load messages for selected branch
-                        chat_view.messages = Some(
-                            chat_view.branches[chat_view.selected_branch]
-                                .messages
-                                .clone(),
-                        );
-                        CurrentScreen::ChatView(chat_view)
-                    }
-                    1 => {
-                        let storage_path = PathBuf::from("settings.json");
-                        let settings = AISettings::load_all(&storage_path).unwrap_or(AISettings {
-                            backend: AIBackend::OpenAI,
-                            model: "gpt-3.5-turbo".to_string(),
-                            api_key: None,
-                            temperature: 0.7,
-                            max_tokens: 2048,
-                        });
-                        CurrentScreen::Settings(Config {
-                            ai_settings: settings,
-                            available_models: vec![String::new()], // fetch the models somehow,
-                            selected_field: 0,
-                            temp_input: String::new(),
-                            tokens_input: String::new(),
-                        })
-                    }
-                    _ => CurrentScreen::Exit(Exit {
-                        data: "Bye!".to_string(),
-                    }),
-                }
+                let selected = menu.selected;
+                self.open_menu_item(selected)?;
             }
             _ => {}
         }
         Ok(())
     }
+
+    /// go to the screen of the `item`th menu entry
+    pub fn open_menu_item(&mut self, item: usize) -> Result<()> {
+        *self = match item {
+            0 => CurrentScreen::ChatView(Box::new(ChatView::open(ChatStore::open(
+                PathBuf::from("chats"),
+            )?)?)),
+            1 => CurrentScreen::Search(Box::new(SearchView::open(ChatStore::open(
+                PathBuf::from("chats"),
+            )?)?)),
+            2 => CurrentScreen::Settings(Box::new(Config::open())),
+            _ => CurrentScreen::Exit(Exit {
+                data: "Bye!".to_string(),
+            }),
+        };
+        Ok(())
+    }
 }
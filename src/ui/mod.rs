@@ -1,7 +1,24 @@
+pub mod actions;
+pub mod arena;
 pub mod chat_view;
+pub mod collapse;
+pub mod commands;
+pub mod copy;
+pub mod find;
 pub mod main_menu;
+pub mod mouse;
+pub mod palette;
+pub mod render_cache;
+pub mod scroll;
+pub mod search;
+pub mod selection;
 pub mod settings;
+pub mod sidebar;
+pub mod status_bar;
+pub mod text_area;
 
 pub use chat_view::ChatView;
 pub use main_menu::MainMenu;
+pub use palette::Palette;
+pub use search::SearchView;
 pub use settings::Config;
@@ -0,0 +1,175 @@
+// the mouse: the wheel scrolls, clicks pick menu entries, chats and settings
+// fields, and the sidebar's right edge can be dragged. terminals only send it
+// while it is captured, which also stops them from selecting text, so it can be
+// turned off with "mouse": false in ui.json or toggled with toggle_mouse.
+use crate::app::{CurrentScreen, content_area};
+use crate::ui::ChatView;
+use crate::ui::main_menu::MENU_ITEMS;
+use crate::ui::settings::FIELD_COUNT;
+use crate::ui::sidebar::SidebarRow;
+use anyhow::Result;
+use crossterm::event::{
+    DisableMouseCapture, EnableMouseCapture, MouseButton, MouseEvent, MouseEventKind,
+};
+use crossterm::execute;
+use ratatui::layout::{Position, Rect};
+use std::io;
+use std::sync::atomic::{AtomicBool, Ordering};
+
+// rows a wheel click scrolls the messages
+const WHEEL_ROWS: isize = 3;
+// the narrowest the sidebar gets, and what it leaves of the chat
+const MIN_SIDEBAR: u16 = 16;
+const MIN_CHAT: u16 = 20;
+
+static CAPTURE: AtomicBool = AtomicBool::new(false);
+
+pub fn capturing() -> bool {
+    CAPTURE.load(Ordering::Relaxed)
+}
+
+/// catch the mouse or leave it to the terminal, until this is called again
+pub fn set_capture(on: bool) -> io::Result<()> {
+    CAPTURE.store(on, Ordering::Relaxed);
+    resume()
+}
+
+/// tell the terminal again, after it was handed to $EDITOR
+pub fn resume() -> io::Result<()> {
+    if capturing() {
+        execute!(io::stdout(), EnableMouseCapture)
+    } else {
+        execute!(io::stdout(), DisableMouseCapture)
+    }
+}
+
+/// let go of the mouse without forgetting whether it was caught
+pub fn release() -> io::Result<()> {
+    execute!(io::stdout(), DisableMouseCapture)
+}
+
+impl CurrentScreen {
+    /// `area` is the whole terminal
+    pub fn on_mouse(&mut self, event: MouseEvent, area: Rect) -> Result<()> {
+        let area = content_area(area);
+        let row = usize::from(event.row.saturating_sub(area.y));
+        match self {
+            CurrentScreen::MainMenu(menu) => match event.kind {
+                MouseEventKind::ScrollDown => {
+                    menu.selected = (menu.selected + 1).min(MENU_ITEMS.len() - 1);
+                }
+                MouseEventKind::ScrollUp => menu.selected = menu.selected.saturating_sub(1),
+                MouseEventKind::Down(MouseButton::Left)
+                    if event.row >= area.y && row < MENU_ITEMS.len() =>
+                {
+                    menu.selected = row;
+                    self.open_menu_item(row)?;
+                }
+                _ => {}
+            },
+            CurrentScreen::ChatView(chat) => chat.on_mouse(event, area)?,
+            CurrentScreen::Settings(config) => match event.kind {
+                MouseEventKind::ScrollDown => {
+                    config.selected_field = (config.selected_field + 1).min(FIELD_COUNT - 1);
+                }
+                MouseEventKind::ScrollUp => {
+                    config.selected_field = config.selected_field.saturating_sub(1);
+                }
+                // the fields start inside the border
+                MouseEventKind::Down(MouseButton::Left)
+                    if event.row > area.y && row - 1 < FIELD_COUNT =>
+                {
+                    config.selected_field = row - 1;
+                }
+                _ => {}
+            },
+            CurrentScreen::Search(_) | CurrentScreen::Palette(_) | CurrentScreen::Exit(_) => {}
+        }
+        Ok(())
+    }
+}
+
+impl ChatView {
+    pub fn on_mouse(&mut self, event: MouseEvent, area: Rect) -> Result<()> {
+        let list = self.sidebar_list.get();
+        let at = Position::new(event.column, event.row);
+        let on_sidebar = self.show_sidebar && event.column < list.right();
+        // dialogs want their keys, not clicks behind them
+        let busy = self.confirm.is_some()
+            || self.code_picker.is_some()
+            || self.arena.is_some()
+            || self.sidebar_input_mode.is_some();
+
+        match event.kind {
+            MouseEventKind::ScrollUp | MouseEventKind::ScrollDown => {
+                let down = event.kind == MouseEventKind::ScrollDown;
+                if let Some((_, scroll)) = self.raw_view.as_mut() {
+                    *scroll = if down {
+                        scroll.saturating_add(3)
+                    } else {
+                        scroll.saturating_sub(3)
+                    };
+                } else if let Some(arena) = self.arena.as_mut() {
+                    arena.scroll = if down {
+                        arena.scroll.saturating_add(3)
+                    } else {
+                        arena.scroll.saturating_sub(3)
+                    };
+                } else if on_sidebar {
+                    if !busy && !self.show_trash {
+                        self.step_selection(down);
+                    }
+                } else {
+                    self.scroll_by(if down { WHEEL_ROWS } else { -WHEEL_ROWS });
+                }
+            }
+            MouseEventKind::Down(MouseButton::Left)
+                if self.show_sidebar && event.column + 1 == list.right() =>
+            {
+                self.resizing_sidebar = true;
+            }
+            MouseEventKind::Drag(MouseButton::Left) if self.resizing_sidebar => {
+                let widest = area.width.saturating_sub(MIN_CHAT).max(MIN_SIDEBAR);
+                self.sidebar_width = (event.column + 1)
+                    .saturating_sub(area.x)
+                    .clamp(MIN_SIDEBAR, widest);
+            }
+            MouseEventKind::Up(MouseButton::Left) => self.resizing_sidebar = false,
+            MouseEventKind::Down(MouseButton::Left) if !busy && list.contains(at) && on_sidebar => {
+                // the rows start inside the border
+                if let Some(row) = (event.row - list.y).checked_sub(1) {
+                    self.click_sidebar_row(usize::from(row))?;
+                }
+            }
+            _ => {}
+        }
+        Ok(())
+    }
+
+    /// the first click on a row selects it, a click on the selected one opens the
+    /// chat or folds the folder like Enter does
+    fn click_sidebar_row(&mut self, row: usize) -> Result<()> {
+        if self.show_trash {
+            if row < self.trash.len() {
+                self.trash_selected = row;
+            }
+            return Ok(());
+        }
+        let rows = self.sidebar_rows();
+        let Some(clicked) = rows.get(row) else {
+            return Ok(());
+        };
+        let was_selected = self.cursor_row(&rows) == Some(row);
+        self.select_row(clicked);
+        if was_selected {
+            match clicked {
+                SidebarRow::Folder { path, .. } => self.toggle_folder(path.clone()),
+                SidebarRow::Chat { index, .. } => {
+                    self.select_branch(*index)?;
+                    self.show_sidebar = false;
+                }
+            }
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,310 @@
+// ^K (or whatever the keymap says) from any screen: type a few letters of an action, a slash command, a chat
+// or a profile and Enter runs it. the screen it was opened from stays underneath.
+use crate::action::Action;
+use crate::ai_backend::AISettings;
+use crate::app::CurrentScreen;
+use crate::chat_store::ChatStore;
+use crate::commands::Command;
+use crate::keymap;
+use crate::profiles::Profiles;
+use crate::theme;
+use crate::ui::MainMenu;
+use anyhow::Result;
+use crossterm::event::{KeyCode, KeyEvent, KeyModifiers};
+use ratatui::prelude::*;
+use ratatui::{
+    buffer::Buffer,
+    layout::Rect,
+    text::{Line, Span},
+    widgets::{Clear, Paragraph, Widget},
+};
+use std::path::{Path, PathBuf};
+
+const PALETTE_ROWS: usize = 15;
+
+pub enum PaletteItem {
+    Action(Action),
+    Command(Command),
+    Chat { id: usize, name: String },
+    Profile(String),
+}
+
+pub struct Palette {
+    pub previous: CurrentScreen,
+    pub query: String,
+    pub items: Vec<PaletteItem>,
+    // indices into `items` that match the query, best first
+    pub matches: Vec<usize>,
+    pub selected: usize,
+}
+
+impl PaletteItem {
+    fn label(&self) -> String {
+        match self {
+            PaletteItem::Action(action) => action.label().to_string(),
+            PaletteItem::Command(command) => format!("/{}  {}", command.name(), command.help()),
+            PaletteItem::Chat { name, .. } => format!("Chat: {name}"),
+            PaletteItem::Profile(name) => format!("Profile: {name}"),
+        }
+    }
+
+    fn key_hint(&self) -> String {
+        match self {
+            PaletteItem::Action(action) => keymap::get().hint(*action),
+            PaletteItem::Command(command) => command.usage(),
+            PaletteItem::Chat { .. } | PaletteItem::Profile(_) => String::new(),
+        }
+    }
+}
+
+/// how well `query` matches `text` as a subsequence, ignoring case, `None` if it
+/// doesn't. letters right after each other and at the start of words count more
+pub fn fuzzy_score(query: &str, text: &str) -> Option<usize> {
+    let text: Vec<char> = text.to_lowercase().chars().collect();
+    let mut score: usize = 0;
+    let mut next = 0;
+    let mut last: Option<usize> = None;
+    for q in query.to_lowercase().chars().filter(|c| !c.is_whitespace()) {
+        let i = next + text[next..].iter().position(|&c| c == q)?;
+        let word_start = i == 0 || !text[i - 1].is_alphanumeric();
+        score += match last {
+            Some(l) if l + 1 == i => 5,
+            _ if word_start => 3,
+            _ => 1,
+        };
+        // a long way from the last letter counts less
+        if let Some(l) = last {
+            score = score.saturating_sub((i - l - 1).min(3));
+        }
+        last = Some(i);
+        next = i + 1;
+    }
+    Some(score)
+}
+
+impl Palette {
+    fn new(previous: CurrentScreen) -> Self {
+        let mut items: Vec<PaletteItem> = Action::ALL
+            .into_iter()
+            .filter(|a| a.in_palette())
+            .map(PaletteItem::Action)
+            .collect();
+        items.extend(Command::ALL.map(PaletteItem::Command));
+        // the chat view has the index at hand, elsewhere it is read once
+        let branches = match &previous {
+            CurrentScreen::ChatView(chat) => chat.branches.clone(),
+            _ => ChatStore::open(PathBuf::from("chats"))
+                .and_then(|mut store| store.load_index())
+                .unwrap_or_default(),
+        };
+        items.extend(
+            branches
+                .into_iter()
+                .filter(|b| !b.archived)
+                .map(|b| PaletteItem::Chat {
+                    id: b.id,
+                    name: b.name,
+                }),
+        );
+        let profiles = Profiles::load(Path::new("profiles.json")).unwrap_or_default();
+        items.extend(
+            profiles
+                .names()
+                .map(|n| PaletteItem::Profile(n.to_string())),
+        );
+
+        let mut palette = Palette {
+            previous,
+            query: String::new(),
+            items,
+            matches: Vec::new(),
+            selected: 0,
+        };
+        palette.update_matches();
+        palette
+    }
+
+    pub fn update_matches(&mut self) {
+        let mut scored: Vec<(usize, usize)> = self
+            .items
+            .iter()
+            .enumerate()
+            .filter_map(|(i, item)| Some((fuzzy_score(&self.query, &item.label())?, i)))
+            .collect();
+        // stable, so equal scores keep the order above
+        scored.sort_by_key(|&(score, _)| std::cmp::Reverse(score));
+        self.matches = scored.into_iter().map(|(_, i)| i).collect();
+        self.selected = 0;
+    }
+}
+
+impl Widget for &Palette {
+    fn render(self, area: Rect, buf: &mut Buffer) {
+        self.previous.render_screen(area, buf);
+
+        let rows = self.matches.len().clamp(1, PALETTE_ROWS);
+        let width = area.width.min(80);
+        let height = u16::try_from(rows + 3).unwrap_or(u16::MAX).min(area.height);
+        let popup = Rect {
+            x: area.x + (area.width - width) / 2,
+            y: area.y + area.height.saturating_sub(height) / 3,
+            width,
+            height,
+        };
+        Clear.render(popup, buf);
+
+        let mut lines = vec![Line::from(format!("> {}", self.query))];
+        // keep the selection in view
+        let first = self.selected.saturating_sub(rows - 1);
+        let inner = usize::from(width.saturating_sub(2));
+        for (row, &i) in self.matches.iter().enumerate().skip(first).take(rows) {
+            let item = &self.items[i];
+            let hint = item.key_hint();
+            let label: String = item
+                .label()
+                .chars()
+                .take(inner.saturating_sub(hint.chars().count() + 1))
+                .collect();
+            let gap = inner.saturating_sub(label.chars().count() + hint.chars().count());
+            let style = if row == self.selected {
+                theme::get().selection
+            } else {
+                Style::default()
+            };
+            lines.push(Line::from(vec![
+                Span::styled(format!("{label}{}", " ".repeat(gap)), style),
+                Span::styled(hint, style.patch(theme::get().muted)),
+            ]));
+        }
+        if self.matches.is_empty() {
+            lines.push(Line::from(Span::styled(
+                "nothing matches",
+                theme::get().muted,
+            )));
+        }
+        Paragraph::new(lines)
+            .block(theme::block().title("Command palette (Enter=run, Esc=close)"))
+            .render(popup, buf);
+    }
+}
+
+impl CurrentScreen {
+    /// put the palette over whatever screen is open
+    pub fn open_palette(&mut self) {
+        let previous = std::mem::replace(self, CurrentScreen::MainMenu(MainMenu { selected: 0 }));
+        *self = CurrentScreen::Palette(Box::new(Palette::new(previous)));
+    }
+
+    pub fn handle_palette(&mut self, key: KeyEvent) {
+        let CurrentScreen::Palette(palette) = self else {
+            return;
+        };
+        let ctrl = key.modifiers.contains(KeyModifiers::CONTROL);
+        let len = palette.matches.len().max(1);
+        match key.code {
+            _ if key.code == KeyCode::Esc || keymap::get().is(Action::Palette, key) => {
+                self.close_palette();
+            }
+            KeyCode::Enter => {
+                let picked = palette.matches.get(palette.selected).copied();
+                let CurrentScreen::Palette(palette) = self.close_palette() else {
+                    return;
+                };
+                if let Some(item) = picked.and_then(|i| palette.items.into_iter().nth(i))
+                    && let Err(err) = self.run_palette_item(item)
+                    && let Some(chat) = self.chat_view_mut()
+                {
+                    chat.notice = Some(format!("{err:#}"));
+                }
+            }
+            KeyCode::Down => palette.selected = (palette.selected + 1) % len,
+            KeyCode::Char('n') if ctrl => palette.selected = (palette.selected + 1) % len,
+            KeyCode::Up => palette.selected = (palette.selected + len - 1) % len,
+            KeyCode::Char('p') if ctrl => palette.selected = (palette.selected + len - 1) % len,
+            KeyCode::Backspace => {
+                palette.query.pop();
+                palette.update_matches();
+            }
+            KeyCode::Char(c) if !ctrl => {
+                palette.query.push(c);
+                palette.update_matches();
+            }
+            _ => {}
+        }
+    }
+
+    /// go back to the screen underneath, returns the palette
+    fn close_palette(&mut self) -> CurrentScreen {
+        let CurrentScreen::Palette(palette) = self else {
+            return CurrentScreen::MainMenu(MainMenu { selected: 0 });
+        };
+        let previous = std::mem::replace(
+            &mut palette.previous,
+            CurrentScreen::MainMenu(MainMenu { selected: 0 }),
+        );
+        std::mem::replace(self, previous)
+    }
+
+    fn run_palette_item(&mut self, item: PaletteItem) -> Result<()> {
+        match item {
+            PaletteItem::Action(action) => self.run_action(action)?,
+            PaletteItem::Command(command) => {
+                let chat = self.chat_view()?;
+                if command.args().starts_with('<') {
+                    // needs an argument, type it in the input
+                    chat.input.set_text(&format!("/{} ", command.name()));
+                } else if let Err(err) =
+                    chat.run_command(command, "", &AISettings::load_or_default())
+                {
+                    chat.notice = Some(format!("/{}: {err:#}", command.name()));
+                }
+            }
+            PaletteItem::Chat { id, .. } => {
+                let chat = self.chat_view()?;
+                chat.jump_to(id, None, &[])?;
+                chat.show_sidebar = false;
+            }
+            PaletteItem::Profile(name) => {
+                let path = Path::new("profiles.json");
+                let mut profiles = Profiles::load(path)?;
+                let switched = profiles.switch(&name)?;
+                profiles.save(path)?;
+                // the settings screen writes its copy back on every key
+                if let CurrentScreen::Settings(config) = self {
+                    config.ai_settings = switched;
+                }
+                if let Some(notice) = self.notice_mut() {
+                    *notice = Some(format!("Switched to {name}"));
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn letters_have_to_come_in_order() {
+        assert!(fuzzy_score("ns", "New chat").is_none());
+        assert!(fuzzy_score("xyz", "New chat").is_none());
+        assert_eq!(fuzzy_score("", "anything"), Some(0));
+        assert_eq!(
+            fuzzy_score("N C", "new chat"),
+            fuzzy_score("nc", "New Chat")
+        );
+    }
+
+    #[test]
+    fn runs_and_word_starts_score_higher() {
+        let score = |query| fuzzy_score(query, "Toggle sidebar").unwrap();
+        // t, then o right after it
+        assert_eq!(score("to"), 3 + 5);
+        // t, then s at the start of a word six letters on
+        assert_eq!(score("ts"), 3 + 3 - 3);
+        assert!(score("side") > score("sdbr"));
+        assert!(fuzzy_score("ch", "new chat") > fuzzy_score("ch", "search"));
+    }
+}
@@ -0,0 +1,422 @@
+// the lines of every message as they were drawn, so a frame only parses the
+// markdown of messages that changed. whatever changes a message says so with
+// `touch` (or `forget_from` when messages go away), new messages at the end are
+// drawn on their own. a new width, theme or anything else in DrawOptions redraws
+// them all.
+use crate::chat_structs::Message;
+use crate::theme::{self, ColorDepth, Theme};
+use crate::ui::chat_view::message_lines;
+use crate::ui::find::FindMatch;
+use ratatui::{
+    style::{Modifier, Style},
+    text::{Line, Span},
+    widgets::{Paragraph, Wrap},
+};
+use regex::Regex;
+use std::ops::Range;
+use std::ptr;
+
+/// what messages are drawn with besides themselves
+#[derive(Debug, Clone, Copy)]
+pub struct DrawOptions {
+    pub width: u16,
+    pub show_timestamps: bool,
+    pub code_scroll: usize,
+    pub theme: &'static Theme,
+    pub depth: ColorDepth,
+}
+
+impl DrawOptions {
+    pub fn new(width: u16, show_timestamps: bool, code_scroll: usize) -> Self {
+        let theme = theme::get();
+        DrawOptions {
+            width,
+            show_timestamps,
+            code_scroll,
+            theme,
+            depth: theme.depth,
+        }
+    }
+}
+
+impl PartialEq for DrawOptions {
+    fn eq(&self, other: &Self) -> bool {
+        self.width == other.width
+            && self.show_timestamps == other.show_timestamps
+            && self.code_scroll == other.code_scroll
+            && ptr::eq(self.theme, other.theme)
+            && self.depth == other.depth
+    }
+}
+
+struct Entry {
+    focused: bool,
+    // the message changed since it was drawn
+    dirty: bool,
+    lines: Vec<Line<'static>>,
+    // how many rows the lines wrap to
+    rows: usize,
+}
+
+#[derive(Default)]
+pub struct RenderCache {
+    entries: Vec<Entry>,
+    // what the entries were drawn with
+    options: Option<DrawOptions>,
+    // the row each entry starts on, one more at the end for the total
+    starts: Vec<usize>,
+    // goes up whenever an entry is drawn again, what was found in the lines is
+    // stale then
+    generation: u64,
+}
+
+/// the matches to highlight in the window, sorted, and which one is the current
+#[derive(Clone, Copy, Default)]
+pub struct Marks<'a> {
+    pub matches: &'a [FindMatch],
+    pub current: Option<usize>,
+}
+
+impl Entry {
+    fn new(message: &Message, focused: bool, options: DrawOptions) -> Self {
+        let lines: Vec<Line<'static>> = message_lines(
+            message,
+            focused,
+            options.show_timestamps,
+            usize::from(options.width),
+            options.code_scroll,
+        )
+        .into_iter()
+        .map(into_owned)
+        .collect();
+        let rows = Paragraph::new(lines.clone())
+            .wrap(Wrap { trim: false })
+            .line_count(options.width);
+        Entry {
+            focused,
+            dirty: false,
+            lines,
+            rows,
+        }
+    }
+}
+
+/// the bytes of drawn line `l` that come from the message itself, leaving out the
+/// name in front, the details line, code fences, line numbers and the count of a
+/// collapsed message
+fn content_range(entry: &Entry, show_timestamps: bool, l: usize) -> Option<Range<usize>> {
+    let theme = theme::get();
+    if l == 0 && show_timestamps {
+        return None;
+    }
+    let line = entry.lines.get(l)?;
+    // the name, or the indent under it
+    let mut start = line.spans.first().map_or(0, |s| s.content.len());
+    let mut rest = line.spans.get(1..).unwrap_or_default();
+    if let Some((gutter, after)) = rest.split_first()
+        && gutter.style == theme.line_number
+        && gutter.content.ends_with(" │ ")
+    {
+        start += gutter.content.len();
+        rest = after;
+    }
+    if rest.first().is_some_and(|s| s.content.starts_with("```")) {
+        return None;
+    }
+    if let Some((count, before)) = rest.split_last()
+        && count.style == theme.muted
+        && count.content.starts_with("  ▸ ")
+    {
+        rest = before;
+    }
+    Some(start..start + rest.iter().map(|s| s.content.len()).sum::<usize>())
+}
+
+// tui-markdown borrows from the message, the cache has to outlive it
+fn into_owned(line: Line<'_>) -> Line<'static> {
+    Line {
+        spans: line
+            .spans
+            .into_iter()
+            .map(|span| Span::styled(span.content.into_owned(), span.style))
+            .collect(),
+        style: line.style,
+        alignment: line.alignment,
+    }
+}
+
+impl RenderCache {
+    /// bring the entries up to date with `messages`, `focused` is highlighted.
+    /// only touched entries, the focus moving and new messages are drawn
+    pub fn update(&mut self, messages: &[Message], focused: Option<usize>, options: DrawOptions) {
+        if self.options != Some(options) {
+            self.options = Some(options);
+            self.entries.clear();
+        }
+        let old = self.starts.len().saturating_sub(1);
+        // the first entry whose rows may be different
+        let mut changed = self.entries.len().min(messages.len());
+        self.entries.truncate(messages.len());
+        for (i, entry) in self.entries.iter_mut().enumerate() {
+            let focused = focused == Some(i);
+            if entry.dirty || entry.focused != focused {
+                *entry = Entry::new(&messages[i], focused, options);
+                changed = changed.min(i);
+            }
+        }
+        for (i, message) in messages.iter().enumerate().skip(self.entries.len()) {
+            self.entries
+                .push(Entry::new(message, focused == Some(i), options));
+        }
+        if changed == self.entries.len() && old == self.entries.len() {
+            return;
+        }
+        self.generation += 1;
+        self.starts.truncate(changed.min(old) + 1);
+        if self.starts.is_empty() {
+            self.starts.push(0);
+        }
+        for entry in &self.entries[self.starts.len() - 1..] {
+            self.starts
+                .push(self.starts[self.starts.len() - 1] + entry.rows);
+        }
+    }
+
+    /// message `m` changed and is drawn again next time
+    pub fn touch(&mut self, m: usize) {
+        if let Some(entry) = self.entries.get_mut(m) {
+            entry.dirty = true;
+        }
+    }
+
+    /// the messages from `m` on were removed or replaced
+    pub fn forget_from(&mut self, m: usize) {
+        self.entries.truncate(m);
+    }
+
+    pub fn clear(&mut self) {
+        self.entries.clear();
+    }
+
+    pub fn generation(&self) -> u64 {
+        self.generation
+    }
+
+    /// where `pattern` matches the drawn text of the messages, in order
+    pub fn find(&self, pattern: &Regex) -> Vec<FindMatch> {
+        let mut matches = Vec::new();
+        let show_timestamps = self.options.is_some_and(|o| o.show_timestamps);
+        for (message, entry) in self.entries.iter().enumerate() {
+            for (line, drawn) in entry.lines.iter().enumerate() {
+                let Some(content) = content_range(entry, show_timestamps, line) else {
+                    continue;
+                };
+                let text: String = drawn.spans.iter().map(|s| s.content.as_ref()).collect();
+                let found = pattern.find_iter(&text[content.clone()]);
+                matches.extend(found.filter(|m| !m.is_empty()).map(|m| FindMatch {
+                    message,
+                    line,
+                    range: m.start() + content.start..m.end() + content.start,
+                }));
+            }
+        }
+        matches
+    }
+
+    /// the row line `l` of message `m` starts on
+    pub fn row_of_line(&self, m: usize, l: usize) -> usize {
+        let Some(entry) = self.entries.get(m) else {
+            return self.total_rows();
+        };
+        let width = self.options.map_or(0, |o| o.width);
+        let above = Paragraph::new(entry.lines[..l.min(entry.lines.len())].to_vec())
+            .wrap(Wrap { trim: false })
+            .line_count(width);
+        self.row_of(m) + above
+    }
+
+    /// the row message `m` starts on
+    pub fn row_of(&self, m: usize) -> usize {
+        self.starts
+            .get(m.min(self.entries.len()))
+            .copied()
+            .unwrap_or_default()
+    }
+
+    pub fn total_rows(&self) -> usize {
+        self.row_of(self.entries.len())
+    }
+
+    /// the lines of the messages that show from row `top` on for `height` rows
+    /// with `marks` highlighted, and how many rows of them are above `top`
+    pub fn window(&self, top: usize, height: usize, marks: Marks) -> (Vec<Line<'static>>, usize) {
+        // the first message that ends below `top`
+        let ends = self.starts.get(1..).unwrap_or_default();
+        let m = ends.partition_point(|&end| end <= top);
+        let Some(first) = self.entries.get(m) else {
+            return (Vec::new(), 0);
+        };
+        let skip = top - self.starts[m];
+        let mut lines = marked(m, &first.lines, marks);
+        let mut rows = first.rows;
+        for (m, entry) in self.entries.iter().enumerate().skip(m + 1) {
+            if rows >= skip + height {
+                break;
+            }
+            lines.extend(marked(m, &entry.lines, marks));
+            rows += entry.rows;
+        }
+        (lines, skip)
+    }
+}
+
+/// the lines of message `m` with its matches highlighted
+fn marked(m: usize, lines: &[Line<'static>], marks: Marks) -> Vec<Line<'static>> {
+    let start = marks.matches.partition_point(|x| x.message < m);
+    let end = marks.matches.partition_point(|x| x.message <= m);
+    if start == end {
+        return lines.to_vec();
+    }
+    let theme = theme::get();
+    let current = theme
+        .search_match
+        .patch(theme.selection)
+        .add_modifier(Modifier::UNDERLINED);
+    lines
+        .iter()
+        .enumerate()
+        .map(|(l, line)| {
+            let ranges: Vec<(Range<usize>, Style)> = (start..end)
+                .filter(|&i| marks.matches[i].line == l)
+                .map(|i| {
+                    let style = if marks.current == Some(i) {
+                        current
+                    } else {
+                        theme.search_match
+                    };
+                    (marks.matches[i].range.clone(), style)
+                })
+                .collect();
+            if ranges.is_empty() {
+                line.clone()
+            } else {
+                mark_line(line, &ranges)
+            }
+        })
+        .collect()
+}
+
+/// `line` with the bytes in `ranges` restyled, the ranges are in order
+fn mark_line(line: &Line<'static>, ranges: &[(Range<usize>, Style)]) -> Line<'static> {
+    let mut spans = Vec::new();
+    let mut pos = 0;
+    for span in &line.spans {
+        let text = span.content.as_ref();
+        let end = pos + text.len();
+        // how far into the span is already pushed
+        let mut cut = pos;
+        for (range, style) in ranges {
+            let from = range.start.clamp(pos, end);
+            let to = range.end.clamp(pos, end);
+            if from >= to {
+                continue;
+            }
+            if from > cut {
+                spans.push(Span::styled(
+                    text[cut - pos..from - pos].to_string(),
+                    span.style,
+                ));
+            }
+            spans.push(Span::styled(
+                text[from - pos..to - pos].to_string(),
+                span.style.patch(*style),
+            ));
+            cut = to;
+        }
+        if cut < end {
+            spans.push(Span::styled(text[cut - pos..].to_string(), span.style));
+        }
+        pos = end;
+    }
+    Line {
+        spans,
+        style: line.style,
+        alignment: line.alignment,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::chat_structs::Role;
+
+    fn text(lines: &[Line<'static>]) -> Vec<String> {
+        lines.iter().map(ToString::to_string).collect()
+    }
+
+    #[test]
+    fn only_touched_messages_are_drawn_again() {
+        let options = DrawOptions::new(40, false, 0);
+        let mut messages: Vec<Message> = ["one", "two", "three"]
+            .into_iter()
+            .map(|m| Message::new(Role::User, m.into()))
+            .collect();
+        let mut cache = RenderCache::default();
+        cache.update(&messages, None, options);
+        let generation = cache.generation();
+
+        messages[1].content = "two\n\nand more".into();
+        cache.update(&messages, None, options);
+        assert_eq!(cache.generation(), generation);
+        assert_eq!(cache.total_rows(), 3);
+
+        cache.touch(1);
+        cache.update(&messages, None, options);
+        assert!(cache.generation() > generation);
+        assert_eq!(
+            (cache.row_of(1), cache.row_of(2), cache.total_rows()),
+            (1, 4, 5)
+        );
+
+        messages.truncate(1);
+        messages.push(Message::new(Role::User, "new".into()));
+        cache.forget_from(1);
+        cache.update(&messages, None, options);
+        let (lines, skip) = cache.window(0, 10, Marks::default());
+        assert_eq!(skip, 0);
+        assert_eq!(text(&lines), ["You: one", "You: new"]);
+    }
+
+    #[test]
+    fn the_window_starts_inside_a_message() {
+        let messages: Vec<Message> = ["a\n\nb\n\nc", "d", "e\n\nf"]
+            .into_iter()
+            .map(|m| Message::new(Role::User, m.into()))
+            .collect();
+        let mut cache = RenderCache::default();
+        cache.update(&messages, None, DrawOptions::new(40, false, 0));
+        assert_eq!(cache.total_rows(), 9);
+        assert_eq!(cache.row_of(2), 6);
+        assert_eq!(cache.row_of(10), 9);
+        assert_eq!(cache.row_of_line(2, 2), 8);
+
+        let (lines, skip) = cache.window(4, 2, Marks::default());
+        assert_eq!(skip, 4);
+        assert_eq!(text(&lines).len(), 6);
+        let (lines, skip) = cache.window(5, 1, Marks::default());
+        assert_eq!((text(&lines), skip), (vec!["You: d".to_string()], 0));
+        assert!(cache.window(9, 5, Marks::default()).0.is_empty());
+    }
+
+    #[test]
+    fn new_options_redraw_everything() {
+        let messages = [Message::new(Role::User, "a long line ".repeat(10))];
+        let mut cache = RenderCache::default();
+        cache.update(&messages, None, DrawOptions::new(200, false, 0));
+        assert_eq!(cache.total_rows(), 1);
+        cache.update(&messages, None, DrawOptions::new(40, false, 0));
+        assert_eq!(cache.total_rows(), 4);
+        cache.update(&messages, None, DrawOptions::new(40, true, 0));
+        assert_eq!(cache.total_rows(), 5);
+    }
+}
@@ -0,0 +1,103 @@
+// scrolling the messages by rows as they are drawn, wrapping included. at the
+// bottom the view follows new messages, scrolling up stops that until the
+// bottom is reached again.
+use crate::ui::ChatView;
+use crate::ui::render_cache::{DrawOptions, RenderCache};
+use regex::RegexBuilder;
+use std::cell::Ref;
+
+impl ChatView {
+    /// the inner size of the messages box at the last draw
+    pub fn view_size(&self) -> (u16, usize) {
+        let (width, height) = self.viewport.get();
+        // nothing was drawn yet
+        (if width == 0 { 80 } else { width }, usize::from(height))
+    }
+
+    /// the messages drawn at `width`, only the ones that changed are drawn again
+    pub fn layout(&self, width: u16) -> Ref<'_, RenderCache> {
+        let options = DrawOptions::new(width, self.show_timestamps, self.code_scroll);
+        self.render_cache.borrow_mut().update(
+            self.messages.as_deref().unwrap_or_default(),
+            self.focused_message,
+            options,
+        );
+        self.render_cache.borrow()
+    }
+
+    /// message `i` changed in place and has to be drawn again
+    pub fn message_changed(&mut self, i: usize) {
+        self.render_cache.get_mut().touch(i);
+    }
+
+    /// the row message `m` starts on
+    pub fn message_row(&self, m: usize) -> usize {
+        let (width, _) = self.view_size();
+        self.layout(width).row_of(m)
+    }
+
+    /// the row to put at the top to show message `m`. when its first line with one
+    /// of `terms` would be below the view that line goes a third of the way down
+    pub fn hit_row(&self, m: usize, terms: &[String]) -> usize {
+        let (width, height) = self.view_size();
+        let layout = self.layout(width);
+        let start = layout.row_of(m);
+        let alternatives: Vec<String> = terms.iter().map(|t| regex::escape(t)).collect();
+        let Ok(pattern) = RegexBuilder::new(&alternatives.join("|"))
+            .case_insensitive(true)
+            .build()
+        else {
+            return start;
+        };
+        let hit = if terms.is_empty() {
+            None
+        } else {
+            layout.find(&pattern).into_iter().find(|f| f.message == m)
+        };
+        match hit.map(|hit| layout.row_of_line(m, hit.line)) {
+            Some(row) if row >= start + height => row.saturating_sub(height / 3),
+            _ => start,
+        }
+    }
+
+    /// how far down the view can go, the last row at the bottom
+    pub fn max_scroll(&self) -> usize {
+        let (width, height) = self.view_size();
+        // and the loading line
+        let rows =
+            self.layout(width).total_rows() + usize::from(self.pending.contains(&self.open_chat));
+        rows.saturating_sub(height)
+    }
+
+    /// the row at the top of the view
+    pub fn top_row(&self, max_scroll: usize) -> usize {
+        if self.follow {
+            max_scroll
+        } else {
+            self.scroll.min(max_scroll)
+        }
+    }
+
+    /// scroll by `rows`, up when negative. reaching the bottom follows again
+    pub fn scroll_by(&mut self, rows: isize) {
+        let max = self.max_scroll();
+        let top = self.top_row(max).saturating_add_signed(rows).min(max);
+        self.scroll = top;
+        self.follow = top == max;
+    }
+
+    /// a screen of messages, with a couple of rows kept for context
+    pub fn page(&self) -> isize {
+        let (_, height) = self.view_size();
+        isize::try_from(height.saturating_sub(2).max(1)).unwrap_or(1)
+    }
+
+    pub fn scroll_to_top(&mut self) {
+        self.scroll = 0;
+        self.follow = false;
+    }
+
+    pub fn scroll_to_bottom(&mut self) {
+        self.follow = true;
+    }
+}
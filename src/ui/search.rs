@@ -0,0 +1,206 @@
+use crate::app::CurrentScreen;
+use crate::chat_branch::ChatBranch;
+use crate::chat_store::ChatStore;
+use crate::chat_structs::{Message, Role};
+use crate::search::{Query, SearchHit, SearchIndex, candidates, find_matches, search};
+use crate::theme;
+use crate::ui::{ChatView, MainMenu};
+use anyhow::{Result, bail};
+use chrono::Local;
+use crossterm::event::{KeyCode, KeyEvent};
+use ratatui::prelude::*;
+use ratatui::{
+    buffer::Buffer,
+    layout::Rect,
+    text::{Line, Span},
+    widgets::{Paragraph, Widget},
+};
+use std::cell::Cell;
+use std::collections::HashMap;
+
+// how much context to show around the first match
+const SNIPPET_BEFORE: usize = 30;
+const SNIPPET_AFTER: usize = 90;
+
+pub struct SearchView {
+    pub query: String,
+    pub branches: Vec<ChatBranch>,
+    // messages of the chats that had results so far
+    pub chats: HashMap<usize, Vec<Message>>,
+    pub store: ChatStore,
+    pub index: SearchIndex,
+    pub results: Vec<SearchHit>,
+    pub selected: usize,
+    // the screen at the last draw, a chat opened from here is laid out to it
+    pub area: Cell<Rect>,
+}
+
+impl SearchView {
+    pub fn open(mut store: ChatStore) -> Result<Self> {
+        let branches = store.load_index()?;
+        let mut index = SearchIndex::load(&store);
+        if index.refresh(&store, &branches)? {
+            index.save(&store)?;
+        }
+        Ok(SearchView {
+            query: String::new(),
+            branches,
+            chats: HashMap::new(),
+            store,
+            index,
+            results: Vec::new(),
+            selected: 0,
+            area: Cell::new(Rect::default()),
+        })
+    }
+
+    fn update_results(&mut self) -> Result<()> {
+        let query = Query::parse(&self.query);
+        for (chat, _) in candidates(&self.index, &self.branches, &query) {
+            if !self.chats.contains_key(&chat) {
+                self.chats.insert(chat, self.store.load_messages(chat)?);
+            }
+        }
+        self.results = search(&self.index, &self.branches, &self.chats, &query);
+        self.selected = 0;
+        Ok(())
+    }
+
+    fn hit_lines(&self, hit: &SearchHit, terms: &[String], selected: bool) -> Vec<Line<'_>> {
+        let Some(branch) = self.branches.iter().find(|b| b.id == hit.chat) else {
+            return Vec::new();
+        };
+        let mut header = vec![Span::styled(
+            format!("{} {}", if selected { "▶" } else { " " }, branch.name),
+            Style::default().add_modifier(Modifier::BOLD),
+        )];
+        let message = hit
+            .message
+            .and_then(|i| self.chats.get(&hit.chat).and_then(|m| m.get(i)));
+        let Some(message) = message else {
+            header.push(Span::styled("  (title)", theme::get().muted));
+            return vec![Line::from(header)];
+        };
+
+        let (who, style) = match &message.role {
+            Role::User => ("You".to_string(), theme::get().user),
+            Role::Assistant(assistant) => (assistant.model.clone(), theme::get().assistant),
+        };
+        header.push(Span::styled(format!("  {who}"), style));
+        if let Some(created) = message.created_at {
+            header.push(Span::styled(
+                format!(
+                    "  {}",
+                    created.with_timezone(&Local).format("%Y-%m-%d %H:%M")
+                ),
+                theme::get().muted,
+            ));
+        }
+        vec![Line::from(header), snippet(&message.content, terms)]
+    }
+}
+
+/// one line of `content` around the first match, with every match highlighted
+fn snippet(content: &str, terms: &[String]) -> Line<'static> {
+    let flat = content.replace(['\n', '\r', '\t'], " ");
+    let first = find_matches(&flat, terms).first().map_or(0, |r| r.0);
+    let start = flat[..first]
+        .char_indices()
+        .rev()
+        .nth(SNIPPET_BEFORE.saturating_sub(1))
+        .map_or(0, |(i, _)| i);
+    let end = flat[first..]
+        .char_indices()
+        .nth(SNIPPET_AFTER)
+        .map_or(flat.len(), |(i, _)| first + i);
+    let window = &flat[start..end];
+
+    let mut spans = vec![Span::raw(if start > 0 { "    …" } else { "    " })];
+    let mut last = 0;
+    for (from, to) in find_matches(window, terms) {
+        spans.push(Span::raw(window[last..from].to_string()));
+        spans.push(Span::styled(
+            window[from..to].to_string(),
+            theme::get().search_match,
+        ));
+        last = to;
+    }
+    spans.push(Span::raw(window[last..].to_string()));
+    if end < flat.len() {
+        spans.push(Span::raw("…"));
+    }
+    Line::from(spans)
+}
+
+impl Widget for &SearchView {
+    fn render(self, area: Rect, buf: &mut Buffer) {
+        self.area.set(area);
+        let chunks = Layout::default()
+            .direction(Direction::Vertical)
+            .constraints([Constraint::Length(3), Constraint::Min(0)])
+            .split(area);
+
+        Paragraph::new(format!("> {}", self.query))
+            .block(
+                theme::block().title("Search (model: role: after: before:, Enter=open, Esc=back)"),
+            )
+            .render(chunks[0], buf);
+
+        let terms = Query::parse(&self.query).terms;
+        // keep the selected result on screen, every hit takes at most two rows
+        let rows = usize::from(chunks[1].height.saturating_sub(2)) / 2;
+        let skip = (self.selected + 1).saturating_sub(rows.max(1));
+        let lines: Vec<Line> = self
+            .results
+            .iter()
+            .enumerate()
+            .skip(skip)
+            .flat_map(|(i, hit)| self.hit_lines(hit, &terms, i == self.selected))
+            .collect();
+
+        Paragraph::new(lines)
+            .block(theme::block().title(format!("{} results", self.results.len())))
+            .render(chunks[1], buf);
+    }
+}
+
+impl CurrentScreen {
+    pub fn handle_search(&mut self, key: KeyEvent) -> Result<()> {
+        let CurrentScreen::Search(view) = self else {
+            bail!("Not in search");
+        };
+        match key.code {
+            KeyCode::Char(c) => {
+                view.query.push(c);
+                view.update_results()?;
+            }
+            KeyCode::Backspace => {
+                view.query.pop();
+                view.update_results()?;
+            }
+            KeyCode::Down if !view.results.is_empty() => {
+                view.selected = (view.selected + 1) % view.results.len();
+            }
+            KeyCode::Up if !view.results.is_empty() => {
+                view.selected = (view.results.len() + view.selected - 1) % view.results.len();
+            }
+            KeyCode::Enter => {
+                if let Some(hit) = view.results.get(view.selected) {
+                    let mut chat = ChatView::open(view.store.clone())?;
+                    // the messages box without the sidebar, above a one line input
+                    let area = view.area.get();
+                    chat.viewport
+                        .set((area.width.saturating_sub(2), area.height.saturating_sub(5)));
+                    let terms = Query::parse(&view.query).terms;
+                    chat.jump_to(hit.chat, hit.message, &terms)?;
+                    *self = CurrentScreen::ChatView(Box::new(chat));
+                }
+            }
+            KeyCode::Esc => {
+                *self = CurrentScreen::MainMenu(MainMenu { selected: 0 });
+            }
+            _ => {}
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,260 @@
+// moving a highlight from message to message and doing things to the message it
+// is on. the highlight is focused_message, the keys are in the keymap's messages
+// section. outside of selecting the message actions go to the last message.
+use crate::action::Action;
+use crate::ai_backend::AISettings;
+use crate::app::CurrentScreen;
+use crate::chat_structs::{Message, Role};
+use crate::keymap::{self, KeyContext};
+use crate::theme;
+use crate::ui::ChatView;
+use crate::ui::sidebar::ConfirmAction;
+use anyhow::{Context, Result, bail};
+use crossterm::event::{KeyCode, KeyEvent};
+use ratatui::{
+    buffer::Buffer,
+    layout::Rect,
+    widgets::{Clear, Paragraph, Widget, Wrap},
+};
+
+impl ChatView {
+    /// the message the message actions work on
+    pub fn target_message(&self) -> Option<usize> {
+        let len = self.messages.as_ref().map_or(0, Vec::len);
+        self.focused_message
+            .filter(|&i| i < len)
+            .or(len.checked_sub(1))
+    }
+
+    /// start selecting on the focused or newest message, or stop
+    pub fn toggle_selecting(&mut self) {
+        if self.selecting {
+            self.selecting = false;
+            self.focused_message = None;
+            self.close_find();
+        } else if let Some(i) = self.target_message() {
+            self.selecting = true;
+            self.select_message(i);
+        } else {
+            self.notice = Some("No messages to select".to_string());
+        }
+    }
+
+    pub fn select_message(&mut self, i: usize) {
+        self.focused_message = Some(i);
+        self.scroll = self.message_row(i);
+        self.follow = false;
+    }
+
+    /// the select actions while selecting, they go through the messages
+    pub fn step_message(&mut self, action: Action) {
+        let len = self.messages.as_ref().map_or(0, Vec::len);
+        let Some(last) = len.checked_sub(1) else {
+            return;
+        };
+        let current = self.focused_message.unwrap_or(last);
+        let next = match action {
+            Action::SelectNext => (current + 1).min(last),
+            Action::SelectPrevious => current.saturating_sub(1),
+            Action::SelectFirst => 0,
+            _ => last,
+        };
+        self.select_message(next);
+    }
+
+    pub fn run_message_action(&mut self, action: Action) -> Result<()> {
+        let Some(i) = self.target_message() else {
+            self.notice = Some("No messages yet".to_string());
+            return Ok(());
+        };
+        let messages = self.messages.get_or_insert_with(Vec::new);
+        match action {
+            Action::CopyMessage => {
+                let content = messages[i].content.clone();
+                self.copy_text(&content, "the message");
+            }
+            Action::CopyCodeBlock => self.copy_code_block(i, None),
+            Action::CopyChat => self.copy_chat()?,
+            Action::DeleteMessage => {
+                self.confirm = Some(ConfirmAction::DeleteMessage(i, messages[i].clone()));
+            }
+            Action::RegenerateMessage => {
+                let prompt_at = messages
+                    .iter()
+                    .take(i + 1)
+                    .rposition(|m| m.role == Role::User);
+                // more than the one reply goes, so ask first
+                if let Some(at) = prompt_at.filter(|at| messages.len() > at + 2) {
+                    self.confirm = Some(ConfirmAction::Regenerate(at, messages[at].clone()));
+                } else if let Err(err) = self.regenerate(i, &AISettings::load_or_default()) {
+                    self.notice = Some(format!("{err:#}"));
+                }
+            }
+            Action::ForkFromMessage => {
+                let idx = self.index_of(self.open_chat).context("no chat is open")?;
+                self.fork(idx, "", Some(i))?;
+            }
+            Action::BookmarkMessage => {
+                messages[i].bookmarked = !messages[i].bookmarked;
+                let on = messages[i].bookmarked;
+                self.message_changed(i);
+                self.save_open_marks()?;
+                self.notice = Some(if on { "Bookmarked" } else { "Bookmark removed" }.to_string());
+            }
+            Action::ViewRawMessage => self.raw_view = Some((i, 0)),
+            Action::ToggleCollapse => self.toggle_collapse(i)?,
+            Action::ToggleCodeCollapse => self.toggle_code_collapse(i, None)?,
+            Action::NextBookmark | Action::PreviousBookmark => {
+                let bookmarks: Vec<usize> = messages
+                    .iter()
+                    .enumerate()
+                    .filter(|(_, m)| m.bookmarked)
+                    .map(|(i, _)| i)
+                    .collect();
+                let current = self.focused_message;
+                // round to the other end after the last one
+                let found = if action == Action::NextBookmark {
+                    bookmarks
+                        .iter()
+                        .find(|&&b| current.is_none_or(|c| b > c))
+                        .or(bookmarks.first())
+                } else {
+                    bookmarks
+                        .iter()
+                        .rev()
+                        .find(|&&b| current.is_none_or(|c| b < c))
+                        .or(bookmarks.last())
+                };
+                match found.copied() {
+                    Some(b) => {
+                        self.selecting = true;
+                        self.select_message(b);
+                    }
+                    None => self.notice = Some("No bookmarks in this chat".to_string()),
+                }
+            }
+            _ => {}
+        }
+        Ok(())
+    }
+
+    /// where `msg` is in the open chat now, `i` is where it was when asked
+    pub fn find_message(&mut self, i: usize, msg: &Message) -> Option<usize> {
+        let messages = self.messages.as_deref().unwrap_or_default();
+        let found = if messages.get(i).is_some_and(|m| m.same(msg)) {
+            Some(i)
+        } else {
+            messages.iter().position(|m| m.same(msg))
+        };
+        if found.is_none() {
+            self.notice = Some("That message is gone or changed, nothing was done".to_string());
+        }
+        found
+    }
+
+    /// what deleting a message does once confirmed
+    pub fn delete_message(&mut self, i: usize, msg: &Message) -> Result<()> {
+        let Some(i) = self.find_message(i, msg) else {
+            return Ok(());
+        };
+        let Some(messages) = self.messages.as_mut() else {
+            return Ok(());
+        };
+        messages.remove(i);
+        self.render_cache.get_mut().forget_from(i);
+        self.save_open_chat("the deletion")?;
+        match self.messages.as_ref().map_or(0, Vec::len).checked_sub(1) {
+            None => {
+                self.selecting = false;
+                self.focused_message = None;
+            }
+            Some(last) if self.selecting => self.select_message(i.min(last)),
+            Some(_) => {}
+        }
+        Ok(())
+    }
+
+    /// ask again for the reply to the prompt at or before message `i`, everything
+    /// after that prompt is dropped
+    pub fn regenerate(&mut self, i: usize, settings: &AISettings) -> Result<()> {
+        if self.pending.contains(&self.open_chat) {
+            bail!("still waiting for the last reply");
+        }
+        let messages = self.messages.get_or_insert_with(Vec::new);
+        let prompt_at = messages
+            .iter()
+            .take(i + 1)
+            .rposition(|m| m.role == Role::User)
+            .context("no prompt to answer before this message")?;
+        let prompt = messages[prompt_at].content.clone();
+        messages.truncate(prompt_at);
+        self.render_cache.get_mut().forget_from(prompt_at);
+        self.send_prompt(prompt, settings)?;
+        Ok(())
+    }
+
+    /// the markdown of a message as it was written, over the messages
+    pub fn render_raw_view(&self, area: Rect, buf: &mut Buffer) {
+        let Some((i, scroll)) = self.raw_view else {
+            return;
+        };
+        let Some(message) = self.messages.as_ref().and_then(|m| m.get(i)) else {
+            return;
+        };
+        let popup = Rect {
+            x: area.x + 2,
+            y: area.y + 1,
+            width: area.width.saturating_sub(4),
+            height: area.height.saturating_sub(2),
+        };
+        Clear.render(popup, buf);
+        Paragraph::new(message.content.as_str())
+            .wrap(Wrap { trim: false })
+            .scroll((scroll, 0))
+            .block(
+                theme::block().title("Raw markdown (j/k/PgUp/PgDn=scroll, any other key closes)"),
+            )
+            .render(popup, buf);
+    }
+}
+
+/// the keys for selecting, under the messages
+pub fn selection_hints() -> String {
+    let keymap = keymap::get();
+    let hints: Vec<String> = [
+        (Action::CopyMessage, "copy"),
+        (Action::CopyCodeBlock, "code"),
+        (Action::DeleteMessage, "delete"),
+        (Action::EditMessage, "edit"),
+        (Action::RegenerateMessage, "regenerate"),
+        (Action::ForkFromMessage, "fork"),
+        (Action::BookmarkMessage, "bookmark"),
+        (Action::ViewRawMessage, "raw"),
+        (Action::ToggleCollapse, "collapse"),
+        (Action::SelectMessages, "done"),
+    ]
+    .into_iter()
+    .filter_map(|(action, what)| {
+        Some(format!(
+            "{}={what}",
+            keymap.key_in(KeyContext::Messages, action)?
+        ))
+    })
+    .collect();
+    hints.join(" ")
+}
+
+impl CurrentScreen {
+    pub fn handle_raw_view(chat: &mut ChatView, key: KeyEvent) {
+        let Some((_, scroll)) = chat.raw_view.as_mut() else {
+            return;
+        };
+        match key.code {
+            KeyCode::Char('j') | KeyCode::Down => *scroll = scroll.saturating_add(1),
+            KeyCode::Char('k') | KeyCode::Up => *scroll = scroll.saturating_sub(1),
+            KeyCode::PageDown | KeyCode::Char(' ') => *scroll = scroll.saturating_add(10),
+            KeyCode::PageUp => *scroll = scroll.saturating_sub(10),
+            _ => chat.raw_view = None,
+        }
+    }
+}
@@ -1,5 +1,7 @@
-use crate::ai_backend::{AIBackend, AISettings};
+use crate::ai_backend::{AIBackend, AISettings, default_system_prompt};
 use crate::app::CurrentScreen;
+use crate::editor::EditTarget;
+use crate::theme;
 use crate::ui::MainMenu;
 use crossterm::event::{KeyCode, KeyEvent};
 use ratatui::prelude::*;
@@ -7,10 +9,13 @@ use ratatui::{
     buffer::Buffer,
     layout::Rect,
     text::{Line, Span},
-    widgets::{Block, Borders, Paragraph, Widget},
+    widgets::{Paragraph, Widget},
 };
 use std::path::PathBuf;
 
+// the rows of the settings list
+pub const FIELD_COUNT: usize = 6;
+
 pub struct Config {
     pub ai_settings: AISettings,
     pub available_models: Vec<String>,
@@ -20,6 +25,34 @@ pub struct Config {
     pub selected_field: usize, // Track which setting is selected
     pub temp_input: String,    // Temporary buffer for temperature input
     pub tokens_input: String,  // Temporary buffer for max_tokens
+    // set when the system prompt should be opened in $EDITOR
+    pub edit_request: Option<EditTarget>,
+    // shown in the status bar until the next key
+    pub notice: Option<String>,
+}
+
+impl Config {
+    pub fn open() -> Self {
+        let storage_path = PathBuf::from("settings.json");
+        let settings = AISettings::load_all(&storage_path).unwrap_or(AISettings {
+            backend: AIBackend::OpenAI,
+            model: "gpt-3.5-turbo".to_string(),
+            api_key: None,
+            temperature: 0.7,
+            max_tokens: 2048,
+            system_prompt: default_system_prompt(),
+            price: None,
+        });
+        Config {
+            ai_settings: settings,
+            available_models: vec![String::new()], // fetch the models somehow,
+            selected_field: 0,
+            temp_input: String::new(),
+            tokens_input: String::new(),
+            edit_request: None,
+            notice: None,
+        }
+    }
 }
 
 impl Widget for &Config {
@@ -37,6 +70,15 @@ impl Widget for &Config {
             ),
             format!("Temperature: {}", self.temp_input),
             format!("Max Tokens: {}", self.tokens_input),
+            format!(
+                "System Prompt: {}{}",
+                self.ai_settings.system_prompt.lines().next().unwrap_or(""),
+                if self.selected_field == 5 {
+                    "  (Enter: edit in $EDITOR)"
+                } else {
+                    ""
+                }
+            ),
         ];
 
         let items: Vec<Line> = fields
@@ -44,7 +86,7 @@ impl Widget for &Config {
             .enumerate()
             .map(|(i, text)| {
                 let style = if i == self.selected_field {
-                    Style::default().add_modifier(Modifier::REVERSED)
+                    theme::get().selection
                 } else {
                     Style::default()
                 };
@@ -53,7 +95,7 @@ impl Widget for &Config {
             .collect();
 
         Paragraph::new(items)
-            .block(Block::default().title("Settings").borders(Borders::ALL))
+            .block(theme::block().title("Settings"))
             .render(area, buf);
     }
 }
@@ -63,12 +105,13 @@ impl CurrentScreen {
         let CurrentScreen::Settings(settings) = self else {
             return;
         };
+        settings.notice = None;
         match key.code {
             KeyCode::Up => {
                 settings.selected_field = settings.selected_field.saturating_sub(1);
             }
             KeyCode::Down => {
-                settings.selected_field = (settings.selected_field + 1) % 5;
+                settings.selected_field = (settings.selected_field + 1) % FIELD_COUNT;
             }
             KeyCode::Left | KeyCode::Right if settings.selected_field == 0 => {
                 // Cycle through backend options
@@ -108,15 +151,15 @@ impl CurrentScreen {
                     _ => {}
                 }
                 // Update actual settings when valid
-                if settings.selected_field == 3 {
-                    if let Ok(temp) = settings.temp_input.parse() {
-                        settings.ai_settings.temperature = temp;
-                    }
+                if settings.selected_field == 3
+                    && let Ok(temp) = settings.temp_input.parse()
+                {
+                    settings.ai_settings.temperature = temp;
                 }
-                if settings.selected_field == 4 {
-                    if let Ok(tokens) = settings.tokens_input.parse() {
-                        settings.ai_settings.max_tokens = tokens;
-                    }
+                if settings.selected_field == 4
+                    && let Ok(tokens) = settings.tokens_input.parse()
+                {
+                    settings.ai_settings.max_tokens = tokens;
                 }
                 AISettings::write_all(&PathBuf::from("settings.json"), &settings.ai_settings).ok();
             }
@@ -130,6 +173,9 @@ impl CurrentScreen {
                 }
                 AISettings::write_all(&PathBuf::from("settings.json"), &settings.ai_settings).ok();
             }
+            KeyCode::Enter if settings.selected_field == 5 => {
+                settings.edit_request = Some(EditTarget::SystemPrompt);
+            }
             KeyCode::Esc => {
                 *self = CurrentScreen::MainMenu(MainMenu { selected: 0 });
             }
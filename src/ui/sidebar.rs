@@ -0,0 +1,850 @@
+use crate::action::Action;
+use crate::ai_backend::AISettings;
+use crate::app::CurrentScreen;
+use crate::chat_branch::ChatBranch;
+use crate::chat_structs::Message;
+use crate::export::export_to_file;
+use crate::import::import_into;
+use crate::keymap::{self, KeyContext};
+use crate::theme;
+use crate::ui::ChatView;
+use crate::ui::chat_view::short_age;
+use anyhow::Result;
+use chrono::{DateTime, Utc};
+use crossterm::event::{KeyCode, KeyEvent};
+use ratatui::prelude::*;
+use ratatui::{
+    buffer::Buffer,
+    layout::Rect,
+    text::{Line, Span},
+    widgets::{Clear, Paragraph, Widget, Wrap},
+};
+use std::collections::BTreeSet;
+use std::path::Path;
+
+pub enum SidebarInputMode {
+    NewBranch,    // User is naming a new branch
+    Renaming,     // User is renaming an existing branch
+    MoveToFolder, // User is typing the folder path for the selected branch
+    Tagging,      // User is editing the comma separated tags of the selected branch
+    TagFilter,    // User is typing a tag to filter the list by
+    Export,       // User is typing the file to export the selected branch to
+    Import,       // User is typing the path of a ChatGPT or Open WebUI export
+}
+
+/// one line of the sidebar tree
+pub enum SidebarRow {
+    Folder { path: String, depth: usize },
+    Chat { index: usize, depth: usize },
+}
+
+/// destructive sidebar actions wait on a y/n dialog first
+pub enum ConfirmAction {
+    Delete(usize), // move the chat with this id to the trash
+    Purge(usize),  // drop the chat with this id from the trash for good
+    Clear(usize),  // delete every message of the chat with this id (/clear)
+    // the message at this index of the open chat, checked again when confirmed
+    DeleteMessage(usize, Message),
+    // the prompt at this index, asked again with everything after it dropped
+    Regenerate(usize, Message),
+}
+
+/// everything `u` can take back, newest last
+pub enum UndoEntry {
+    Delete { branch: ChatBranch, index: usize },
+    Archive { id: usize },
+    Pin { id: usize, index: usize },
+    Move { id: usize, other: usize },
+    Folder { id: usize, folder: String },
+}
+
+/// the line under the chat list, with the keys from the keymap
+pub fn sidebar_hints() -> String {
+    let keymap = keymap::get();
+    let hints: Vec<String> = [
+        (Action::NewChat, "new"),
+        (Action::RenameChat, "rename"),
+        (Action::DeleteChat, "delete"),
+        (Action::ArchiveChat, "archive"),
+        (Action::PinChat, "pin"),
+        (Action::MoveChatDown, "down"),
+        (Action::MoveChatUp, "up"),
+        (Action::SortByActivity, "sort"),
+        (Action::MoveToFolder, "folder"),
+        (Action::TagChat, "tags"),
+        (Action::FilterByTag, "filter"),
+        (Action::ExportChat, "export"),
+        (Action::ImportChats, "import"),
+        (Action::Undo, "undo"),
+        (Action::ShowArchived, "archived"),
+        (Action::ShowTrash, "trash"),
+    ]
+    .into_iter()
+    .filter_map(|(action, what)| {
+        Some(format!(
+            "{} {what}",
+            keymap.key_in(KeyContext::Sidebar, action)?
+        ))
+    })
+    .collect();
+    hints.join("  ")
+}
+
+/// the same under the trash, only the way back is in the keymap
+pub fn trash_hints() -> String {
+    match keymap::get().key_in(KeyContext::Sidebar, Action::ShowTrash) {
+        Some(key) => format!("Enter restore  D delete forever  {key}/Esc back"),
+        None => "Enter restore  D delete forever  Esc back".to_string(),
+    }
+}
+
+impl ChatView {
+    pub fn index_of(&self, id: usize) -> Option<usize> {
+        self.branches.iter().position(|b| b.id == id)
+    }
+
+    /// indices into `branches` that the sidebar currently lists
+    pub fn visible_branches(&self) -> Vec<usize> {
+        let mut visible: Vec<usize> = self
+            .branches
+            .iter()
+            .enumerate()
+            .filter(|(_, b)| self.show_archived || !b.archived)
+            .filter(|(_, b)| self.tag_filter.as_ref().is_none_or(|t| b.tags.contains(t)))
+            .map(|(i, _)| i)
+            .collect();
+        if self.sort_by_activity {
+            // pinned chats still come first, the sort is stable for chats without messages
+            visible.sort_by_key(|&i| {
+                let branch = &self.branches[i];
+                (!branch.pinned, std::cmp::Reverse(branch.updated_at))
+            });
+        }
+        visible
+    }
+
+    /// the visible chats as a tree, chats of a folder come before its subfolders
+    /// so pinned chats stay on top
+    pub fn sidebar_rows(&self) -> Vec<SidebarRow> {
+        let visible = self.visible_branches();
+        let mut folders = BTreeSet::new();
+        for &i in &visible {
+            let folder = self.branches[i].folder.as_str();
+            // every parent of a folder is a folder too
+            for (end, _) in folder.match_indices('/') {
+                folders.insert(&folder[..end]);
+            }
+            if !folder.is_empty() {
+                folders.insert(folder);
+            }
+        }
+
+        let mut rows = Vec::new();
+        self.push_folder_rows(&visible, &folders, "", 0, &mut rows);
+        rows
+    }
+
+    fn push_folder_rows(
+        &self,
+        visible: &[usize],
+        folders: &BTreeSet<&str>,
+        parent: &str,
+        depth: usize,
+        rows: &mut Vec<SidebarRow>,
+    ) {
+        for &index in visible {
+            if self.branches[index].folder == parent {
+                rows.push(SidebarRow::Chat { index, depth });
+            }
+        }
+        let children = folders.iter().filter(|f| match f.rsplit_once('/') {
+            Some((up, _)) => up == parent,
+            None => parent.is_empty(),
+        });
+        for &folder in children {
+            rows.push(SidebarRow::Folder {
+                path: folder.to_string(),
+                depth,
+            });
+            if !self.collapsed_folders.contains(folder) {
+                self.push_folder_rows(visible, folders, folder, depth + 1, rows);
+            }
+        }
+    }
+
+    pub fn cursor_row(&self, rows: &[SidebarRow]) -> Option<usize> {
+        rows.iter()
+            .position(|row| match (row, &self.selected_folder) {
+                (SidebarRow::Folder { path, .. }, Some(selected)) => path == selected,
+                (SidebarRow::Chat { index, .. }, None) => *index == self.selected_branch,
+                _ => false,
+            })
+    }
+
+    /// the folder new chats go into: the one under the cursor or the selected chat's
+    fn current_folder(&self) -> String {
+        self.selected_folder
+            .clone()
+            .unwrap_or_else(|| self.branches[self.selected_branch].folder.clone())
+    }
+
+    /// write the chat list and trash, merging in whatever other instances changed
+    pub fn save_branches(&mut self) -> Result<()> {
+        let selected = self.branches.get(self.selected_branch).map(|b| b.id);
+        let open_stamp = self.open_stamp();
+        self.store.save_index(&mut self.branches)?;
+        self.store.save_trash(&mut self.trash)?;
+        self.reselect(selected)?;
+        self.reload_open_chat_if(open_stamp)
+    }
+
+    pub fn open_stamp(&self) -> Option<DateTime<Utc>> {
+        self.index_of(self.open_chat)
+            .and_then(|idx| self.branches[idx].updated_at)
+    }
+
+    /// another instance wrote the open chat if its time moved since `stamp`,
+    /// otherwise it may have bookmarked or collapsed something in it
+    pub fn reload_open_chat_if(&mut self, stamp: Option<DateTime<Utc>>) -> Result<()> {
+        if self.index_of(self.open_chat).is_none() {
+            return Ok(());
+        }
+        let disk = self.store.load_messages(self.open_chat)?;
+        if self.open_stamp() != stamp {
+            self.messages = Some(disk);
+            self.render_cache.get_mut().clear();
+        } else if let Some(messages) = self.messages.as_mut() {
+            for (i, (ours, theirs)) in messages.iter_mut().zip(&disk).enumerate() {
+                if ours.same(theirs)
+                    && (ours.bookmarked, ours.collapsed, &ours.collapsed_code)
+                        != (theirs.bookmarked, theirs.collapsed, &theirs.collapsed_code)
+                {
+                    ours.copy_marks(theirs);
+                    self.render_cache.get_mut().touch(i);
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// keep the cursor and the open chat where they were after the list changed
+    /// underneath them
+    pub fn reselect(&mut self, selected: Option<usize>) -> Result<()> {
+        if self.branches.is_empty() {
+            let id = self.store.new_id(&self.branches, &self.trash)?;
+            self.branches.push(ChatBranch::new(id, "Default Chat"));
+        }
+        self.selected_branch = selected
+            .and_then(|id| self.index_of(id))
+            .unwrap_or(0)
+            .min(self.branches.len() - 1);
+        if self.index_of(self.open_chat).is_none() {
+            self.notice = Some("The open chat was deleted in another window".to_string());
+            self.select_branch(self.selected_branch)?;
+        }
+        Ok(())
+    }
+
+    /// open the chat at `idx`, the previous one's messages are dropped from memory
+    pub fn select_branch(&mut self, idx: usize) -> Result<()> {
+        self.selected_branch = idx;
+        self.focused_message = None;
+        self.selecting = false;
+        self.raw_view = None;
+        self.code_picker = None;
+        self.find = None;
+        self.arena = None;
+        self.code_scroll = 0;
+        self.follow = true;
+        self.render_cache.get_mut().clear();
+        self.open_chat = self.branches[idx].id;
+        self.messages = Some(self.store.load_messages(self.open_chat)?);
+        Ok(())
+    }
+
+    /// the chat at `idx` with its messages, which only the open chat has in memory
+    pub fn full_chat(&self, idx: usize) -> Result<ChatBranch> {
+        let entry = &self.branches[idx];
+        match &self.messages {
+            Some(messages) if entry.id == self.open_chat => {
+                let mut branch = entry.clone();
+                branch.messages.clone_from(messages);
+                Ok(branch)
+            }
+            _ => self.store.load_chat(entry),
+        }
+    }
+
+    /// move the selection one row through the tree, wrapping around
+    pub fn step_selection(&mut self, forward: bool) {
+        let rows = self.sidebar_rows();
+        if rows.is_empty() {
+            return;
+        }
+        let next = match self.cursor_row(&rows) {
+            Some(pos) if forward => (pos + 1) % rows.len(),
+            Some(pos) => (rows.len() + pos - 1) % rows.len(),
+            None => 0,
+        };
+        self.select_row(&rows[next]);
+    }
+
+    /// the top or bottom row of the sidebar
+    pub fn select_end(&mut self, last: bool) {
+        let rows = self.sidebar_rows();
+        let row = if last { rows.last() } else { rows.first() };
+        if let Some(row) = row {
+            self.select_row(row);
+        }
+    }
+
+    pub fn select_row(&mut self, row: &SidebarRow) {
+        match row {
+            SidebarRow::Folder { path, .. } => self.selected_folder = Some(path.clone()),
+            SidebarRow::Chat { index, .. } => {
+                self.selected_folder = None;
+                self.selected_branch = *index;
+            }
+        }
+    }
+
+    pub fn toggle_folder(&mut self, path: String) {
+        if !self.collapsed_folders.remove(&path) {
+            self.collapsed_folders.insert(path);
+        }
+    }
+
+    fn move_to_folder(&mut self, folder: &str) -> Result<()> {
+        // normalise "/a//b/" into "a/b"
+        let folder = folder
+            .split('/')
+            .map(str::trim)
+            .filter(|part| !part.is_empty())
+            .collect::<Vec<_>>()
+            .join("/");
+        let branch = &mut self.branches[self.selected_branch];
+        self.undo_stack.push(UndoEntry::Folder {
+            id: branch.id,
+            folder: std::mem::replace(&mut branch.folder, folder),
+        });
+        // make sure the chat is still visible where it landed
+        let mut parent = branch.folder.as_str();
+        loop {
+            self.collapsed_folders.remove(parent);
+            match parent.rsplit_once('/') {
+                Some((up, _)) => parent = up,
+                None => break,
+            }
+        }
+        self.save_branches()
+    }
+
+    fn set_tags(&mut self, input: &str) -> Result<()> {
+        let mut tags: Vec<String> = input
+            .split(',')
+            .map(|t| t.trim().trim_start_matches('#').to_string())
+            .filter(|t| !t.is_empty())
+            .collect();
+        tags.sort();
+        tags.dedup();
+        self.branches[self.selected_branch].tags = tags;
+        self.save_branches()
+    }
+
+    fn delete_branch(&mut self, id: usize) -> Result<()> {
+        let Some(index) = self.index_of(id) else {
+            return Ok(());
+        };
+        let branch = self.branches.remove(index);
+        self.notice = Some(format!("Deleted '{}' (u: undo)", branch.name));
+        self.trash.push(branch.clone());
+        self.undo_stack.push(UndoEntry::Delete { branch, index });
+
+        // there always has to be something to look at
+        if self.branches.is_empty() {
+            let id = self.store.new_id(&self.branches, &self.trash)?;
+            self.branches.push(ChatBranch::new(id, "Default Chat"));
+        }
+        self.select_branch(index.min(self.branches.len() - 1))?;
+        self.save_branches()
+    }
+
+    pub fn toggle_archive(&mut self) -> Result<()> {
+        let branch = &mut self.branches[self.selected_branch];
+        branch.archived = !branch.archived;
+        self.notice = Some(format!(
+            "{} '{}'",
+            if branch.archived {
+                "Archived"
+            } else {
+                "Unarchived"
+            },
+            branch.name
+        ));
+        self.undo_stack.push(UndoEntry::Archive { id: branch.id });
+        if !self.show_archived && branch.archived {
+            self.step_selection(true);
+        }
+        self.save_branches()
+    }
+
+    /// pinning moves the chat to the end of the pinned block, unpinning to the
+    /// start of the unpinned one, so pinned chats always stay on top
+    pub fn toggle_pin(&mut self) -> Result<()> {
+        let index = self.selected_branch;
+        let mut branch = self.branches.remove(index);
+        branch.pinned = !branch.pinned;
+        self.undo_stack.push(UndoEntry::Pin {
+            id: branch.id,
+            index,
+        });
+        let pinned_count = self.branches.iter().filter(|b| b.pinned).count();
+        self.branches.insert(pinned_count, branch);
+        self.selected_branch = pinned_count;
+        self.save_branches()
+    }
+
+    /// swap the selected chat with its visible neighbour, staying inside its pin
+    /// group and folder
+    pub fn move_branch(&mut self, down: bool) -> Result<()> {
+        let folder = &self.branches[self.selected_branch].folder;
+        let visible: Vec<usize> = self
+            .visible_branches()
+            .into_iter()
+            .filter(|&i| self.branches[i].folder == *folder)
+            .collect();
+        let Some(pos) = visible.iter().position(|&i| i == self.selected_branch) else {
+            return Ok(());
+        };
+        let neighbour = if down {
+            visible.get(pos + 1)
+        } else {
+            pos.checked_sub(1).and_then(|p| visible.get(p))
+        };
+        let Some(&other) = neighbour else {
+            return Ok(());
+        };
+        if self.branches[other].pinned != self.branches[self.selected_branch].pinned {
+            return Ok(());
+        }
+        self.undo_stack.push(UndoEntry::Move {
+            id: self.branches[self.selected_branch].id,
+            other: self.branches[other].id,
+        });
+        self.branches.swap(self.selected_branch, other);
+        self.selected_branch = other;
+        self.save_branches()
+    }
+
+    pub fn undo(&mut self) -> Result<()> {
+        let Some(entry) = self.undo_stack.pop() else {
+            self.notice = Some("Nothing to undo".to_string());
+            return Ok(());
+        };
+        match entry {
+            UndoEntry::Delete { branch, index } => {
+                self.trash.retain(|b| b.id != branch.id);
+                self.notice = Some(format!("Restored '{}'", branch.name));
+                let index = index.min(self.branches.len());
+                self.branches.insert(index, branch);
+                self.select_branch(index)?;
+            }
+            UndoEntry::Archive { id } => {
+                if let Some(idx) = self.index_of(id) {
+                    self.branches[idx].archived = !self.branches[idx].archived;
+                    self.selected_branch = idx;
+                }
+            }
+            UndoEntry::Pin { id, index } => {
+                if let Some(idx) = self.index_of(id) {
+                    let mut branch = self.branches.remove(idx);
+                    branch.pinned = !branch.pinned;
+                    let index = index.min(self.branches.len());
+                    self.branches.insert(index, branch);
+                    self.selected_branch = index;
+                }
+            }
+            UndoEntry::Move { id, other } => {
+                if let (Some(a), Some(b)) = (self.index_of(id), self.index_of(other)) {
+                    self.branches.swap(a, b);
+                    self.selected_branch = b;
+                }
+            }
+            UndoEntry::Folder { id, folder } => {
+                if let Some(idx) = self.index_of(id) {
+                    self.branches[idx].folder = folder;
+                    self.selected_branch = idx;
+                }
+            }
+        }
+        self.save_branches()
+    }
+
+    fn restore_from_trash(&mut self) -> Result<()> {
+        if self.trash_selected >= self.trash.len() {
+            return Ok(());
+        }
+        let mut branch = self.trash.remove(self.trash_selected);
+        branch.pinned = false;
+        // undoing the delete now would put a second copy back
+        let id = branch.id;
+        self.undo_stack
+            .retain(|e| !matches!(e, UndoEntry::Delete { branch, .. } if branch.id == id));
+        self.notice = Some(format!("Restored '{}'", branch.name));
+        self.branches.push(branch);
+        self.trash_selected = self.trash_selected.min(self.trash.len().saturating_sub(1));
+        self.select_branch(self.branches.len() - 1)?;
+        self.save_branches()
+    }
+
+    fn purge(&mut self, id: usize) -> Result<()> {
+        self.trash.retain(|b| b.id != id);
+        self.store.delete_messages(id)?;
+        // an undo entry for a purged chat would resurrect it from nowhere
+        self.undo_stack
+            .retain(|e| !matches!(e, UndoEntry::Delete { branch, .. } if branch.id == id));
+        self.trash_selected = self.trash_selected.min(self.trash.len().saturating_sub(1));
+        self.notice = Some("Deleted permanently".to_string());
+        self.save_branches()
+    }
+
+    fn chat_row_line(&self, index: usize, depth: usize) -> Line<'_> {
+        let branch = &self.branches[index];
+        let is_selected = self.selected_folder.is_none() && index == self.selected_branch;
+        let prefix = if is_selected { "▶" } else { " " };
+        let indent = "  ".repeat(depth);
+        let pin = if branch.pinned { "★ " } else { "" };
+
+        let theme = theme::get();
+        let mut style = Style::default();
+        if branch.archived {
+            style = theme.muted;
+        }
+        if is_selected && matches!(self.sidebar_input_mode, Some(SidebarInputMode::Renaming)) {
+            return Line::from(Span::styled(
+                format!("{prefix} {indent}{}", self.sidebar_input_buffer),
+                style.patch(theme.input),
+            ));
+        }
+
+        let mut spans = vec![Span::styled(
+            format!("{prefix} {indent}{pin}{}", branch.name),
+            style,
+        )];
+        if let Some(last) = branch.updated_at {
+            spans.push(Span::styled(format!(" {}", short_age(last)), theme.muted));
+        }
+        for tag in &branch.tags {
+            spans.push(Span::styled(format!(" #{tag}"), theme.tag));
+        }
+        Line::from(spans)
+    }
+
+    fn sidebar_lines(&self) -> Vec<Line<'_>> {
+        if self.show_trash {
+            if self.trash.is_empty() {
+                return vec![Line::from(Span::styled(
+                    "  (trash is empty)",
+                    theme::get().muted,
+                ))];
+            }
+            return self
+                .trash
+                .iter()
+                .enumerate()
+                .map(|(i, branch)| {
+                    let prefix = if i == self.trash_selected { "▶" } else { " " };
+                    Line::from(format!("{prefix} {}", branch.name))
+                })
+                .collect();
+        }
+
+        let mut lines: Vec<Line> = self
+            .sidebar_rows()
+            .into_iter()
+            .map(|row| match row {
+                SidebarRow::Folder { path, depth } => {
+                    let is_selected = self.selected_folder.as_ref() == Some(&path);
+                    let prefix = if is_selected { "▶" } else { " " };
+                    let arrow = if self.collapsed_folders.contains(&path) {
+                        "▸"
+                    } else {
+                        "▾"
+                    };
+                    let name = path.rsplit('/').next().unwrap_or_default();
+                    Line::from(Span::styled(
+                        format!("{prefix} {}{arrow} {name}/", "  ".repeat(depth)),
+                        Style::default().add_modifier(Modifier::BOLD),
+                    ))
+                }
+                SidebarRow::Chat { index, depth } => self.chat_row_line(index, depth),
+            })
+            .collect();
+
+        match self.sidebar_input_mode {
+            Some(SidebarInputMode::NewBranch) => lines.push(Line::from(Span::styled(
+                format!("▶ {}", self.sidebar_input_buffer),
+                theme::get().input,
+            ))),
+            Some(
+                SidebarInputMode::MoveToFolder
+                | SidebarInputMode::Tagging
+                | SidebarInputMode::TagFilter
+                | SidebarInputMode::Export
+                | SidebarInputMode::Import,
+            ) => {
+                lines.push(Line::from(""));
+                lines.push(Line::from(Span::styled(
+                    format!("> {}", self.sidebar_input_buffer),
+                    theme::get().input,
+                )));
+            }
+            _ => {}
+        }
+        lines
+    }
+
+    pub fn render_sidebar(&self, area: Rect, buf: &mut Buffer) {
+        let v = Layout::default()
+            .direction(Direction::Vertical)
+            .constraints([Constraint::Min(3), Constraint::Length(6)])
+            .split(area);
+
+        let title = match self.sidebar_input_mode {
+            Some(SidebarInputMode::NewBranch) => "New Chat (Enter: save, Esc: cancel)".to_string(),
+            Some(SidebarInputMode::Renaming) => "Renaming (Enter: save, Esc: cancel)".to_string(),
+            Some(SidebarInputMode::MoveToFolder) => "Move to folder (a/b, empty: top)".to_string(),
+            Some(SidebarInputMode::Tagging) => "Tags (comma separated)".to_string(),
+            Some(SidebarInputMode::TagFilter) => "Filter by tag (empty: all)".to_string(),
+            Some(SidebarInputMode::Export) => "Export to (.md, .html, .json)".to_string(),
+            Some(SidebarInputMode::Import) => "Import ChatGPT/Open WebUI file".to_string(),
+            None if self.show_trash => "Trash".to_string(),
+            None => format!(
+                "Chats{}{}{}",
+                if self.sort_by_activity {
+                    " (recent)"
+                } else {
+                    ""
+                },
+                if self.show_archived { " +archived" } else { "" },
+                self.tag_filter
+                    .as_ref()
+                    .map(|t| format!(" #{t}"))
+                    .unwrap_or_default()
+            ),
+        };
+        self.sidebar_list.set(v[0]);
+        Paragraph::new(self.sidebar_lines())
+            .block(theme::block().title(title))
+            .render(v[0], buf);
+
+        Paragraph::new(if self.show_trash {
+            trash_hints()
+        } else {
+            sidebar_hints()
+        })
+        .style(theme::get().muted)
+        .wrap(Wrap { trim: true })
+        .render(v[1], buf);
+    }
+
+    pub fn render_confirm(&self, area: Rect, buf: &mut Buffer) {
+        let Some(confirm) = &self.confirm else {
+            return;
+        };
+        let (id, text) = match confirm {
+            ConfirmAction::Delete(id) => (id, "Move chat to trash?"),
+            ConfirmAction::Purge(id) => (id, "Delete chat forever?"),
+            ConfirmAction::Clear(id) => (id, "Delete every message?"),
+            ConfirmAction::DeleteMessage(..) => (&self.open_chat, "Delete this message?"),
+            ConfirmAction::Regenerate(..) => (&self.open_chat, "Drop everything after it?"),
+        };
+        let name = self
+            .branches
+            .iter()
+            .chain(&self.trash)
+            .find(|b| b.id == *id)
+            .map_or("", |b| b.name.as_str());
+
+        let popup = centered_rect(area, 40, 5);
+        Clear.render(popup, buf);
+        Paragraph::new(vec![
+            Line::from(format!("'{name}'")),
+            Line::from(""),
+            Line::from("y: yes   n: no"),
+        ])
+        .alignment(Alignment::Center)
+        .block(theme::block().border_style(theme::get().error).title(text))
+        .render(popup, buf);
+    }
+}
+
+pub fn centered_rect(area: Rect, width: u16, height: u16) -> Rect {
+    let width = width.min(area.width);
+    let height = height.min(area.height);
+    Rect {
+        x: area.x + (area.width - width) / 2,
+        y: area.y + (area.height - height) / 2,
+        width,
+        height,
+    }
+}
+
+impl CurrentScreen {
+    pub fn handle_sidebar_confirm(chat: &mut ChatView, key: KeyEvent) -> Result<()> {
+        let Some(confirm) = chat.confirm.take() else {
+            return Ok(());
+        };
+        match (key.code, confirm) {
+            (KeyCode::Char('y' | 'Y') | KeyCode::Enter, ConfirmAction::Delete(id)) => {
+                chat.delete_branch(id)?;
+            }
+            (KeyCode::Char('y' | 'Y') | KeyCode::Enter, ConfirmAction::Purge(id)) => {
+                chat.purge(id)?;
+            }
+            (KeyCode::Char('y' | 'Y') | KeyCode::Enter, ConfirmAction::Clear(id)) => {
+                chat.clear_chat(id)?;
+            }
+            (KeyCode::Char('y' | 'Y') | KeyCode::Enter, ConfirmAction::DeleteMessage(i, msg)) => {
+                chat.delete_message(i, &msg)?;
+            }
+            (KeyCode::Char('y' | 'Y') | KeyCode::Enter, ConfirmAction::Regenerate(i, msg)) => {
+                if let Some(i) = chat.find_message(i, &msg)
+                    && let Err(err) = chat.regenerate(i, &AISettings::load_or_default())
+                {
+                    chat.notice = Some(format!("{err:#}"));
+                }
+            }
+            _ => {}
+        }
+        Ok(())
+    }
+
+    fn handle_sidebar_trash(chat: &mut ChatView, key: KeyEvent) -> Result<()> {
+        // moving and going back follow the keymap, restoring and purging have
+        // no actions of their own
+        let keymap = keymap::get();
+        if keymap.is(Action::ShowTrash, key) {
+            chat.show_trash = false;
+            return Ok(());
+        }
+        match key.code {
+            KeyCode::Esc => chat.show_trash = false,
+            _ if chat.trash.is_empty() => {}
+            _ if keymap.is(Action::SelectNext, key) => {
+                chat.trash_selected = (chat.trash_selected + 1) % chat.trash.len();
+            }
+            _ if keymap.is(Action::SelectPrevious, key) => {
+                chat.trash_selected =
+                    (chat.trash.len() + chat.trash_selected - 1) % chat.trash.len();
+            }
+            KeyCode::Enter => chat.restore_from_trash()?,
+            KeyCode::Char('D') => {
+                if let Some(branch) = chat.trash.get(chat.trash_selected) {
+                    chat.confirm = Some(ConfirmAction::Purge(branch.id));
+                }
+            }
+            _ => {}
+        }
+        Ok(())
+    }
+
+    fn handle_sidebar_input(chat: &mut ChatView, key: KeyEvent) -> Result<()> {
+        // Handle input for renaming, new branches, folders and tags
+        match key.code {
+            KeyCode::Enter => {
+                let input = chat.sidebar_input_buffer.trim().to_string();
+                match chat.sidebar_input_mode.take() {
+                    Some(SidebarInputMode::NewBranch) if !input.is_empty() => {
+                        // Create new branch with custom name in the current folder
+                        let id = chat.store.new_id(&chat.branches, &chat.trash)?;
+                        let mut branch = ChatBranch::new(id, &input);
+                        branch.folder = chat.current_folder();
+                        chat.branches.push(branch);
+                        chat.selected_folder = None;
+                        chat.select_branch(chat.branches.len() - 1)?;
+                        chat.save_branches()?;
+                    }
+                    Some(SidebarInputMode::Renaming) if !input.is_empty() => {
+                        // Rename selected branch
+                        if let Some(branch) = chat.branches.get_mut(chat.selected_branch) {
+                            branch.name = input;
+                            chat.save_branches()?;
+                        }
+                    }
+                    Some(SidebarInputMode::MoveToFolder) => chat.move_to_folder(&input)?,
+                    Some(SidebarInputMode::Tagging) => chat.set_tags(&input)?,
+                    Some(SidebarInputMode::Export) if !input.is_empty() => {
+                        let branch = chat.full_chat(chat.selected_branch)?;
+                        match export_to_file(&branch, Path::new(&input)) {
+                            Ok(()) => chat.notice = Some(format!("Exported to {input}")),
+                            Err(e) => chat.notice = Some(format!("Export failed: {e}")),
+                        }
+                    }
+                    Some(SidebarInputMode::Import) if !input.is_empty() => {
+                        match import_into(
+                            Path::new(&input),
+                            None,
+                            &chat.store,
+                            &mut chat.branches,
+                            &chat.trash,
+                        ) {
+                            Ok(count) => {
+                                chat.save_branches()?;
+                                chat.notice = Some(format!("Imported {count} chats"));
+                            }
+                            Err(e) => chat.notice = Some(format!("Import failed: {e}")),
+                        }
+                    }
+                    Some(SidebarInputMode::TagFilter) => {
+                        chat.tag_filter = Some(input.trim_start_matches('#').to_string())
+                            .filter(|t| !t.is_empty());
+                        chat.selected_folder = None;
+                    }
+                    _ => {}
+                }
+                chat.sidebar_input_buffer.clear();
+            }
+            KeyCode::Char(c) => chat.sidebar_input_buffer.push(c),
+            KeyCode::Backspace => {
+                chat.sidebar_input_buffer.pop();
+            }
+            KeyCode::Esc => {
+                chat.sidebar_input_mode = None;
+                chat.sidebar_input_buffer.clear();
+            }
+            _ => {}
+        }
+        Ok(())
+    }
+
+    pub fn handle_chat_view_sidebar(chat: &mut ChatView, key: KeyEvent) -> Result<bool> {
+        if chat.sidebar_input_mode.is_some() {
+            Self::handle_sidebar_input(chat, key)?;
+            return Ok(true);
+        }
+        if chat.show_trash {
+            Self::handle_sidebar_trash(chat, key)?;
+            return Ok(true);
+        }
+        chat.notice = None;
+
+        // a folder row only opens and closes, everything else acts on chats. the
+        // rest of the sidebar keys are in the keymap
+        if let Some(folder) = chat.selected_folder.clone()
+            && matches!(
+                key.code,
+                KeyCode::Enter | KeyCode::Char(' ' | 'h' | 'l') | KeyCode::Left | KeyCode::Right
+            )
+        {
+            chat.toggle_folder(folder);
+            return Ok(true);
+        }
+        if key.code == KeyCode::Enter {
+            // switch to that chat branch
+            chat.select_branch(chat.selected_branch)?;
+            chat.show_sidebar = false;
+        }
+        Ok(true)
+    }
+}
@@ -0,0 +1,178 @@
+// the line at the top: which profile and model answer, how many tokens the draft
+// and the chat are, what the replies cost so far, a spinner while waiting and the
+// screen's notice until the next key
+use crate::ai::estimate_tokens;
+use crate::ai_backend::AISettings;
+use crate::app::CurrentScreen;
+use crate::profiles::Profiles;
+use crate::theme;
+use crate::ui::arena::Arena;
+use ratatui::{
+    buffer::Buffer,
+    layout::Rect,
+    style::Style,
+    text::{Line, Span},
+    widgets::{Paragraph, Widget},
+};
+use std::fs;
+use std::path::Path;
+use std::sync::{LazyLock, Mutex};
+use std::time::{Duration, Instant, SystemTime};
+use unicode_width::UnicodeWidthStr;
+
+const SPINNER: [&str; 10] = ["⠋", "⠙", "⠹", "⠸", "⠼", "⠴", "⠦", "⠧", "⠇", "⠏"];
+const SPINNER_STEP: Duration = Duration::from_millis(80);
+// how often settings.json and profiles.json are looked at, not every frame
+const RECHECK_EVERY: Duration = Duration::from_secs(1);
+
+static STARTED: LazyLock<Instant> = LazyLock::new(Instant::now);
+// settings.json and profiles.json are only read again when they change
+static ACTIVE: Mutex<Option<Active>> = Mutex::new(None);
+// dollars spent on replies since the app started, whichever screen is open
+static SESSION_COST: Mutex<f64> = Mutex::new(0.0);
+
+/// count what a reply cost
+pub fn add_cost(cost: f64) {
+    *SESSION_COST.lock().unwrap() += cost;
+}
+
+struct Active {
+    stamps: [Option<SystemTime>; 2],
+    checked: Instant,
+    label: String,
+    system_tokens: usize,
+}
+
+fn modified(path: &str) -> Option<SystemTime> {
+    fs::metadata(path).and_then(|m| m.modified()).ok()
+}
+
+/// "profile · backend · model" and the tokens of the system prompt
+fn active() -> (String, usize) {
+    let mut active = ACTIVE.lock().unwrap();
+    if let Some(a) = active.as_ref()
+        && a.checked.elapsed() < RECHECK_EVERY
+    {
+        return (a.label.clone(), a.system_tokens);
+    }
+    let stamps = [modified("settings.json"), modified("profiles.json")];
+    if let Some(a) = active.as_mut().filter(|a| a.stamps == stamps) {
+        a.checked = Instant::now();
+    } else {
+        // loading would write an empty settings.json
+        let settings = if stamps[0].is_some() {
+            AISettings::load_or_default()
+        } else {
+            AISettings::fallback()
+        };
+        let profile = Profiles::load(Path::new("profiles.json"))
+            .ok()
+            .and_then(|p| p.active);
+        let label = match profile {
+            Some(profile) => format!("{profile} · {:?} · {}", settings.backend, settings.model),
+            None => format!("{:?} · {}", settings.backend, settings.model),
+        };
+        *active = Some(Active {
+            stamps,
+            checked: Instant::now(),
+            label,
+            system_tokens: estimate_tokens(&settings.system_prompt),
+        });
+    }
+    active
+        .as_ref()
+        .map(|a| (a.label.clone(), a.system_tokens))
+        .unwrap_or_default()
+}
+
+/// 1234 as "1.2k"
+fn short_count(n: usize) -> String {
+    if n < 1000 {
+        n.to_string()
+    } else {
+        #[allow(clippy::cast_precision_loss)]
+        let thousands = n as f64 / 1000.0;
+        format!("{thousands:.1}k")
+    }
+}
+
+pub fn spinner() -> &'static str {
+    let step = STARTED.elapsed().as_millis() / SPINNER_STEP.as_millis();
+    SPINNER[usize::try_from(step).unwrap_or(0) % SPINNER.len()]
+}
+
+impl CurrentScreen {
+    pub fn render_status_bar(&self, area: Rect, buf: &mut Buffer) {
+        let theme = theme::get();
+        let (label, system_tokens) = active();
+        let mut parts = vec![format!(" llm-tui :3 │ {label}")];
+        let mut waiting = 0;
+        if let Some(chat) = self.chat_view_ref() {
+            let context = system_tokens
+                + chat
+                    .messages
+                    .iter()
+                    .flatten()
+                    .map(|m| estimate_tokens(&m.content))
+                    .sum::<usize>();
+            parts.push(format!(
+                "draft ~{} · context ~{} tokens",
+                short_count(estimate_tokens(chat.input.text())),
+                short_count(context)
+            ));
+            if let Some(find) = &chat.find {
+                parts.push(format!("find: {}", find.counter()));
+            }
+            waiting = chat.pending.len() + chat.arena.as_ref().map_or(0, Arena::waiting);
+        }
+        let cost = *SESSION_COST.lock().unwrap();
+        if cost > 0.0 {
+            parts.push(format!("${cost:.4}"));
+        }
+        let mut spans = vec![Span::raw(parts.join(" │ "))];
+        match waiting {
+            0 => {}
+            1 => spans.push(Span::styled(
+                format!(" │ {} waiting for the reply", spinner()),
+                theme.notice,
+            )),
+            n => spans.push(Span::styled(
+                format!(" │ {} waiting for {n} replies", spinner()),
+                theme.notice,
+            )),
+        }
+        Paragraph::new(Line::from(spans))
+            .style(theme.status)
+            .render(area, buf);
+        if let Some(text) = self.notice() {
+            render_corner(text, theme.status.patch(theme.notice), area, buf);
+        }
+    }
+}
+
+/// `text` in the bar's right corner, over whatever was there
+pub fn render_corner(text: &str, style: Style, area: Rect, buf: &mut Buffer) {
+    let width = u16::try_from(text.width() + 2)
+        .unwrap_or(u16::MAX)
+        .min(area.width);
+    let corner = Rect {
+        x: area.right() - width,
+        width,
+        ..area
+    };
+    Paragraph::new(format!(" {text} "))
+        .style(style)
+        .render(corner, buf);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn big_counts_are_shortened() {
+        assert_eq!(short_count(999), "999");
+        assert_eq!(short_count(1000), "1.0k");
+        assert_eq!(short_count(12_345), "12.3k");
+    }
+}
@@ -0,0 +1,355 @@
+// the prompt editor: a multi-line text with a cursor.
+// the cursor is a byte offset that always sits on a grapheme boundary, so an emoji
+// or a CJK character is stepped over and deleted as one, widths come from
+// unicode-width so wide characters take two cells when wrapping.
+use crossterm::event::{KeyCode, KeyEvent, KeyModifiers};
+use ratatui::{
+    style::{Modifier, Style},
+    text::{Line, Span},
+};
+use unicode_segmentation::UnicodeSegmentation;
+use unicode_width::UnicodeWidthStr;
+
+const TAB: &str = "    ";
+
+#[derive(Debug, Clone, Default)]
+pub struct TextArea {
+    text: String,
+    cursor: usize,
+    // column to aim for when moving up and down over shorter lines
+    column: Option<usize>,
+}
+
+fn is_word(grapheme: &str) -> bool {
+    grapheme.chars().any(|c| c.is_alphanumeric() || c == '_')
+}
+
+fn display_width(grapheme: &str) -> usize {
+    if grapheme == "\t" {
+        TAB.len()
+    } else {
+        grapheme.width()
+    }
+}
+
+impl TextArea {
+    pub fn text(&self) -> &str {
+        &self.text
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.text.is_empty()
+    }
+
+    /// replace the whole text and put the cursor at its end
+    pub fn set_text(&mut self, text: &str) {
+        self.text = text.to_string();
+        self.cursor = self.text.len();
+        self.column = None;
+    }
+
+    pub fn clear(&mut self) {
+        self.set_text("");
+    }
+
+    pub fn insert_str(&mut self, text: &str) {
+        // pasted text can come with windows line endings
+        let text = text.replace("\r\n", "\n").replace('\r', "\n");
+        self.text.insert_str(self.cursor, &text);
+        self.cursor += text.len();
+    }
+
+    pub fn insert_char(&mut self, c: char) {
+        self.text.insert(self.cursor, c);
+        self.cursor += c.len_utf8();
+    }
+
+    fn prev_boundary(&self, pos: usize) -> usize {
+        self.text[..pos]
+            .grapheme_indices(true)
+            .next_back()
+            .map_or(0, |(i, _)| i)
+    }
+
+    fn next_boundary(&self, pos: usize) -> usize {
+        self.text[pos..]
+            .graphemes(true)
+            .next()
+            .map_or(pos, |g| pos + g.len())
+    }
+
+    fn line_start(&self, pos: usize) -> usize {
+        self.text[..pos].rfind('\n').map_or(0, |i| i + 1)
+    }
+
+    fn line_end(&self, pos: usize) -> usize {
+        self.text[pos..]
+            .find('\n')
+            .map_or(self.text.len(), |i| pos + i)
+    }
+
+    /// start of the word left of `pos`, skipping the spaces and punctuation before it
+    fn word_start(&self, pos: usize) -> usize {
+        let mut graphemes = self.text[..pos].grapheme_indices(true).rev().peekable();
+        let mut start = pos;
+        while let Some((i, g)) = graphemes.next_if(|(_, g)| !is_word(g)) {
+            // a line break is a word of its own
+            if g == "\n" {
+                return if start == pos { i } else { start };
+            }
+            start = i;
+        }
+        while let Some((i, _)) = graphemes.next_if(|(_, g)| is_word(g)) {
+            start = i;
+        }
+        start
+    }
+
+    fn word_end(&self, pos: usize) -> usize {
+        let mut graphemes = self.text[pos..].graphemes(true).peekable();
+        let mut end = pos;
+        while let Some(g) = graphemes.next_if(|g| !is_word(g)) {
+            if g == "\n" {
+                return if end == pos { end + 1 } else { end };
+            }
+            end += g.len();
+        }
+        while let Some(g) = graphemes.next_if(|g| is_word(g)) {
+            end += g.len();
+        }
+        end
+    }
+
+    fn delete_range(&mut self, from: usize, to: usize) {
+        self.text.replace_range(from..to, "");
+        self.cursor = from;
+    }
+
+    /// display column of the cursor within its line
+    fn cursor_column(&self) -> usize {
+        self.text[self.line_start(self.cursor)..self.cursor]
+            .graphemes(true)
+            .map(display_width)
+            .sum()
+    }
+
+    /// the offset in the line `start..end` closest to `column`
+    fn offset_at_column(&self, start: usize, end: usize, column: usize) -> usize {
+        let mut width = 0;
+        for (i, g) in self.text[start..end].grapheme_indices(true) {
+            width += display_width(g);
+            if width > column {
+                return start + i;
+            }
+        }
+        end
+    }
+
+    /// move to the line above, false if the cursor already is on the first line
+    pub fn up(&mut self) -> bool {
+        let start = self.line_start(self.cursor);
+        if start == 0 {
+            return false;
+        }
+        let column = *self.column.get_or_insert(self.cursor_column());
+        let prev_start = self.line_start(start - 1);
+        self.cursor = self.offset_at_column(prev_start, start - 1, column);
+        true
+    }
+
+    /// move to the line below, false if the cursor already is on the last line
+    pub fn down(&mut self) -> bool {
+        let end = self.line_end(self.cursor);
+        if end == self.text.len() {
+            return false;
+        }
+        let column = *self.column.get_or_insert(self.cursor_column());
+        let next_end = self.line_end(end + 1);
+        self.cursor = self.offset_at_column(end + 1, next_end, column);
+        true
+    }
+
+    /// handle an editing key, false if the key isn't one. plain Enter is left to
+    /// the caller, Shift+Enter, Alt+Enter and Ctrl+J insert a line break
+    pub fn input(&mut self, key: KeyEvent) -> bool {
+        let ctrl = key.modifiers.contains(KeyModifiers::CONTROL);
+        let alt = key.modifiers.contains(KeyModifiers::ALT);
+        let pos = self.cursor;
+        if !matches!(key.code, KeyCode::Up | KeyCode::Down) {
+            self.column = None;
+        }
+        match key.code {
+            KeyCode::Enter
+                if key
+                    .modifiers
+                    .intersects(KeyModifiers::SHIFT | KeyModifiers::ALT) =>
+            {
+                self.insert_char('\n');
+            }
+            KeyCode::Char('j') if ctrl => self.insert_char('\n'),
+            KeyCode::Char('w') if ctrl => self.delete_range(self.word_start(pos), pos),
+            KeyCode::Char('u') if ctrl => self.delete_range(self.line_start(pos), pos),
+            KeyCode::Char('b') if alt => self.cursor = self.word_start(pos),
+            KeyCode::Char('f') if alt => self.cursor = self.word_end(pos),
+            KeyCode::Char('d') if alt => {
+                let end = self.word_end(pos);
+                self.delete_range(pos, end);
+            }
+            KeyCode::Char(c) if !ctrl && !alt => self.insert_char(c),
+            KeyCode::Backspace if ctrl || alt => self.delete_range(self.word_start(pos), pos),
+            KeyCode::Backspace => self.delete_range(self.prev_boundary(pos), pos),
+            KeyCode::Delete if ctrl || alt => {
+                let end = self.word_end(pos);
+                self.delete_range(pos, end);
+            }
+            KeyCode::Delete => {
+                let end = self.next_boundary(pos);
+                self.delete_range(pos, end);
+            }
+            KeyCode::Left if ctrl || alt => self.cursor = self.word_start(pos),
+            KeyCode::Left => self.cursor = self.prev_boundary(pos),
+            KeyCode::Right if ctrl || alt => self.cursor = self.word_end(pos),
+            KeyCode::Right => self.cursor = self.next_boundary(pos),
+            KeyCode::Home if ctrl => self.cursor = 0,
+            KeyCode::Home => self.cursor = self.line_start(pos),
+            KeyCode::End if ctrl => self.cursor = self.text.len(),
+            KeyCode::End => self.cursor = self.line_end(pos),
+            KeyCode::Up => return self.up(),
+            KeyCode::Down => return self.down(),
+            _ => return false,
+        }
+        true
+    }
+
+    /// the text wrapped to `width` cells behind a "> " prompt, and the row the
+    /// cursor is on. the cursor is drawn as a reversed cell when `show_cursor` is set
+    pub fn lines(&self, width: u16, show_cursor: bool) -> (Vec<Line<'static>>, usize) {
+        let width = usize::from(width).saturating_sub(2).max(1);
+        let cursor_style = Style::default().add_modifier(Modifier::REVERSED);
+        let mut rows: Vec<Vec<Span<'static>>> = Vec::new();
+        let mut cursor_row = 0;
+        let mut start = 0;
+        for line in self.text.split('\n') {
+            let mut row = Vec::new();
+            let mut row_width = 0;
+            let mut graphemes: Vec<(usize, &str)> = line
+                .grapheme_indices(true)
+                .map(|(i, g)| (start + i, g))
+                .collect();
+            // a cursor at the end of the line needs a cell of its own
+            if show_cursor && self.cursor == start + line.len() {
+                graphemes.push((self.cursor, " "));
+            }
+            for (offset, g) in graphemes {
+                let g_width = display_width(g);
+                if row_width + g_width > width && row_width > 0 {
+                    rows.push(std::mem::take(&mut row));
+                    row_width = 0;
+                }
+                let shown = if g == "\t" { TAB } else { g }.to_string();
+                if show_cursor && offset == self.cursor {
+                    cursor_row = rows.len();
+                    row.push(Span::styled(shown, cursor_style));
+                } else {
+                    row.push(Span::raw(shown));
+                }
+                row_width += g_width;
+            }
+            rows.push(row);
+            start += line.len() + 1;
+        }
+        let lines = rows
+            .into_iter()
+            .enumerate()
+            .map(|(i, mut row)| {
+                row.insert(0, Span::raw(if i == 0 { "> " } else { "  " }));
+                Line::from(row)
+            })
+            .collect();
+        (lines, cursor_row)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn press(area: &mut TextArea, code: KeyCode, modifiers: KeyModifiers) {
+        area.input(KeyEvent::new(code, modifiers));
+    }
+
+    /// the text with a | where the cursor is
+    fn shown(area: &TextArea) -> String {
+        let mut text = area.text.clone();
+        text.insert(area.cursor, '|');
+        text
+    }
+
+    #[test]
+    fn word_motions_stop_at_words_and_line_breaks() {
+        let mut area = TextArea::default();
+        area.set_text("let x_1 = foo(bar);\nnext");
+        let mut stops = Vec::new();
+        while area.cursor > 0 {
+            press(&mut area, KeyCode::Left, KeyModifiers::CONTROL);
+            stops.push(shown(&area));
+        }
+        assert_eq!(
+            stops,
+            [
+                "let x_1 = foo(bar);\n|next",
+                "let x_1 = foo(bar);|\nnext",
+                "let x_1 = foo(|bar);\nnext",
+                "let x_1 = |foo(bar);\nnext",
+                "let |x_1 = foo(bar);\nnext",
+                "|let x_1 = foo(bar);\nnext",
+            ]
+        );
+        press(&mut area, KeyCode::Char('f'), KeyModifiers::ALT);
+        press(&mut area, KeyCode::Char('f'), KeyModifiers::ALT);
+        assert_eq!(shown(&area), "let x_1| = foo(bar);\nnext");
+        press(&mut area, KeyCode::Char('d'), KeyModifiers::ALT);
+        assert_eq!(shown(&area), "let x_1|(bar);\nnext");
+        press(&mut area, KeyCode::Char('w'), KeyModifiers::CONTROL);
+        assert_eq!(shown(&area), "let |(bar);\nnext");
+        press(&mut area, KeyCode::Char('u'), KeyModifiers::CONTROL);
+        assert_eq!(shown(&area), "|(bar);\nnext");
+    }
+
+    #[test]
+    fn graphemes_are_stepped_over_and_deleted_whole() {
+        let mut area = TextArea::default();
+        area.set_text("a👍🏽é\u{301}b");
+        press(&mut area, KeyCode::Left, KeyModifiers::NONE);
+        press(&mut area, KeyCode::Backspace, KeyModifiers::NONE);
+        assert_eq!(shown(&area), "a👍🏽|b");
+        press(&mut area, KeyCode::Left, KeyModifiers::NONE);
+        assert_eq!(shown(&area), "a|👍🏽b");
+        press(&mut area, KeyCode::Delete, KeyModifiers::NONE);
+        assert_eq!(shown(&area), "a|b");
+        area.insert_str("x\r\ny");
+        assert_eq!(shown(&area), "ax\ny|b");
+    }
+
+    #[test]
+    fn up_and_down_keep_the_column() {
+        let mut area = TextArea::default();
+        area.set_text("long line\nab\n漢字漢字");
+        assert!(area.up());
+        assert_eq!(shown(&area), "long line\nab|\n漢字漢字");
+        assert!(area.up());
+        assert_eq!(shown(&area), "long lin|e\nab\n漢字漢字");
+        assert!(!area.up());
+
+        press(&mut area, KeyCode::Home, KeyModifiers::CONTROL);
+        for _ in 0..4 {
+            press(&mut area, KeyCode::Right, KeyModifiers::NONE);
+        }
+        assert!(area.down());
+        assert_eq!(shown(&area), "long line\nab|\n漢字漢字");
+        assert!(area.down());
+        // the wide characters take two columns each
+        assert_eq!(shown(&area), "long line\nab\n漢字|漢字");
+        assert!(!area.down());
+    }
+}
@@ -0,0 +1,71 @@
+// how things look, read once from ui.json. every field can be left out:
+//
+//   { "theme": "light", "code_theme": "Solarized (dark)", "line_numbers": true,
+//     "mouse": false, "collapse_after": 40 }
+use crate::highlight;
+use crate::theme::{self, ColorDepth};
+use serde::Deserialize;
+use std::fs;
+use std::path::Path;
+use std::sync::OnceLock;
+
+#[derive(Deserialize, Debug, Clone)]
+#[serde(default)]
+pub struct UiConfig {
+    // a built-in theme by name or the path of a theme file
+    pub theme: String,
+    // "auto" goes by the terminal, or "truecolor", "256", "16" or "none"
+    pub colors: ColorDepth,
+    // one of syntect's themes by name, or the path of a .tmTheme file. left out
+    // the theme picks one
+    pub code_theme: Option<String>,
+    pub line_numbers: bool,
+    // off leaves selecting text to the terminal, toggle_mouse flips it for a while
+    pub mouse: bool,
+    // columns, the sidebar's edge can be dragged after that
+    pub sidebar_width: u16,
+    // lines, a longer message starts collapsed. 0 never collapses one
+    pub collapse_after: usize,
+    // what was wrong with the file, the main menu lists it
+    #[serde(skip)]
+    pub problems: Vec<String>,
+}
+
+impl Default for UiConfig {
+    fn default() -> Self {
+        UiConfig {
+            theme: theme::DEFAULT_THEME.to_string(),
+            colors: ColorDepth::Auto,
+            code_theme: None,
+            line_numbers: false,
+            mouse: true,
+            sidebar_width: 30,
+            collapse_after: 60,
+            problems: Vec::new(),
+        }
+    }
+}
+
+impl UiConfig {
+    /// a broken file falls back to the defaults for what's wrong
+    pub fn load(path: &Path) -> Self {
+        let Ok(data) = fs::read_to_string(path) else {
+            return UiConfig::default();
+        };
+        let mut config: UiConfig = serde_json::from_str(&data).unwrap_or_else(|err| UiConfig {
+            problems: vec![format!("{}: {err}", path.display())],
+            ..UiConfig::default()
+        });
+        if let Some(Err(err)) = config.code_theme.as_deref().map(highlight::find_theme) {
+            config.problems.push(format!("code_theme: {err:#}"));
+        }
+        config
+    }
+}
+
+static UI_CONFIG: OnceLock<UiConfig> = OnceLock::new();
+
+/// the settings from ui.json, read the first time they are needed
+pub fn get() -> &'static UiConfig {
+    UI_CONFIG.get_or_init(|| UiConfig::load(Path::new("ui.json")))
+}